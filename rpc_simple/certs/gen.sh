@@ -0,0 +1,30 @@
+#!/bin/sh
+# self-signed CA, a server cert for localhost and 127.0.0.1, and a client cert,
+# for trying TLS locally: RPC_TLS_DIR=certs cargo run --bin subpub
+#
+#   helloworld-server --tls-cert certs/server.pem --tls-key certs/server.key --tls-client-ca certs/ca.pem
+#   helloworld-client --tls-ca certs/ca.pem --tls-cert certs/client.pem --tls-key certs/client.key
+#
+# never use these anywhere else, the keys are not protected.
+set -e
+cd "$(dirname "$0")"
+
+openssl req -x509 -newkey rsa:2048 -nodes -days 365 -subj "/CN=rpc_simple test CA" \
+    -keyout ca.key -out ca.pem \
+    -addext "basicConstraints=critical,CA:TRUE" -addext "keyUsage=critical,keyCertSign,cRLSign"
+
+# name, subject alt names, extended key usage
+issue() {
+    openssl req -newkey rsa:2048 -nodes -subj "/CN=$1" -keyout "$1.rsa" -out "$1.csr"
+    # PKCS#8 whatever the openssl version
+    openssl pkcs8 -topk8 -nocrypt -in "$1.rsa" -out "$1.key"
+    printf "basicConstraints=CA:FALSE\nsubjectAltName=%s\nextendedKeyUsage=%s\n" "$2" "$3" > "$1.ext"
+    openssl x509 -req -in "$1.csr" -CA ca.pem -CAkey ca.key -CAcreateserial -days 365 \
+        -extfile "$1.ext" -out "$1.pem"
+    rm "$1.rsa" "$1.csr" "$1.ext"
+}
+
+issue server "DNS:localhost,IP:127.0.0.1" serverAuth
+issue client "DNS:rpc-simple-client" clientAuth
+rm -f ca.srl
+echo "wrote ca.pem, server.pem/.key and client.pem/.key to $(pwd)"
@@ -0,0 +1,160 @@
+use futures::{
+    future::{self, Ready},
+    prelude::*,
+    stream,
+};
+use service::{
+    codec::{self, Format},
+    envelope::{Direction, OrderStatus, Trade},
+    transport::Address,
+};
+use std::{
+    error::Error,
+    io,
+    time::{Duration, Instant},
+};
+use structopt::StructOpt;
+use tarpc::{
+    client, context,
+    server::{self, Channel},
+};
+
+#[tarpc::service]
+trait Echo {
+    /// Returns the trades as they came.
+    async fn echo(trades: Vec<Trade>) -> Vec<Trade>;
+}
+
+#[derive(Clone)]
+struct EchoServer;
+
+impl Echo for EchoServer {
+    type EchoFut = Ready<Vec<Trade>>;
+
+    fn echo(self, _: context::Context, trades: Vec<Trade>) -> Self::EchoFut {
+        future::ready(trades)
+    }
+}
+
+/// codec-bench command line
+#[derive(Debug, StructOpt)]
+#[structopt(name = "codec-bench", about = "echoes trades over localhost with each codec")]
+struct Opts {
+    /// codecs to compare
+    #[structopt(long, default_value = "json,bincode,msgpack,cbor")]
+    codecs: String,
+
+    /// calls per codec, each for latency and for throughput
+    #[structopt(long, default_value = "2000")]
+    calls: usize,
+
+    /// trades per call
+    #[structopt(long, default_value = "100")]
+    batch: usize,
+
+    /// calls in flight for throughput
+    #[structopt(long, default_value = "32")]
+    concurrency: usize,
+
+    /// where the echo server listens, also `unix:/path` or `mem:name`
+    #[structopt(long, default_value = "tcp:127.0.0.1:0")]
+    address: String,
+}
+
+fn trades(count: usize) -> Vec<Trade> {
+    (0..count).map(|i| Trade {
+        code      : format!("{:06}", i % 3000),
+        exchange  : if i % 2 == 0 { "SZSE".into() } else { "SSE".into() },
+        price     : 10.0 + (i % 500) as f64 / 100.0,
+        volume    : 100 * (1 + i as u64 % 50),
+        direction : if i % 3 == 0 { Direction::Sell } else { Direction::Buy },
+        status    : OrderStatus::Filled,
+    }).collect()
+}
+
+/// what one codec did
+struct Run {
+    format  : Format,
+    bytes   : usize, // one batch encoded
+    p50     : Duration,
+    p99     : Duration,
+    mean    : Duration,
+    per_sec : f64,
+}
+
+async fn run(addr: &Address, format: Format, opts: &Opts, batch: &[Trade]) -> io::Result<Run> {
+    let transport = codec::connect(addr, &[format], None).await?;
+    let client = EchoClient::new(client::Config::default(), transport).spawn()?;
+    let call = |mut client: EchoClient| {
+        let batch = batch.to_vec();
+        async move {
+            let trades = client.echo(context::current(), batch).await?;
+            Ok::<_, io::Error>(trades.len())
+        }
+    };
+
+    // warm up, then one call at a time
+    for _ in 0..opts.calls.min(100) {
+        call(client.clone()).await?;
+    }
+    let mut latencies = Vec::with_capacity(opts.calls);
+    for _ in 0..opts.calls {
+        let start = Instant::now();
+        call(client.clone()).await?;
+        latencies.push(start.elapsed());
+    }
+    latencies.sort_unstable();
+    let mean = latencies.iter().sum::<Duration>() / opts.calls as u32;
+
+    let start = Instant::now();
+    stream::iter(0..opts.calls)
+        .map(|_| call(client.clone()))
+        .buffer_unordered(opts.concurrency)
+        .try_for_each(|_| future::ok(()))
+        .await?;
+    let elapsed = start.elapsed();
+
+    Ok(Run {
+        format,
+        bytes   : format.encode(&batch)?.len(),
+        p50     : latencies[opts.calls / 2],
+        p99     : latencies[opts.calls * 99 / 100],
+        mean,
+        per_sec : opts.calls as f64 / elapsed.as_secs_f64(),
+    })
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let opts = Opts::from_args();
+    let formats = codec::parse_formats(&opts.codecs)?;
+    if opts.calls == 0 || opts.concurrency == 0 {
+        return Err("calls and concurrency must be at least 1".into());
+    }
+
+    let addr: Address = opts.address.parse()?;
+    let incoming = codec::listen(&addr, formats.clone(), None).await?;
+    let addr = incoming.local_addr().clone();
+    tokio::spawn(
+        incoming
+            .filter_map(|r| future::ready(r.map_err(|e| eprintln!("accept failed: {}", e)).ok()))
+            .map(server::BaseChannel::with_defaults)
+            .for_each_concurrent(None, |channel| channel.respond_with(EchoServer.serve()).execute()),
+    );
+
+    let batch = trades(opts.batch);
+    println!("{} calls of {} trades over {}, {} in flight for throughput", opts.calls, opts.batch, addr, opts.concurrency);
+    println!("{:<8} {:>8} {:>10} {:>10} {:>10} {:>10} {:>10}", "codec", "bytes", "mean", "p50", "p99", "calls/s", "MB/s");
+    for format in formats {
+        match run(&addr, format, &opts, &batch).await {
+            Ok(run) => println!(
+                "{:<8} {:>8} {:>10.1?} {:>10.1?} {:>10.1?} {:>10.0} {:>10.1}",
+                run.format.to_string(), run.bytes, run.mean, run.p50, run.p99, run.per_sec,
+                // there and back
+                run.per_sec * 2.0 * run.bytes as f64 / 1e6,
+            ),
+            Err(e) => eprintln!("{}: {}", format, e),
+        }
+    }
+    Ok(())
+}
@@ -1,30 +1,49 @@
-use std::{io, net::SocketAddr};
-use futures::{future::join_all, prelude::*};
+use std::{
+    error::Error,
+    io,
+    time::{Duration, SystemTime},
+};
+use futures::{prelude::*, stream};
+use service::{codec::{self, Format}, config::ClientConfig, tls, transport::Address};
 use tarpc::{client, context};
-use tokio_serde::formats::Json;
 
+/// one hello to `addr`, connecting and answering each have `timeout`
+async fn hello(addr: Address, codecs: Vec<Format>, tls: Option<tls::Connector>, name: String, timeout: Duration) -> io::Result<String> {
+    let transport = tokio::time::timeout(timeout, codec::connect(&addr, &codecs, tls.as_ref()))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "connect timed out"))??;
+    let mut client = service::WorldClient::new(client::Config::default(), transport).spawn()?;
+    let mut ctx = context::current();
+    ctx.deadline = SystemTime::now() + timeout;
+    client.hello(ctx, name).await
+}
 
 #[tokio::main]
-async fn main() -> io::Result<()> {
-    let mut res = vec![];
-    for port in 8000..8008 {
-        let server_addr = format!("127.0.0.1:{}", port);
-        let server_addr = server_addr.parse::<SocketAddr>().unwrap();
-
-        let th = tokio::spawn(async move {
-            let transport = tarpc::serde_transport::tcp::connect(server_addr, Json::default()).await.unwrap();
-            let mut client = service::WorldClient::new(client::Config::default(), transport).spawn().unwrap();
-            let hello = client.hello(context::current(), "wiki".into());
-            hello.await
-        });
-        res.push(th);
-    }
+async fn main() -> Result<(), Box<dyn Error>> {
+    let config = ClientConfig::load()?;
+    let tls = config.tls.as_ref().map(|files| tls::Connector::new(files, &config.host)).transpose()?;
 
-    let out = join_all(res).await;
+    let mut answers: Vec<(Address, io::Result<String>)> = stream::iter(config.endpoints.iter().cloned())
+        .map(|addr| {
+            hello(addr.clone(), config.codecs.of(&addr).to_vec(), tls.clone(), config.name.clone(), config.timeout)
+                .map(move |answer| (addr, answer))
+        })
+        .buffer_unordered(config.concurrency)
+        .collect()
+        .await;
+    answers.sort_by(|(a, _), (b, _)| a.cmp(b));
 
-    for o in out.into_iter() {
-        println!("{:?}", o);
+    // a dead endpoint is reported, the others still answer
+    let mut failed = 0;
+    for (addr, answer) in answers.into_iter() {
+        match answer {
+            Ok(hello) => println!("{}: {}", addr, hello),
+            Err(e)    => { failed += 1; eprintln!("{}: {}", addr, e); },
+        }
     }
 
+    if failed > 0 {
+        return Err(format!("{} of {} endpoints failed", failed, config.endpoints.len()).into());
+    }
     Ok(())
 }
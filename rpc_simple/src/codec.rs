@@ -0,0 +1,281 @@
+use crate::tls;
+use crate::transport::{Address, Conn, Listener};
+
+use bytes::{Bytes, BytesMut};
+use futures::{prelude::*, stream};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt,
+    io,
+    marker::PhantomData,
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_rustls::TlsAcceptor;
+use tokio_serde::{Deserializer, Serializer};
+use tokio_util::codec::{Framed, LengthDelimitedCodec};
+
+/// first bytes of every connection, then the offered formats
+const MAGIC: &[u8; 4] = b"rpcs";
+/// a client that says nothing for this long is dropped, TLS included
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// connections in their handshake at once per listener
+const MAX_HANDSHAKES: usize = 64;
+
+/// how messages are put on the wire
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Format {
+    Json,
+    Bincode,
+    MessagePack,
+    Cbor,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Json, Format::Bincode, Format::MessagePack, Format::Cbor];
+
+    /// on the wire in the handshake, 0 is the refusal
+    fn id(self) -> u8 {
+        match self {
+            Format::Json        => 1,
+            Format::Bincode     => 2,
+            Format::MessagePack => 3,
+            Format::Cbor        => 4,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        Self::ALL.iter().copied().find(|f| f.id() == id)
+    }
+
+    pub fn encode<T: Serialize>(self, item: &T) -> io::Result<Vec<u8>> {
+        match self {
+            Format::Json        => serde_json::to_vec(item).map_err(invalid),
+            Format::Bincode     => bincode::serialize(item).map_err(invalid),
+            Format::MessagePack => rmp_serde::to_vec_named(item).map_err(invalid),
+            Format::Cbor        => serde_cbor::to_vec(item).map_err(invalid),
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8]) -> io::Result<T> {
+        match self {
+            Format::Json        => serde_json::from_slice(bytes).map_err(invalid),
+            Format::Bincode     => bincode::deserialize(bytes).map_err(invalid),
+            Format::MessagePack => rmp_serde::from_read_ref(bytes).map_err(invalid),
+            Format::Cbor        => serde_cbor::from_slice(bytes).map_err(invalid),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Format::Json        => "json",
+            Format::Bincode     => "bincode",
+            Format::MessagePack => "msgpack",
+            Format::Cbor        => "cbor",
+        })
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.trim().to_lowercase().as_str() {
+            "json"                    => Ok(Format::Json),
+            "bincode"                 => Ok(Format::Bincode),
+            "msgpack" | "messagepack" => Ok(Format::MessagePack),
+            "cbor"                    => Ok(Format::Cbor),
+            other                     => Err(format!("unknown codec {:?}, expect json, bincode, msgpack or cbor", other)),
+        }
+    }
+}
+
+/// `json,cbor`, in order of preference
+pub fn parse_formats(spec: &str) -> Result<Vec<Format>, String> {
+    let mut formats: Vec<Format> = Vec::new();
+    for format in spec.split(',').filter(|s| !s.trim().is_empty()) {
+        let format = format.parse()?;
+        if !formats.contains(&format) {
+            formats.push(format);
+        }
+    }
+    if formats.is_empty() {
+        return Err(format!("no codecs in {:?}", spec));
+    }
+    Ok(formats)
+}
+
+fn list(formats: &[Format]) -> String {
+    formats.iter().map(|f| f.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+fn invalid<E: fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// the `Format` agreed on for one connection, as tarpc's serde transport wants it.
+pub struct Codec<Item, SinkItem> {
+    format : Format,
+    ghost  : PhantomData<fn() -> (Item, SinkItem)>,
+}
+
+impl<Item, SinkItem> Codec<Item, SinkItem> {
+    pub fn new(format: Format) -> Self {
+        Self { format, ghost: PhantomData }
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+}
+
+impl<Item, SinkItem: Serialize> Serializer<SinkItem> for Codec<Item, SinkItem> {
+    type Error = io::Error;
+
+    fn serialize(self: Pin<&mut Self>, item: &SinkItem) -> io::Result<Bytes> {
+        self.format.encode(item).map(Bytes::from)
+    }
+}
+
+impl<Item: DeserializeOwned, SinkItem> Deserializer<Item> for Codec<Item, SinkItem> {
+    type Error = io::Error;
+
+    fn deserialize(self: Pin<&mut Self>, src: &BytesMut) -> io::Result<Item> {
+        self.format.decode(src)
+    }
+}
+
+pub type Transport<Item, SinkItem> = tarpc::serde_transport::Transport<Conn, Item, SinkItem, Codec<Item, SinkItem>>;
+
+fn transport<Item, SinkItem>(io: Conn, format: Format) -> Transport<Item, SinkItem>
+where
+    Item: DeserializeOwned,
+    SinkItem: Serialize,
+{
+    tarpc::serde_transport::new(Framed::new(io, LengthDelimitedCodec::new()), Codec::new(format))
+}
+
+/// connects to `addr` with the first of `formats`, by preference, the server also speaks.
+/// With `tls` the handshake and everything after it is encrypted.
+pub async fn connect<Item, SinkItem>(addr: &Address, formats: &[Format], tls: Option<&tls::Connector>) -> io::Result<Transport<Item, SinkItem>>
+where
+    Item: DeserializeOwned,
+    SinkItem: Serialize,
+{
+    let io = Conn::connect(addr).await.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", addr, e)))?;
+    let mut io = match tls {
+        Some(tls) => tls.connect(io).await.map_err(|e| io::Error::new(e.kind(), format!("{}: TLS: {}", addr, e)))?,
+        None      => io,
+    };
+    let format = offer(&mut io, formats).await
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", addr, e)))?;
+    Ok(transport(io, format))
+}
+
+/// client side of the handshake: magic, count and ids, answered by the chosen id or a refusal
+async fn offer<S: AsyncRead + AsyncWrite + Unpin>(io: &mut S, formats: &[Format]) -> io::Result<Format> {
+    let mut hello = MAGIC.to_vec();
+    hello.push(formats.len() as u8);
+    hello.extend(formats.iter().map(|f| f.id()));
+    io.write_all(&hello).await?;
+
+    let mut answer = [0; 1];
+    io.read_exact(&mut answer).await?;
+    if answer[0] != 0 {
+        return Format::from_id(answer[0])
+            .filter(|f| formats.contains(f))
+            .ok_or_else(|| invalid(format!("server picked codec {} that was not offered", answer[0])));
+    }
+    // refused with what the server speaks
+    io.read_exact(&mut answer).await?;
+    let mut ids = vec![0; answer[0] as usize];
+    io.read_exact(&mut ids).await?;
+    let theirs: Vec<Format> = ids.into_iter().filter_map(Format::from_id).collect();
+    Err(invalid(format!("codec mismatch, offered {} but the server speaks {}", list(formats), list(&theirs))))
+}
+
+/// server side of the handshake, the client's preference wins among `formats`
+async fn accept<S: AsyncRead + AsyncWrite + Unpin>(io: &mut S, formats: &[Format]) -> io::Result<Format> {
+    let mut head = [0; 5];
+    io.read_exact(&mut head).await?;
+    if &head[..4] != MAGIC {
+        return Err(invalid("not an rpc_simple client"));
+    }
+    let mut ids = vec![0; head[4] as usize];
+    io.read_exact(&mut ids).await?;
+    let offered: Vec<Format> = ids.into_iter().filter_map(Format::from_id).collect();
+
+    match offered.iter().find(|f| formats.contains(f)) {
+        Some(&format) => {
+            io.write_all(&[format.id()]).await?;
+            Ok(format)
+        },
+        None => {
+            let mut refusal = vec![0, formats.len() as u8];
+            refusal.extend(formats.iter().map(|f| f.id()));
+            io.write_all(&refusal).await?;
+            Err(invalid(format!("codec mismatch, client offered {} but this end speaks {}", list(&offered), list(formats))))
+        },
+    }
+}
+
+/// connections on one address that finished their handshake, a failed one is an error item.
+pub struct Incoming<Item, SinkItem> {
+    local_addr : Address,
+    inner      : Pin<Box<dyn Stream<Item = io::Result<Transport<Item, SinkItem>>> + Send>>,
+}
+
+impl<Item, SinkItem> Incoming<Item, SinkItem> {
+    pub fn local_addr(&self) -> &Address {
+        &self.local_addr
+    }
+}
+
+impl<Item, SinkItem> Stream for Incoming<Item, SinkItem> {
+    type Item = io::Result<Transport<Item, SinkItem>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+/// listens on `addr` for clients speaking one of `formats`, over TLS only if there is an acceptor
+pub async fn listen<Item, SinkItem>(addr: &Address, formats: Vec<Format>, tls: Option<TlsAcceptor>) -> io::Result<Incoming<Item, SinkItem>>
+where
+    Item: DeserializeOwned + Send + 'static,
+    SinkItem: Serialize + Send + 'static,
+{
+    let listener = Listener::bind(addr).await.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", addr, e)))?;
+    let local_addr = listener.local_addr()?;
+    let formats = Arc::new(formats);
+    let inner = stream::unfold(listener, |mut listener| async move {
+            let conn = listener.accept().await;
+            Some((conn, listener))
+        })
+        // a slow handshake does not hold up the next connection
+        .map(move |conn| {
+            let formats = formats.clone();
+            let tls = tls.clone();
+            async move {
+                let io = conn?;
+                let peer = io.peer();
+                let handshake = async {
+                    let mut io = tls::accept(io, tls.as_ref()).await?;
+                    let format = accept(&mut io, &formats).await?;
+                    Ok::<_, io::Error>(transport(io, format))
+                };
+                tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake).await
+                    .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "handshake timed out"))
+                    .and_then(|transport| transport)
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", peer, e)))
+            }
+        })
+        .buffer_unordered(MAX_HANDSHAKES);
+    Ok(Incoming { local_addr, inner: Box::pin(inner) })
+}
@@ -0,0 +1,394 @@
+use crate::codec::{self, Format};
+use crate::journal::Retention;
+use crate::tls;
+use crate::transport::Address;
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::ToSocketAddrs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use structopt::StructOpt;
+
+pub const DEFAULT_CONFIG: &str = "rpc_simple.toml";
+
+/// helloworld-server command line, each option falls back to its environment variable.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "helloworld-server", about = "answers hello on every listed port")]
+pub struct ServerOpts {
+    /// config file [default: rpc_simple.toml if present]
+    #[structopt(short, long, env = "RPC_CONFIG", parse(from_os_str))]
+    pub config: Option<PathBuf>,
+
+    /// host to listen on with the ports, or `unix:/path` [default: 127.0.0.1]
+    #[structopt(short, long, env = "RPC_LISTEN")]
+    pub listen: Option<String>,
+
+    /// ports, e.g. `8000..8008` or `8000,8001,9000..9002` [default: 8000..8008]
+    #[structopt(short, long, env = "RPC_PORTS")]
+    pub ports: Option<String>,
+
+    /// connections served at once per port [default: 10]
+    #[structopt(long, env = "RPC_CONCURRENCY")]
+    pub concurrency: Option<usize>,
+
+    /// connections per client ip and port [default: 1]
+    #[structopt(long, env = "RPC_CHANNELS_PER_IP")]
+    pub channels_per_ip: Option<u32>,
+
+    /// codecs clients may pick, e.g. `json,cbor` [default: json,bincode,msgpack,cbor]
+    #[structopt(long, env = "RPC_CODECS")]
+    pub codecs: Option<String>,
+
+    /// PEM certificate chain, serves TLS only together with --tls-key
+    #[structopt(long, env = "RPC_TLS_CERT", parse(from_os_str))]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key of --tls-cert
+    #[structopt(long, env = "RPC_TLS_KEY", parse(from_os_str))]
+    pub tls_key: Option<PathBuf>,
+
+    /// PEM CA that client certificates must be signed by, makes them required
+    #[structopt(long, env = "RPC_TLS_CLIENT_CA", parse(from_os_str))]
+    pub tls_client_ca: Option<PathBuf>,
+}
+
+/// helloworld-client command line, each option falls back to its environment variable.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "helloworld-client", about = "says hello to every listed port")]
+pub struct ClientOpts {
+    /// config file [default: rpc_simple.toml if present]
+    #[structopt(short, long, env = "RPC_CONFIG", parse(from_os_str))]
+    pub config: Option<PathBuf>,
+
+    /// host to connect to on the ports, or `unix:/path` [default: 127.0.0.1]
+    #[structopt(short = "H", long, env = "RPC_CONNECT")]
+    pub connect: Option<String>,
+
+    /// ports, e.g. `8000..8008` or `8000,8001,9000..9002` [default: 8000..8008]
+    #[structopt(short, long, env = "RPC_PORTS")]
+    pub ports: Option<String>,
+
+    /// requests in flight at once [default: 8]
+    #[structopt(long, env = "RPC_CONCURRENCY")]
+    pub concurrency: Option<usize>,
+
+    /// milliseconds to connect and to get an answer, per endpoint [default: 5000]
+    #[structopt(short, long, env = "RPC_TIMEOUT")]
+    pub timeout: Option<u64>,
+
+    /// who says hello [default: wiki]
+    #[structopt(short, long)]
+    pub name: Option<String>,
+
+    /// codecs offered, most wanted first, e.g. `bincode,json` [default: json]
+    #[structopt(long, env = "RPC_CODECS")]
+    pub codecs: Option<String>,
+
+    /// PEM CA the server certificate must be signed by, connects with TLS
+    #[structopt(long, env = "RPC_TLS_CA", parse(from_os_str))]
+    pub tls_ca: Option<PathBuf>,
+
+    /// PEM client certificate for servers that want one
+    #[structopt(long, env = "RPC_TLS_CERT", parse(from_os_str))]
+    pub tls_cert: Option<PathBuf>,
+
+    /// PEM private key of --tls-cert
+    #[structopt(long, env = "RPC_TLS_KEY", parse(from_os_str))]
+    pub tls_key: Option<PathBuf>,
+
+    /// name the server certificate must carry, required to connect to an ip or socket [default: the host]
+    #[structopt(long, env = "RPC_TLS_SERVER_NAME")]
+    pub tls_server_name: Option<String>,
+}
+
+/// subpub command line, each option falls back to its environment variable.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "subpub", about = "a publisher and its subscribers on every transport, in one process")]
+pub struct PubSubOpts {
+    /// config file [default: rpc_simple.toml if present]
+    #[structopt(short, long, env = "RPC_CONFIG", parse(from_os_str))]
+    pub config: Option<PathBuf>,
+
+    /// file the publisher keeps messages in for replay, across restarts [default: subpub.journal]
+    #[structopt(long, env = "RPC_JOURNAL", parse(from_os_str))]
+    pub journal: Option<PathBuf>,
+
+    /// messages kept for replay, 0 for no limit [default: 10000]
+    #[structopt(long, env = "RPC_RETAIN_MESSAGES")]
+    pub retain_messages: Option<usize>,
+
+    /// seconds a message is kept for replay, 0 for no limit [default: 86400]
+    #[structopt(long, env = "RPC_RETAIN_SECS")]
+    pub retain_secs: Option<u64>,
+}
+
+/// `[server]` of the config file, every key is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerFile {
+    pub listen          : Option<String>,
+    pub ports           : Option<String>,
+    pub concurrency     : Option<usize>,
+    pub channels_per_ip : Option<u32>,
+    pub codecs          : Option<String>,
+    /// codecs of single ports, in place of `codecs`, toml keys are strings
+    pub port_codecs     : HashMap<String, String>,
+    pub tls             : TlsFile,
+}
+
+/// `[client]` of the config file, every key is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClientFile {
+    pub connect     : Option<String>,
+    pub ports       : Option<String>,
+    pub concurrency : Option<usize>,
+    pub timeout     : Option<u64>,
+    pub name        : Option<String>,
+    pub codecs      : Option<String>,
+    /// codecs offered to single ports, in place of `codecs`, toml keys are strings
+    pub port_codecs : HashMap<String, String>,
+    pub tls         : TlsFile,
+}
+
+/// `[pubsub]` of the config file, every key is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PubSubFile {
+    pub journal         : Option<PathBuf>,
+    pub retain_messages : Option<usize>,
+    pub retain_secs     : Option<u64>,
+}
+
+/// `[server.tls]` or `[client.tls]`, paths are relative to where the binary runs.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsFile {
+    /// server: required client certs are signed by this
+    pub client_ca   : Option<PathBuf>,
+    /// client: the server cert is signed by this
+    pub ca          : Option<PathBuf>,
+    pub cert        : Option<PathBuf>,
+    pub key         : Option<PathBuf>,
+    pub server_name : Option<String>,
+}
+
+/// the config file, shared by the binaries.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub server : ServerFile,
+    pub client : ClientFile,
+    pub pubsub : PubSubFile,
+}
+
+impl FileConfig {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("{}: {}", path.as_ref().display(), e))?;
+        let config = toml::from_str(&text)
+            .map_err(|e| format!("{}: {}", path.as_ref().display(), e))?;
+        Ok(config)
+    }
+
+    /// an explicit config must exist, the default one is optional
+    fn find(path: Option<&PathBuf>) -> Result<Self, Box<dyn Error>> {
+        match path {
+            Some(path)                                 => Self::load(path),
+            None if Path::new(DEFAULT_CONFIG).exists() => Self::load(DEFAULT_CONFIG),
+            None                                       => Ok(Self::default()),
+        }
+    }
+}
+
+/// `8000..8008` (end excluded), `8000` or several of them separated by commas
+pub fn parse_ports(spec: &str) -> Result<Vec<u16>, String> {
+    let mut ports = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let port = |s: &str| s.trim().parse::<u16>().map_err(|_| format!("bad port {} in {}", s, spec));
+        match part.find("..") {
+            Some(pos) => {
+                let (start, end) = (port(&part[..pos])?, port(&part[pos + 2..])?);
+                if start >= end {
+                    return Err(format!("empty port range {}", part));
+                }
+                ports.extend(start..end);
+            },
+            None => ports.push(port(part)?),
+        }
+    }
+    if ports.is_empty() {
+        return Err(format!("no ports in {:?}", spec));
+    }
+    ports.sort_unstable();
+    ports.dedup();
+    Ok(ports)
+}
+
+/// `host` (or `tcp:host`) on each port, a host name takes its first address.
+/// `unix:/path` is the one endpoint, the ports do not apply.
+fn endpoints(host: &str, ports: &[u16]) -> Result<Vec<Address>, String> {
+    let host = host.trim();
+    if host.starts_with("unix:") {
+        return Ok(vec![host.parse()?]);
+    }
+    if host.starts_with("mem:") {
+        return Err(format!("{}: mem addresses only reach within one process", host));
+    }
+    let host = host.trim_start_matches("tcp:");
+    ports.iter().map(|&port| {
+        (host, port).to_socket_addrs()
+            .map_err(|e| format!("{}:{}: {}", host, port, e))?
+            .next()
+            .map(Address::Tcp)
+            .ok_or_else(|| format!("{}:{}: no address", host, port))
+    }).collect()
+}
+
+/// `codecs` on every endpoint but those in `ports`
+#[derive(Debug, Clone)]
+pub struct Codecs {
+    pub default : Vec<Format>,
+    pub ports   : HashMap<u16, Vec<Format>>,
+}
+
+impl Codecs {
+    fn parse(default: &str, ports: &HashMap<String, String>) -> Result<Self, String> {
+        let ports = ports.iter()
+            .map(|(port, spec)| {
+                let port = port.trim().parse::<u16>()
+                    .map_err(|_| format!("port_codecs: {:?} is not a port, expect a number from 0 to 65535", port))?;
+                codec::parse_formats(spec).map(|formats| (port, formats)).map_err(|e| format!("port_codecs {}: {}", port, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { default: codec::parse_formats(default)?, ports })
+    }
+
+    pub fn of(&self, addr: &Address) -> &[Format] {
+        addr.port().and_then(|port| self.ports.get(&port)).unwrap_or(&self.default)
+    }
+}
+
+fn positive(name: &str, value: usize) -> Result<usize, String> {
+    if value == 0 { Err(format!("{} must be at least 1", name)) } else { Ok(value) }
+}
+
+/// resolved server settings: command line, then environment, then config file, then defaults.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub endpoints       : Vec<Address>,
+    pub concurrency     : usize,
+    pub channels_per_ip : u32,
+    pub codecs          : Codecs,
+    /// cert and key to serve TLS with, plain TCP when `None`
+    pub tls             : Option<tls::ServerFiles>,
+}
+
+impl ServerConfig {
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        Self::merge(ServerOpts::from_args())
+    }
+
+    pub fn merge(opts: ServerOpts) -> Result<Self, Box<dyn Error>> {
+        let file = FileConfig::find(opts.config.as_ref())?.server;
+        let host = opts.listen.or(file.listen).unwrap_or_else(|| "127.0.0.1".into());
+        let ports = parse_ports(&opts.ports.or(file.ports).unwrap_or_else(|| "8000..8008".into()))?;
+        let channels_per_ip = opts.channels_per_ip.or(file.channels_per_ip).unwrap_or(1);
+        positive("channels_per_ip", channels_per_ip as usize)?;
+        if file.tls.ca.is_some() || file.tls.server_name.is_some() {
+            return Err("[server.tls] takes cert, key and client_ca".into());
+        }
+        let tls = match (opts.tls_cert.or(file.tls.cert), opts.tls_key.or(file.tls.key)) {
+            (Some(cert), Some(key)) => Some(tls::ServerFiles { cert, key, client_ca: opts.tls_client_ca.or(file.tls.client_ca) }),
+            (None, None)            => None,
+            _                       => return Err("TLS needs both a certificate and its key".into()),
+        };
+
+        Ok(Self {
+            endpoints       : endpoints(&host, &ports)?,
+            concurrency     : positive("concurrency", opts.concurrency.or(file.concurrency).unwrap_or(10))?,
+            channels_per_ip,
+            codecs          : Codecs::parse(&opts.codecs.or(file.codecs).unwrap_or_else(|| "json,bincode,msgpack,cbor".into()), &file.port_codecs)?,
+            tls,
+        })
+    }
+}
+
+/// resolved client settings: command line, then environment, then config file, then defaults.
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    /// as given, names the server for TLS
+    pub host        : String,
+    pub endpoints   : Vec<Address>,
+    pub concurrency : usize,
+    pub timeout     : Duration,
+    pub name        : String,
+    pub codecs      : Codecs,
+    /// CA and client cert to connect with TLS, plain TCP when `None`
+    pub tls         : Option<tls::ClientFiles>,
+}
+
+impl ClientConfig {
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        Self::merge(ClientOpts::from_args())
+    }
+
+    pub fn merge(opts: ClientOpts) -> Result<Self, Box<dyn Error>> {
+        let file = FileConfig::find(opts.config.as_ref())?.client;
+        let host = opts.connect.or(file.connect).unwrap_or_else(|| "127.0.0.1".into());
+        let ports = parse_ports(&opts.ports.or(file.ports).unwrap_or_else(|| "8000..8008".into()))?;
+        let timeout = opts.timeout.or(file.timeout).unwrap_or(5000);
+        if timeout == 0 {
+            return Err("timeout must be at least 1ms".into());
+        }
+        if file.tls.client_ca.is_some() {
+            return Err("[client.tls] takes ca, cert, key and server_name".into());
+        }
+        let tls = opts.tls_ca.or(file.tls.ca).map(|ca| tls::ClientFiles {
+            ca,
+            cert        : opts.tls_cert.or(file.tls.cert),
+            key         : opts.tls_key.or(file.tls.key),
+            server_name : opts.tls_server_name.or(file.tls.server_name),
+        });
+
+        Ok(Self {
+            endpoints   : endpoints(&host, &ports)?,
+            host,
+            concurrency : positive("concurrency", opts.concurrency.or(file.concurrency).unwrap_or(8))?,
+            timeout     : Duration::from_millis(timeout),
+            name        : opts.name.or(file.name).unwrap_or_else(|| "wiki".into()),
+            codecs      : Codecs::parse(&opts.codecs.or(file.codecs).unwrap_or_else(|| "json".into()), &file.port_codecs)?,
+            tls,
+        })
+    }
+}
+
+/// resolved subpub settings: command line, then environment, then config file, then defaults.
+#[derive(Debug, Clone)]
+pub struct PubSubConfig {
+    pub journal   : PathBuf,
+    pub retention : Retention,
+}
+
+impl PubSubConfig {
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        Self::merge(PubSubOpts::from_args())
+    }
+
+    pub fn merge(opts: PubSubOpts) -> Result<Self, Box<dyn Error>> {
+        let file = FileConfig::find(opts.config.as_ref())?.pubsub;
+        let default = Retention::default();
+        let max_messages = opts.retain_messages.or(file.retain_messages).or(default.max_messages);
+        let max_age = opts.retain_secs.or(file.retain_secs).map(Duration::from_secs).or(default.max_age);
+
+        Ok(Self {
+            journal   : opts.journal.or(file.journal).unwrap_or_else(|| "subpub.journal".into()),
+            retention : Retention {
+                max_messages : max_messages.filter(|&max| max > 0),
+                max_age      : max_age.filter(|age| *age > Duration::from_secs(0)),
+            },
+        })
+    }
+}
@@ -0,0 +1,305 @@
+use crate::codec::{self, Format};
+use crate::envelope::Envelope;
+use crate::tls;
+use crate::transport::Address;
+use crate::pubsub::subscriber::{self, SubscriberClient};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    prelude::*,
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tarpc::{client, context};
+use tokio::sync::Semaphore;
+
+/// dead letters kept for `dead_letters`, the oldest go first
+const MAX_DEAD_LETTERS: usize = 1000;
+/// `receive` calls at once over all subscribers
+pub const MAX_IN_FLIGHT: usize = 64;
+
+/// how hard a message is pushed to one subscriber before it becomes a dead letter.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// tries in total, the last failure makes a dead letter
+    pub attempts    : u32,
+    /// wait after the first failure, doubled after each further one
+    pub backoff     : Duration,
+    pub max_backoff : Duration,
+    /// for each call and reconnect, `receive` is shorter while the publish deadline is sooner
+    pub timeout     : Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts    : 5,
+            backoff     : Duration::from_millis(100),
+            max_backoff : Duration::from_secs(5),
+            timeout     : Duration::from_secs(2),
+        }
+    }
+}
+
+/// when a subscriber counts as dead.
+#[derive(Clone, Copy, Debug)]
+pub struct Liveness {
+    /// an idle subscriber is pinged this often
+    pub heartbeat   : Duration,
+    /// failed calls in a row, pings included, before the subscriber is evicted
+    pub evict_after : u32,
+}
+
+impl Default for Liveness {
+    fn default() -> Self {
+        Self { heartbeat: Duration::from_secs(5), evict_after: 10 }
+    }
+}
+
+/// a message that one subscriber never acknowledged.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub subscriber : u32,
+    pub envelope   : Envelope,
+    pub attempts   : u32,
+    pub error      : String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SubscriberState {
+    /// answered its last call
+    Live,
+    /// its last call failed, the connection was made again
+    Failing,
+    /// its last call failed and connecting again did too
+    Reconnecting,
+    /// failed too often, gets nothing until it subscribes again
+    Evicted,
+}
+
+/// what the queue task knows about its subscriber.
+#[derive(Clone, Debug)]
+pub struct Health {
+    pub state          : SubscriberState,
+    pub last_seen      : Option<SystemTime>,
+    /// failed calls in a row
+    pub failures       : u32,
+    pub total_failures : u64,
+    /// messages waiting, the one being sent included
+    pub queued         : usize,
+}
+
+/// settings and state all subscriber queues of a publisher share.
+pub struct Delivery {
+    pub policy   : RetryPolicy,
+    pub liveness : Liveness,
+    /// offered to subscribers, most wanted first
+    pub codecs   : Vec<Format>,
+    /// connects to subscribers over TLS, in the clear when `None`
+    pub tls      : Option<tls::Connector>,
+    in_flight    : Semaphore,
+    dead         : Mutex<Vec<DeadLetter>>,
+}
+
+impl Default for Delivery {
+    fn default() -> Self {
+        Self::new(RetryPolicy::default(), Liveness::default(), MAX_IN_FLIGHT)
+    }
+}
+
+impl Delivery {
+    /// at most `max_in_flight` subscribers are waited on at once, the rest queue up
+    pub fn new(policy: RetryPolicy, liveness: Liveness, max_in_flight: usize) -> Self {
+        Self {
+            policy,
+            liveness,
+            codecs    : vec![Format::Json],
+            tls       : None,
+            in_flight : Semaphore::new(max_in_flight.max(1)),
+            dead      : Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead.lock().unwrap().clone()
+    }
+
+    fn bury(&self, subscriber: u32, pending: Pending, attempts: u32, error: String) {
+        warn!("Subscriber {} gave up on message {} after {} attempts: {}", subscriber, pending.envelope.seq, attempts, error);
+        let mut dead = self.dead.lock().unwrap();
+        if dead.len() >= MAX_DEAD_LETTERS {
+            dead.remove(0);
+        }
+        dead.push(DeadLetter { subscriber, envelope: pending.envelope, attempts, error });
+        let _ = pending.acked.send(false);
+    }
+}
+
+pub async fn connect(addr: &Address, codecs: &[Format], tls: Option<&tls::Connector>) -> io::Result<SubscriberClient> {
+    let conn = codec::connect(addr, codecs, tls).await?;
+    subscriber::SubscriberClient::new(client::Config::default(), conn).spawn()
+}
+
+/// a message waiting in a subscriber queue, `acked` tells the publish how it ended
+struct Pending {
+    envelope : Envelope,
+    deadline : SystemTime, // of the publish
+    acked    : oneshot::Sender<bool>,
+}
+
+/// the queue of one subscriber. Messages go out one at a time in order, each retried
+/// until acknowledged or given up; dropping the queue lets it drain and stop.
+pub struct Queue {
+    tx     : mpsc::UnboundedSender<Pending>,
+    health : Arc<Mutex<Health>>,
+}
+
+impl Queue {
+    /// spawns the task delivering to `client`, connected to `address`
+    pub fn spawn(id: u32, address: Address, client: SubscriberClient, delivery: Arc<Delivery>) -> Self {
+        let (tx, rx) = mpsc::unbounded();
+        let health = Arc::new(Mutex::new(Health {
+            state          : SubscriberState::Live,
+            last_seen      : Some(SystemTime::now()),
+            failures       : 0,
+            total_failures : 0,
+            queued         : 0,
+        }));
+        let link = Link { id, address, client, delivery, health: health.clone() };
+        tokio::spawn(link.run(rx));
+        Self { tx, health }
+    }
+
+    /// resolves to true once the subscriber acknowledged, false if it became a dead letter
+    pub fn push(&self, envelope: Envelope, deadline: SystemTime) -> oneshot::Receiver<bool> {
+        let (acked, receipt) = oneshot::channel();
+        // counted first, the task may be done with it before `unbounded_send` returns.
+        // A stopped task drops `acked`, the receipt then reads as not delivered.
+        self.health.lock().unwrap().queued += 1;
+        if self.tx.unbounded_send(Pending { envelope, deadline, acked }).is_err() {
+            let mut health = self.health.lock().unwrap();
+            health.queued = health.queued.saturating_sub(1);
+        }
+        receipt
+    }
+
+    pub fn health(&self) -> Health {
+        self.health.lock().unwrap().clone()
+    }
+
+    pub fn is_evicted(&self) -> bool {
+        self.health.lock().unwrap().state == SubscriberState::Evicted
+    }
+}
+
+/// the queue task's end of one subscriber
+struct Link {
+    id       : u32,
+    address  : Address,
+    client   : SubscriberClient,
+    delivery : Arc<Delivery>,
+    health   : Arc<Mutex<Health>>,
+}
+
+impl Link {
+    async fn run(mut self, mut queue: mpsc::UnboundedReceiver<Pending>) {
+        while !self.is_evicted() {
+            match tokio::time::timeout(self.delivery.liveness.heartbeat, queue.next()).await {
+                Ok(Some(pending)) => {
+                    self.send(pending).await;
+                    let mut health = self.health.lock().unwrap();
+                    health.queued = health.queued.saturating_sub(1);
+                },
+                // unsubscribed and drained
+                Ok(None)          => return,
+                Err(_)            => self.ping().await,
+            }
+        }
+
+        // nobody takes what is left, new messages are refused
+        queue.close();
+        while let Some(pending) = queue.next().await {
+            self.delivery.bury(self.id, pending, 0, "subscriber evicted".into());
+        }
+        self.health.lock().unwrap().queued = 0;
+    }
+
+    /// one message until it is acknowledged or buried
+    async fn send(&mut self, pending: Pending) {
+        let policy = self.delivery.policy;
+        let mut backoff = policy.backoff;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let permit = self.delivery.in_flight.acquire().await;
+            // a hung subscriber holds its permit no longer than this
+            let mut ctx = context::current();
+            ctx.deadline = SystemTime::now() + policy.timeout;
+            if pending.deadline > SystemTime::now() {
+                ctx.deadline = ctx.deadline.min(pending.deadline);
+            }
+            let result = self.client.receive(ctx, pending.envelope.clone()).await;
+            drop(permit);
+            let error = match result {
+                Ok(Ok(()))   => { self.seen(); let _ = pending.acked.send(true); return; },
+                // refused, but alive
+                Ok(Err(e))   => { self.seen(); e },
+                Err(e)       => { self.failed(&e).await; e.to_string() },
+            };
+
+            if attempts >= policy.attempts || self.is_evicted() {
+                return self.delivery.bury(self.id, pending, attempts, error);
+            }
+            warn!("Subscriber {} failed message {}: {}, retry in {:?}", self.id, pending.envelope.seq, error, backoff);
+            tokio::time::delay_for(backoff).await;
+            backoff = (backoff * 2).min(policy.max_backoff);
+        }
+    }
+
+    async fn ping(&mut self) {
+        let mut ctx = context::current();
+        ctx.deadline = SystemTime::now() + self.delivery.policy.timeout;
+        match self.client.ping(ctx).await {
+            Ok(()) => self.seen(),
+            Err(e) => self.failed(&e).await,
+        }
+    }
+
+    fn seen(&self) {
+        let mut health = self.health.lock().unwrap();
+        health.state = SubscriberState::Live;
+        health.last_seen = Some(SystemTime::now());
+        health.failures = 0;
+    }
+
+    /// counts the failure, then evicts or connects again as the old connection may be gone
+    async fn failed(&mut self, error: &io::Error) {
+        let failures = {
+            let mut health = self.health.lock().unwrap();
+            health.failures += 1;
+            health.total_failures += 1;
+            health.failures
+        };
+        if failures >= self.delivery.liveness.evict_after {
+            warn!("Evicting subscriber {} after {} failures: {}", self.id, failures, error);
+            self.health.lock().unwrap().state = SubscriberState::Evicted;
+            return;
+        }
+
+        let state = match tokio::time::timeout(self.delivery.policy.timeout, connect(&self.address, &self.delivery.codecs, self.delivery.tls.as_ref())).await {
+            Ok(Ok(client)) => { self.client = client; SubscriberState::Failing },
+            Ok(Err(e))     => { warn!("Subscriber {} at {}: {}", self.id, self.address, e); SubscriberState::Reconnecting },
+            Err(_)         => { warn!("Subscriber {} at {}: connect timed out", self.id, self.address); SubscriberState::Reconnecting },
+        };
+        self.health.lock().unwrap().state = state;
+    }
+
+    fn is_evicted(&self) -> bool {
+        self.health.lock().unwrap().state == SubscriberState::Evicted
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// a message as it travels and is kept: where it went, when, and a body of `content_type`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Envelope {
+    pub topic        : String,
+    pub seq          : u64,
+    pub at           : SystemTime,
+    pub content_type : String,
+    pub body         : Vec<u8>,
+}
+
+impl Envelope {
+    /// the body as a `T`, if it was published as one
+    pub fn decode<T: Payload>(&self) -> Result<T, String> {
+        if self.content_type != content_type::<T>() {
+            return Err(format!("message {} is {}, not {}", self.seq, self.content_type, content_type::<T>()));
+        }
+        serde_json::from_slice(&self.body).map_err(|e| format!("message {}: {}", self.seq, e))
+    }
+
+    pub fn is<T: Payload>(&self) -> bool {
+        self.content_type == content_type::<T>()
+    }
+}
+
+/// a type publishers and subscribers share, its bodies are json
+pub trait Payload: Serialize + DeserializeOwned {
+    /// names the type in the content type, keep it when the type changes compatibly
+    const NAME: &'static str;
+}
+
+pub fn content_type<T: Payload>() -> String {
+    format!("application/json; type={}", T::NAME)
+}
+
+/// content type and body of `value`, what `publish` takes
+pub fn encode<T: Payload>(value: &T) -> Result<(String, Vec<u8>), String> {
+    let body = serde_json::to_vec(value).map_err(|e| format!("{}: {}", T::NAME, e))?;
+    Ok((content_type::<T>(), body))
+}
+
+impl Payload for String {
+    const NAME: &'static str = "text";
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Direction {
+    Buy,
+    Sell,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum OrderStatus {
+    Pending,
+    Filled,
+    Cancel,
+    Error,
+}
+
+/// an order or trade, one row of the trades table in tui_simple
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trade {
+    pub code      : String,
+    pub exchange  : String,
+    pub price     : f64,
+    pub volume    : u64,
+    pub direction : Direction,
+    pub status    : OrderStatus,
+}
+
+impl Payload for Trade {
+    const NAME: &'static str = "trade";
+}
@@ -0,0 +1,252 @@
+use crate::envelope::Envelope;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// dropped entries before the file is rewritten without them, at least
+const COMPACT_MIN: usize = 1000;
+
+/// where a new subscriber starts
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum StartFrom {
+    /// only messages published from now on
+    Live,
+    /// kept messages with this sequence number or later first
+    Seq(u64),
+    /// kept messages published at this time or later first
+    Time(SystemTime),
+}
+
+impl Default for StartFrom {
+    fn default() -> Self {
+        StartFrom::Live
+    }
+}
+
+/// how much of the past is kept for replay, `None` for no limit
+#[derive(Clone, Copy, Debug)]
+pub struct Retention {
+    pub max_messages : Option<usize>,
+    pub max_age      : Option<Duration>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self { max_messages: Some(10_000), max_age: Some(Duration::from_secs(24 * 60 * 60)) }
+    }
+}
+
+/// messages in publish order with their sequence numbers, appended to a file if there is one,
+/// an envelope a json line, see `Line`.
+pub struct Journal {
+    entries   : VecDeque<Envelope>,
+    next_seq  : u64,
+    retention : Retention,
+    file      : Option<(PathBuf, BufWriter<File>)>,
+    dropped   : usize, // still in the file
+}
+
+/// an envelope as a journal line. A json body is written as it is and any other as base64,
+/// serde writes bytes as an array of numbers, three to four times the size of the body.
+#[derive(Serialize, Deserialize)]
+struct Line {
+    topic        : String,
+    seq          : u64,
+    at           : SystemTime,
+    content_type : String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    json         : Option<Box<RawValue>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    base64       : Option<String>,
+    /// journals from before, the body as an array of bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body         : Option<Vec<u8>>,
+}
+
+impl Line {
+    fn new(entry: &Envelope) -> Self {
+        // only if it reads back byte for byte, and a newline would end the journal line
+        let json = std::str::from_utf8(&entry.body).ok()
+            .filter(|text| !text.contains('\n'))
+            .and_then(|text| RawValue::from_string(text.to_string()).ok())
+            .filter(|raw| raw.get().as_bytes() == entry.body.as_slice());
+        let base64 = match json {
+            Some(_) => None,
+            None    => Some(base64::encode(&entry.body)),
+        };
+        Line {
+            topic        : entry.topic.clone(),
+            seq          : entry.seq,
+            at           : entry.at,
+            content_type : entry.content_type.clone(),
+            json,
+            base64,
+            body         : None,
+        }
+    }
+
+    fn into_envelope(self) -> Result<Envelope, String> {
+        let body = match (self.json, self.base64, self.body) {
+            (Some(json), _, _) => json.get().as_bytes().to_vec(),
+            (_, Some(text), _) => base64::decode(&text).map_err(|e| format!("bad base64 body: {}", e))?,
+            (_, _, Some(body)) => body,
+            (None, None, None) => Vec::new(),
+        };
+        Ok(Envelope { topic: self.topic, seq: self.seq, at: self.at, content_type: self.content_type, body })
+    }
+}
+
+fn write_line<W: Write>(out: &mut W, entry: &Envelope) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, &Line::new(entry))?;
+    out.write_all(b"\n")
+}
+
+impl Journal {
+    /// nothing survives a restart
+    pub fn memory(retention: Retention) -> Self {
+        Self { entries: VecDeque::new(), next_seq: 0, retention, file: None, dropped: 0 }
+    }
+
+    /// reads what `path` kept and appends to it, sequence numbers go on where it ended.
+    /// A line that does not parse is logged and skipped, the entries after it are kept. Only a
+    /// last line cut short when the publisher died is cut off.
+    pub fn open<P: AsRef<Path>>(path: P, retention: Retention) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let mut slf = Self::memory(retention);
+        let mut good = 0; // bytes up to the end of the last whole line
+        let mut newline = true; // the last whole line ends with one
+        if path.exists() {
+            let mut reader = BufReader::new(File::open(&path)?);
+            let mut line = Vec::new();
+            for idx in 1.. {
+                line.clear();
+                let read = reader.read_until(b'\n', &mut line)?;
+                if read == 0 { break; }
+                let last = !line.ends_with(b"\n");
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    good += read as u64;
+                    continue;
+                }
+                match serde_json::from_slice::<Line>(&line).map_err(|e| e.to_string()).and_then(Line::into_envelope) {
+                    Ok(entry) => {
+                        slf.next_seq = entry.seq + 1;
+                        // an empty topic only marks the last sequence number, see `compact`
+                        if !entry.topic.is_empty() {
+                            slf.entries.push_back(entry);
+                        }
+                        good += read as u64;
+                        newline = !last;
+                    },
+                    Err(e) if last => warn!("{}:{}: {}, cutting off the torn last line", path.display(), idx, e),
+                    Err(e)         => {
+                        warn!("{}:{}: {}, skipping the line", path.display(), idx, e);
+                        good += read as u64;
+                    },
+                }
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        // the next entry would be appended onto the torn line and be lost with it
+        if file.metadata()?.len() > good {
+            file.set_len(good)?;
+        }
+        let mut out = BufWriter::new(file);
+        if !newline {
+            out.write_all(b"\n")?;
+            out.flush()?;
+        }
+        slf.file = Some((path, out));
+        slf.expire();
+        slf.compact()?;
+        Ok(slf)
+    }
+
+    /// seals `body` in an envelope with the next sequence number
+    pub fn append(&mut self, topic: &str, content_type: &str, body: Vec<u8>) -> io::Result<Envelope> {
+        let entry = Envelope {
+            topic        : topic.to_string(),
+            seq          : self.next_seq,
+            at           : SystemTime::now(),
+            content_type : content_type.to_string(),
+            body,
+        };
+        if let Some((_, ref mut out)) = self.file {
+            write_line(out, &entry)?;
+            out.flush()?;
+        }
+        self.next_seq += 1;
+        self.entries.push_back(entry.clone());
+        self.expire();
+        if self.dropped >= self.entries.len().max(COMPACT_MIN) {
+            self.compact()?;
+        }
+        Ok(entry)
+    }
+
+    /// kept entries from `from` on, oldest first
+    pub fn since(&mut self, from: StartFrom) -> Vec<Envelope> {
+        self.expire();
+        let take = |entry: &&Envelope| match from {
+            StartFrom::Live       => false,
+            StartFrom::Seq(seq)   => entry.seq >= seq,
+            StartFrom::Time(time) => entry.at >= time,
+        };
+        self.entries.iter().filter(take).cloned().collect()
+    }
+
+    fn expire(&mut self) {
+        let before = self.entries.len();
+        if let Some(max) = self.retention.max_messages {
+            while self.entries.len() > max {
+                self.entries.pop_front();
+            }
+        }
+        if let Some(age) = self.retention.max_age {
+            let now = SystemTime::now();
+            while self.entries.front().map_or(false, |e| now.duration_since(e.at).unwrap_or_default() > age) {
+                self.entries.pop_front();
+            }
+        }
+        self.dropped += before - self.entries.len();
+    }
+
+    /// rewrites the file with the kept entries only
+    fn compact(&mut self) -> io::Result<()> {
+        let path = match self.file {
+            Some((ref path, _)) if self.dropped > 0 => path.clone(),
+            _                                       => return Ok(()),
+        };
+        let tmp = path.with_extension("tmp");
+        {
+            let mut out = BufWriter::new(File::create(&tmp)?);
+            for entry in self.entries.iter() {
+                write_line(&mut out, entry)?;
+            }
+            // with nothing kept the sequence would start over after a restart
+            if self.entries.is_empty() && self.next_seq > 0 {
+                let last = Envelope {
+                    topic        : String::new(),
+                    seq          : self.next_seq - 1,
+                    at           : SystemTime::UNIX_EPOCH,
+                    content_type : String::new(),
+                    body         : Vec::new(),
+                };
+                write_line(&mut out, &last)?;
+            }
+            out.flush()?;
+        }
+        fs::rename(&tmp, &path)?;
+        let file = OpenOptions::new().append(true).open(&path)?;
+        self.file = Some((path, BufWriter::new(file)));
+        self.dropped = 0;
+        Ok(())
+    }
+}
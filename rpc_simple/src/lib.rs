@@ -1,3 +1,13 @@
+pub mod codec;
+pub mod config;
+pub mod delivery;
+pub mod envelope;
+pub mod journal;
+pub mod pubsub;
+pub mod tls;
+pub mod topic;
+pub mod transport;
+
 #[tarpc::service]
 pub trait World {
     /// Returns a greeting for name.
@@ -0,0 +1,290 @@
+use crate::delivery::{self, DeadLetter, Delivery, Queue, SubscriberState};
+use crate::envelope::Envelope;
+use crate::journal::{Journal, Retention, StartFrom};
+use crate::topic::{self, Pattern};
+use crate::transport::Address;
+
+use futures::{
+    future::{self, Ready},
+    prelude::*,
+    Future,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+use tarpc::context;
+
+/// left of the publish deadline to get the answer back to the caller
+const REPLY_MARGIN: Duration = Duration::from_millis(100);
+
+pub mod subscriber {
+    use crate::envelope::Envelope;
+
+    #[tarpc::service]
+    pub trait Subscriber {
+        /// Ok acknowledges the message, an error has it sent again later.
+        /// `Envelope::decode` gives back what was published.
+        async fn receive(envelope: Envelope) -> Result<(), String>;
+        /// Heartbeat while no messages go out.
+        async fn ping();
+    }
+}
+
+pub mod publisher {
+    use super::{PublishReport, SubscriberInfo, TopicInfo};
+    use crate::delivery::DeadLetter;
+    use crate::journal::StartFrom;
+    use crate::transport::Address;
+
+    #[tarpc::service]
+    pub trait Publisher {
+        /// Sends `body` to subscribers with a pattern matching `topic`, all at once, see
+        /// `envelope::encode` for typed messages. Returns who acknowledged it before the
+        /// deadline; the late ones keep getting it until they do or it becomes a dead letter.
+        async fn publish(topic: String, content_type: String, body: Vec<u8>) -> Result<PublishReport, String>;
+        /// Replaces the patterns of a subscriber with the same id, see `topic::Pattern`.
+        /// Kept messages from `from` on go out before any new one. The publisher connects
+        /// to `address`, a `mem:` one only from within the same process.
+        async fn subscribe(id: u32, address: Address, topics: Vec<String>, from: StartFrom) -> Result<(), String>;
+        async fn unsubscribe(id: u32);
+        /// Messages given up on, oldest first.
+        async fn dead_letters() -> Vec<DeadLetter>;
+        /// Topics published so far, by name.
+        async fn list_topics() -> Vec<TopicInfo>;
+        /// Subscribers by id, evicted ones stay until they subscribe again or unsubscribe.
+        async fn list_subscribers() -> Vec<SubscriberInfo>;
+    }
+}
+
+/// how a publish went by its deadline
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PublishReport {
+    pub seq    : u64,
+    /// subscribers that acknowledged
+    pub acked  : Vec<u32>,
+    /// slow or hung, still being retried
+    pub late   : Vec<u32>,
+    /// gave up on or gone, see `dead_letters`
+    pub failed : Vec<u32>,
+}
+
+/// one topic as told by `list_topics`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TopicInfo {
+    pub topic       : String,
+    pub messages    : u64,
+    /// subscribers whose patterns match now
+    pub subscribers : Vec<u32>,
+}
+
+/// one subscriber as told by `list_subscribers`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SubscriberInfo {
+    pub id             : u32,
+    pub address        : Address,
+    pub topics         : Vec<String>,
+    pub state          : SubscriberState,
+    pub last_seen      : Option<SystemTime>,
+    /// failed calls in a row
+    pub failures       : u32,
+    pub total_failures : u64,
+    pub queued         : usize,
+}
+
+struct Subscription {
+    address  : Address,
+    patterns : Vec<Pattern>,
+    queue    : Queue,
+}
+
+impl Subscription {
+    /// evicted subscribers want nothing
+    fn wants(&self, topic: &str) -> bool {
+        self.patterns.iter().any(|p| p.matches(topic)) && !self.queue.is_evicted()
+    }
+}
+
+/// delivers every message at least once to each subscriber of its topic, through a queue per subscriber.
+#[derive(Clone)]
+pub struct Publisher {
+    subscriptions : Arc<Mutex<HashMap<u32, Subscription>>>,
+    topics        : Arc<Mutex<BTreeMap<String, u64>>>, // messages per topic
+    journal       : Arc<Mutex<Journal>>,
+    delivery      : Arc<Delivery>,
+}
+
+impl Publisher {
+    pub fn new() -> Publisher {
+        Self::with_delivery(Delivery::default())
+    }
+
+    /// retries, liveness and limits of every subscriber queue
+    pub fn with_delivery(delivery: Delivery) -> Publisher {
+        Self::with_journal(delivery, Journal::memory(Retention::default()))
+    }
+
+    /// messages kept in `journal` for subscribers that start from the past
+    pub fn with_journal(delivery: Delivery, journal: Journal) -> Publisher {
+        Publisher {
+            subscriptions : Arc::new(Mutex::new(HashMap::new())),
+            topics        : Arc::new(Mutex::new(BTreeMap::new())),
+            journal       : Arc::new(Mutex::new(journal)),
+            delivery      : Arc::new(delivery),
+        }
+    }
+}
+
+impl Default for Publisher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl publisher::Publisher for Publisher {
+    type PublishFut = Pin<Box<dyn Future<Output = Result<PublishReport, String>> + Send>>;
+
+    fn publish(self, ctx: context::Context, topic: String, content_type: String, body: Vec<u8>) -> Self::PublishFut {
+        if let Err(e) = topic::check_topic(&topic) {
+            return future::err(e).boxed();
+        }
+        if content_type.is_empty() {
+            return future::err("publish with a content type".to_string()).boxed();
+        }
+        let deadline = ctx.deadline.checked_sub(REPLY_MARGIN).unwrap_or(ctx.deadline);
+        let (seq, receipts) = {
+            // held while queueing, so a subscriber replaying the journal sees each message once
+            let mut journal = self.journal.lock().unwrap();
+            let envelope: Envelope = match journal.append(&topic, &content_type, body) {
+                Ok(envelope) => envelope,
+                Err(e)       => return future::err(format!("journal: {}", e)).boxed(),
+            };
+            let receipts: Vec<_> = self.subscriptions.lock().unwrap()
+                .iter()
+                .filter(|(_, sub)| sub.wants(&topic))
+                .map(|(&id, sub)| (id, sub.queue.push(envelope.clone(), deadline)))
+                .collect();
+            (envelope.seq, receipts)
+        };
+        *self.topics.lock().unwrap().entry(topic.clone()).or_insert(0) += 1;
+
+        // every queue works on its own, waiting here does not hold up the others
+        let left = deadline.duration_since(SystemTime::now()).unwrap_or_default();
+        let receipts = receipts.into_iter().map(move |(id, receipt)| {
+            tokio::time::timeout(left, receipt).map(move |r| (id, r))
+        });
+        future::join_all(receipts)
+            .map(move |receipts| {
+                let mut report = PublishReport { seq, ..Default::default() };
+                for (id, receipt) in receipts {
+                    match receipt {
+                        Ok(Ok(true)) => report.acked.push(id),
+                        Ok(_)        => report.failed.push(id),
+                        Err(_)       => {
+                            warn!("Subscriber {} has not acked message {} on {} by the deadline", id, seq, topic);
+                            report.late.push(id);
+                        },
+                    }
+                }
+                report.acked.sort_unstable();
+                report.late.sort_unstable();
+                report.failed.sort_unstable();
+                Ok(report)
+            })
+            .boxed()
+    }
+
+    type SubscribeFut = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+    fn subscribe(self, _: context::Context, id: u32, addr: Address, topics: Vec<String>, from: StartFrom) -> Self::SubscribeFut {
+        async fn subscribe(publisher: Publisher, id: u32, addr: Address, patterns: Vec<Pattern>, from: StartFrom) -> io::Result<()> {
+            let subscriber = delivery::connect(&addr, &publisher.delivery.codecs, publisher.delivery.tls.as_ref()).await?;
+            info!("Subscribing {} to {}.", id, patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", "));
+            let queue = Queue::spawn(id, addr.clone(), subscriber, publisher.delivery.clone());
+
+            // nothing is published between the replay and going live
+            let mut journal = publisher.journal.lock().unwrap();
+            let replay: Vec<_> = journal.since(from).into_iter()
+                .filter(|envelope| patterns.iter().any(|p| p.matches(&envelope.topic)))
+                .collect();
+            if !replay.is_empty() {
+                info!("Replaying {} messages to {}.", replay.len(), id);
+            }
+            for envelope in replay {
+                // nobody waits for these, they are retried like any other
+                let _ = queue.push(envelope, SystemTime::now());
+            }
+            publisher.subscriptions.lock().unwrap().insert(id, Subscription { address: addr, patterns, queue });
+            Ok(())
+        }
+
+        let patterns = match topics.iter().map(|t| Pattern::parse(t)).collect::<Result<Vec<_>, _>>() {
+            Ok(patterns) if patterns.is_empty() => return future::err("subscribe to at least one topic".to_string()).boxed(),
+            Ok(patterns)                        => patterns,
+            Err(e)                              => return future::err(e).boxed(),
+        };
+        subscribe(self, id, addr, patterns, from)
+            .map_err(|e| e.to_string())
+            .boxed()
+    }
+
+    type UnsubscribeFut = Ready<()>;
+
+    fn unsubscribe(self, _: context::Context, id: u32) -> Self::UnsubscribeFut {
+        info!("Unsubscribing {}", id);
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        // what is queued still goes out, then the queue stops
+        if subscriptions.remove(&id).is_none() {
+            let mut ids: Vec<&u32> = subscriptions.keys().collect();
+            ids.sort();
+            warn!("Client {} not found. Existings clients: {:?}", id, ids);
+        }
+        future::ready(())
+    }
+
+    type DeadLettersFut = Ready<Vec<DeadLetter>>;
+
+    fn dead_letters(self, _: context::Context) -> Self::DeadLettersFut {
+        future::ready(self.delivery.dead_letters())
+    }
+
+    type ListTopicsFut = Ready<Vec<TopicInfo>>;
+
+    fn list_topics(self, _: context::Context) -> Self::ListTopicsFut {
+        let subscriptions = self.subscriptions.lock().unwrap();
+        let topics = self.topics.lock().unwrap().iter().map(|(topic, &messages)| {
+            let mut subscribers: Vec<u32> = subscriptions.iter()
+                .filter(|(_, sub)| sub.wants(topic))
+                .map(|(&id, _)| id)
+                .collect();
+            subscribers.sort_unstable();
+            TopicInfo { topic: topic.clone(), messages, subscribers }
+        }).collect();
+        future::ready(topics)
+    }
+
+    type ListSubscribersFut = Ready<Vec<SubscriberInfo>>;
+
+    fn list_subscribers(self, _: context::Context) -> Self::ListSubscribersFut {
+        let mut subscribers: Vec<SubscriberInfo> = self.subscriptions.lock().unwrap().iter().map(|(&id, sub)| {
+            let health = sub.queue.health();
+            SubscriberInfo {
+                id,
+                address        : sub.address.clone(),
+                topics         : sub.patterns.iter().map(|p| p.to_string()).collect(),
+                state          : health.state,
+                last_seen      : health.last_seen,
+                failures       : health.failures,
+                total_failures : health.total_failures,
+                queued         : health.queued,
+            }
+        }).collect();
+        subscribers.sort_by_key(|s| s.id);
+        future::ready(subscribers)
+    }
+}
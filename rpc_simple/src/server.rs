@@ -1,21 +1,23 @@
 use futures::{
     future::{self, Ready},
     prelude::*,
+    stream::FuturesUnordered,
 };
-use service::World;
+use service::{codec, config::ServerConfig, tls, transport::Address, World};
 use std::{
+    error::Error,
     io,
-    net::{IpAddr, SocketAddr},
+    net::{IpAddr, Ipv4Addr},
 };
 use tarpc::{
     context,
     server::{self, Channel, Handler},
 };
-use tokio_serde::formats::Json;
+use tokio_rustls::TlsAcceptor;
 
 
 #[derive(Clone)]
-pub struct HelloServer(SocketAddr);
+pub struct HelloServer(String);
 
 impl World for HelloServer {
     // Each defined rpc generates two items in the trait, a fn that serves the RPC, and
@@ -25,48 +27,65 @@ impl World for HelloServer {
 
     fn hello(self, _: context::Context, name: String) -> Self::HelloFut {
         future::ready(format!(
-            "Hello, {}! You are connected from {:?}.",
+            "Hello, {}! You are connected from {}.",
             name, self.0
         ))
     }
 }
 
 
+/// answers on `addr` until the process stops, only binding fails
+async fn serve(addr: Address, config: ServerConfig, tls: Option<TlsAcceptor>) -> io::Result<()> {
+    let codecs = config.codecs.of(&addr).to_vec();
+    println!("{}: listening{}, speaks {:?}", addr, if tls.is_some() { " with TLS" } else { "" }, codecs);
+    let listener = codec::listen(&addr, codecs, tls).await?;
+    listener
+        // a failed accept or handshake only loses that connection
+        .filter_map(|r| future::ready(r.map_err(|e| eprintln!("{}: accept failed: {}", addr, e)).ok()))
+        .map(server::BaseChannel::with_defaults)
+        // Limit channels per IP, unix peers count as this host.
+        .max_channels_per_key(config.channels_per_ip, |t| {
+            t.as_ref().get_ref().peer_ip().unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        })
+        // serve is generated by the service attribute. It takes as input any type implementing
+        // the generated World trait.
+        .map(|channel| {
+            let peer = channel.as_ref().as_ref().get_ref().peer();
+            channel.respond_with(HelloServer(peer).serve()).execute()
+        })
+        .buffer_unordered(config.concurrency)
+        .for_each(|_| async {
+            println!("Got collection.");
+        })
+        .await;
+    Ok(())
+}
+
 #[tokio::main]
-async fn main() -> io::Result<()> {
-    let mut ths = vec![];
+async fn main() -> Result<(), Box<dyn Error>> {
+    let config = ServerConfig::load()?;
+    // bad files stop everything, rather than serving in the clear
+    let tls = config.tls.as_ref().map(tls::acceptor).transpose()?;
 
-    for port in 8000..8008 {
-        let server_addr = format!("127.0.0.1:{}", port);
-        let server_addr = server_addr.parse::<SocketAddr>().unwrap();
-        let th = tokio::spawn(async move {
-            tarpc::serde_transport::tcp::listen(&server_addr, Json::default)
-                .await
-                .unwrap()
-                // Ignore accept errors.
-                .filter_map(|r| future::ready(r.ok()))
-                .map(server::BaseChannel::with_defaults)
-                // Limit channels to 1 per IP.
-                .max_channels_per_key(1, |t| t.as_ref().peer_addr().unwrap().ip())
-                // serve is generated by the service attribute. It takes as input any type implementing
-                // the generated World trait.
-                .map(|channel| {
-                    let server = HelloServer(channel.as_ref().as_ref().peer_addr().unwrap());
-                    channel.respond_with(server.serve()).execute()
-                })
-            .buffer_unordered(10)
-                .for_each(|_| async {
-                    println!("Got collection.");
-                })
-            .await;
+    let mut ths: FuturesUnordered<_> = config.endpoints.iter()
+        .map(|addr| {
+            let serving = tokio::spawn(serve(addr.clone(), config.clone(), tls.clone()));
+            serving.map(move |result| (addr, result))
+        })
+        .collect();
 
-            });
-        ths.push(th);
+    // an endpoint that fails is reported, the others keep serving
+    let mut failed = 0;
+    while let Some((addr, result)) = ths.next().await {
+        match result {
+            Ok(Ok(())) => {  },
+            Ok(Err(e)) => { failed += 1; eprintln!("{}: {}", addr, e); },
+            Err(e)     => { failed += 1; eprintln!("{}: {}", addr, e); },
+        }
     }
 
-    for th in ths.into_iter() {
-        th.await?;
+    if failed == config.endpoints.len() {
+        return Err("no endpoint could be served".into());
     }
-
     Ok(())
 }
@@ -7,13 +7,23 @@
 use futures::{
     future::{self, Ready},
     prelude::*,
-    Future,
+};
+use service::{
+    codec::{self, Format},
+    config::PubSubConfig,
+    delivery::Delivery,
+    envelope::{self, Direction, Envelope, OrderStatus, Trade},
+    journal::{Journal, StartFrom},
+    pubsub::{publisher, subscriber, Publisher},
+    tls,
+    transport::Address,
 };
 use std::{
-    collections::HashMap,
-    io,
-    net::SocketAddr,
-    pin::Pin,
+    collections::HashSet,
+    env,
+    error::Error,
+    fs, io,
+    path::PathBuf,
     sync::{Arc, Mutex},
     time::Duration,
 };
@@ -21,182 +31,196 @@ use tarpc::{
     client, context,
     server::{self, Handler},
 };
-use tokio_serde::formats::Json;
+use tokio_rustls::TlsAcceptor;
 
 use publisher::Publisher as _;
 use subscriber::Subscriber as _;
 
-pub mod subscriber {
-    #[tarpc::service]
-    pub trait Subscriber {
-        async fn receive(message: String);
-    }
-}
-
-pub mod publisher {
-    use std::net::SocketAddr;
-
-    #[tarpc::service]
-    pub trait Publisher {
-        async fn broadcast(message: String);
-        async fn subscribe(id: u32, address: SocketAddr) -> Result<(), String>;
-        async fn unsubscribe(id: u32);
-    }
-}
-
 #[derive(Clone, Debug)]
 struct Subscriber {
     id: u32,
+    /// refuses each message the first time, to show the publisher retrying
+    flaky: bool,
+    seen: Arc<Mutex<HashSet<u64>>>,
 }
 
 impl subscriber::Subscriber for Subscriber {
-    type ReceiveFut = Ready<()>;
+    type ReceiveFut = Ready<Result<(), String>>;
+
+    fn receive(self, _: context::Context, envelope: Envelope) -> Self::ReceiveFut {
+        let message = if envelope.is::<Trade>() {
+            envelope.decode::<Trade>().map(|t| format!("{:?} {} {} at {} ({:?})", t.direction, t.volume, t.code, t.price, t.status))
+        } else {
+            envelope.decode::<String>()
+        };
+        let message = match message {
+            Ok(message) => message,
+            // sending it again won't help, acknowledged so it goes away
+            Err(e)      => { eprintln!("{} dropped {}", self.id, e); return future::ready(Ok(())); },
+        };
+        if self.flaky && self.seen.lock().unwrap().insert(envelope.seq) {
+            eprintln!("{} refused message {} on {}: {}", self.id, envelope.seq, envelope.topic, message);
+            return future::ready(Err("busy, try again".into()));
+        }
+        eprintln!("{} received message {} on {}: {}", self.id, envelope.seq, envelope.topic, message);
+        future::ready(Ok(()))
+    }
+
+    type PingFut = Ready<()>;
 
-    fn receive(self, _: context::Context, message: String) -> Self::ReceiveFut {
-        eprintln!("{} received message: {}", self.id, message);
+    fn ping(self, _: context::Context) -> Self::PingFut {
         future::ready(())
     }
 }
 
 impl Subscriber {
-    async fn listen(id: u32, config: server::Config) -> io::Result<SocketAddr> {
-        let incoming = tarpc::serde_transport::tcp::listen("localhost:0", Json::default)
-            .await?
-            .filter_map(|r| future::ready(r.ok()));
-        let addr = incoming.get_ref().local_addr();
+    async fn listen(id: u32, addr: &str, flaky: bool, config: server::Config, tls: Option<TlsAcceptor>) -> io::Result<Address> {
+        let addr = addr.parse().map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let incoming = codec::listen(&addr, Format::ALL.to_vec(), tls).await?;
+        let addr = incoming.local_addr().clone();
+        let incoming = incoming.filter_map(|r| future::ready(r.ok()));
         tokio::spawn(
             server::new(config)
                 .incoming(incoming)
-                .take(1)
-                .respond_with(Subscriber { id }.serve()),
+                // the publisher connects again after failures
+                .respond_with(Subscriber { id, flaky, seen: Default::default() }.serve()),
         );
         Ok(addr)
     }
 }
 
-#[derive(Clone, Debug)]
-struct Publisher {
-    clients: Arc<Mutex<HashMap<u32, subscriber::SubscriberClient>>>,
-}
-
-impl Publisher {
-    fn new() -> Publisher {
-        Publisher {
-            clients: Arc::new(Mutex::new(HashMap::new())),
-        }
-    }
-}
-
-impl publisher::Publisher for Publisher {
-    type BroadcastFut = Pin<Box<dyn Future<Output = ()> + Send>>;
-
-    fn broadcast(self, _: context::Context, message: String) -> Self::BroadcastFut {
-        async fn broadcast(
-            clients: Arc<Mutex<HashMap<u32, subscriber::SubscriberClient>>>,
-            message: String,
-        ) {
-            let mut clients = clients.lock().unwrap().clone();
-            for client in clients.values_mut() {
-                // Ignore failing subscribers. In a real pubsub,
-                // you'd want to continually retry until subscribers
-                // ack.
-                let _ = client.receive(context::current(), message.clone()).await;
-            }
-        }
-
-        broadcast(self.clients.clone(), message).boxed()
-    }
-
-    type SubscribeFut = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
-
-    fn subscribe(self, _: context::Context, id: u32, addr: SocketAddr) -> Self::SubscribeFut {
-        async fn subscribe(
-            clients: Arc<Mutex<HashMap<u32, subscriber::SubscriberClient>>>,
-            id: u32,
-            addr: SocketAddr,
-        ) -> io::Result<()> {
-            let conn = tarpc::serde_transport::tcp::connect(addr, Json::default()).await?;
-            let subscriber =
-                subscriber::SubscriberClient::new(client::Config::default(), conn).spawn()?;
-            eprintln!("Subscribing {}.", id);
-            clients.lock().unwrap().insert(id, subscriber);
-            Ok(())
-        }
-
-        subscribe(Arc::clone(&self.clients), id, addr)
-            .map_err(|e| e.to_string())
-            .boxed()
-    }
-
-    type UnsubscribeFut = Pin<Box<dyn Future<Output = ()> + Send>>;
-
-    fn unsubscribe(self, _: context::Context, id: u32) -> Self::UnsubscribeFut {
-        eprintln!("Unsubscribing {}", id);
-        let mut clients = self.clients.lock().unwrap();
-        if clients.remove(&id).is_none() {
-            eprintln!(
-                "Client {} not found. Existings clients: {:?}",
-                id, &*clients
-            );
-        }
-        future::ready(()).boxed()
-    }
+/// with RPC_TLS_DIR set, e.g. to what certs/gen.sh wrote, everything goes over mutual TLS
+fn tls() -> io::Result<Option<(TlsAcceptor, tls::Connector)>> {
+    let dir = match env::var_os("RPC_TLS_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None      => return Ok(None),
+    };
+    let acceptor = tls::acceptor(&tls::ServerFiles {
+        cert      : dir.join("server.pem"),
+        key       : dir.join("server.key"),
+        client_ca : Some(dir.join("ca.pem")),
+    })?;
+    let connector = tls::Connector::new(&tls::ClientFiles {
+        ca          : dir.join("ca.pem"),
+        cert        : Some(dir.join("client.pem")),
+        key         : Some(dir.join("client.key")),
+        server_name : None,
+    }, "localhost")?;
+    Ok(Some((acceptor, connector)))
 }
 
 #[tokio::main]
-async fn main() -> io::Result<()> {
-    env_logger::init();
-
-    let transport = tarpc::serde_transport::tcp::listen("localhost:0", Json::default)
-        .await?
-        .filter_map(|r| future::ready(r.ok()));
-    let publisher_addr = transport.get_ref().local_addr();
+async fn main() -> Result<(), Box<dyn Error>> {
+    // the publisher logs subscriptions and delivery trouble at info and warn
+    env_logger::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let config = PubSubConfig::load()?;
+    // trades of earlier runs are still there for the latecomer
+    let journal = Journal::open(&config.journal, config.retention)
+        .map_err(|e| format!("{}: {}", config.journal.display(), e))?;
+    println!("Journal in {}.", config.journal.display());
+
+    let (acceptor, connector) = match tls()? {
+        Some((acceptor, connector)) => { println!("Using mutual TLS."); (Some(acceptor), Some(connector)) },
+        None                        => (None, None),
+    };
+
+    // all in one process, so the publisher needs no port
+    let publisher_addr = Address::Mem("publisher".into());
+    let transport = codec::listen(&publisher_addr, vec![Format::Json, Format::Bincode], acceptor.clone()).await?;
+    // subscribers are sent msgpack
+    let mut delivery = Delivery::default();
+    delivery.codecs = vec![Format::MessagePack, Format::Json];
+    delivery.tls = connector.clone();
     tokio::spawn(
         transport
+            .filter_map(|r| future::ready(r.ok()))
             .take(1)
             .map(server::BaseChannel::with_defaults)
-            .respond_with(Publisher::new().serve()),
+            .respond_with(Publisher::with_journal(delivery, journal).serve()),
     );
 
-    let subscriber1 = Subscriber::listen(0, server::Config::default()).await?;
-    let subscriber2 = Subscriber::listen(1, server::Config::default()).await?;
-    let subscriber3 = Subscriber::listen(2, server::Config::default()).await?;
+    // one of each transport
+    let socket = env::temp_dir().join(format!("subpub-{}.sock", std::process::id()));
+    let subscriber1 = Subscriber::listen(0, "mem:subscriber-0", false, server::Config::default(), acceptor.clone()).await?;
+    let subscriber2 = Subscriber::listen(1, "mem:subscriber-1", false, server::Config::default(), acceptor.clone()).await?;
+    let subscriber3 = Subscriber::listen(2, &format!("unix:{}", socket.display()), true, server::Config::default(), acceptor.clone()).await?;
+    let latecomer = Subscriber::listen(3, "tcp:127.0.0.1:0", false, server::Config::default(), acceptor).await?;
 
-    let publisher_conn = tarpc::serde_transport::tcp::connect(publisher_addr, Json::default());
+    // cbor is not spoken by the publisher, bincode is picked
+    let publisher_conn = codec::connect(&publisher_addr, &[Format::Cbor, Format::Bincode], connector.as_ref());
     let publisher_conn = publisher_conn.await?;
     let mut publisher =
         publisher::PublisherClient::new(client::Config::default(), publisher_conn).spawn()?;
 
     if let Err(e) = publisher
-        .subscribe(context::current(), 0, subscriber1)
+        .subscribe(context::current(), 0, subscriber1, vec!["trades.#".into()], StartFrom::Live)
         .await?
     {
         eprintln!("Couldn't subscribe subscriber 0: {}", e);
     }
     if let Err(e) = publisher
-        .subscribe(context::current(), 1, subscriber2)
+        .subscribe(context::current(), 1, subscriber2, vec!["trades.000001".into(), "news".into()], StartFrom::Live)
         .await?
     {
         eprintln!("Couldn't subscribe subscriber 1: {}", e);
     }
     if let Err(e) = publisher
-        .subscribe(context::current(), 2, subscriber3)
+        .subscribe(context::current(), 2, subscriber3, vec!["trades.*".into()], StartFrom::Live)
         .await?
     {
         eprintln!("Couldn't subscribe subscriber 2: {}", e);
     }
 
-    println!("Broadcasting...");
-    publisher
-        .broadcast(context::current(), "hello to all".to_string())
-        .await?;
+    let trade = |code: &str, price, volume, direction, status| Trade {
+        code     : code.to_string(),
+        exchange : "SZSE".to_string(),
+        price,
+        volume,
+        direction,
+        status,
+    };
+    println!("Publishing...");
+    for (topic, message) in vec![
+        ("trades.000001", envelope::encode(&trade("000001", 12.5, 300, Direction::Buy, OrderStatus::Filled))),
+        ("news", envelope::encode(&"market opens".to_string())),
+        ("trades.000002.canceled", envelope::encode(&trade("000002", 8.2, 100, Direction::Sell, OrderStatus::Cancel))),
+    ] {
+        let (content_type, body) = message.expect("trades and text encode");
+        match publisher.publish(context::current(), topic.to_string(), content_type, body).await? {
+            Ok(report) => println!("{}: acked by {:?}, late {:?}, failed {:?}.", topic, report.acked, report.late, report.failed),
+            Err(e)     => eprintln!("Couldn't publish to {}: {}", topic, e),
+        }
+    }
     publisher.unsubscribe(context::current(), 1).await?;
-    publisher
-        .broadcast(context::current(), "hi again".to_string())
-        .await?;
+    // gets the trades published so far before the next one
+    if let Err(e) = publisher
+        .subscribe(context::current(), 3, latecomer, vec!["trades.#".into()], StartFrom::Seq(0))
+        .await?
+    {
+        eprintln!("Couldn't subscribe subscriber 3: {}", e);
+    }
+    let (content_type, body) = envelope::encode(&trade("000001", 12.6, 200, Direction::Sell, OrderStatus::Pending))
+        .expect("trades encode");
+    if let Ok(report) = publisher
+        .publish(context::current(), "trades.000001".to_string(), content_type, body)
+        .await?
+    {
+        println!("Subscribers {:?} got trades.000001.", report.acked);
+    }
+    for sub in publisher.list_subscribers(context::current()).await? {
+        println!("Subscriber {} at {}: {:?}, {} failures, {} queued", sub.id, sub.address, sub.state, sub.failures, sub.queued);
+    }
+    for topic in publisher.list_topics(context::current()).await? {
+        println!("Topic {}: {} messages, subscribers {:?}", topic.topic, topic.messages, topic.subscribers);
+    }
+    for letter in publisher.dead_letters(context::current()).await? {
+        eprintln!("Dead letter: {:?}", letter);
+    }
     drop(publisher);
 
     tokio::time::delay_for(Duration::from_millis(100)).await;
+    // the listener still holds it, it goes away with the process otherwise
+    let _ = fs::remove_file(&socket);
     println!("Done.");
 
     Ok(())
@@ -0,0 +1,130 @@
+use crate::transport::Conn;
+
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio_rustls::{
+    rustls::{
+        internal::pemfile, AllowAnyAuthenticatedClient, Certificate, ClientConfig, NoClientAuth, PrivateKey,
+        RootCertStore, ServerConfig,
+    },
+    webpki::DNSNameRef,
+    TlsAcceptor, TlsConnector, TlsStream,
+};
+
+/// what the server side loads: its chain and key, and the CA client certs must chain to for mutual TLS.
+#[derive(Clone, Debug)]
+pub struct ServerFiles {
+    pub cert      : PathBuf,
+    pub key       : PathBuf,
+    pub client_ca : Option<PathBuf>,
+}
+
+/// what the client side loads: the CA the server cert must chain to, a cert and key of its own
+/// if the server wants one, and the name the server cert must carry.
+#[derive(Clone, Debug)]
+pub struct ClientFiles {
+    pub ca          : PathBuf,
+    pub cert        : Option<PathBuf>,
+    pub key         : Option<PathBuf>,
+    pub server_name : Option<String>,
+}
+
+fn bad<P: AsRef<Path>>(path: P, what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.as_ref().display(), what))
+}
+
+fn open<P: AsRef<Path>>(path: P) -> io::Result<BufReader<File>> {
+    File::open(path.as_ref())
+        .map(BufReader::new)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.as_ref().display(), e)))
+}
+
+fn certs<P: AsRef<Path>>(path: P) -> io::Result<Vec<Certificate>> {
+    match pemfile::certs(&mut open(&path)?) {
+        Ok(certs) if !certs.is_empty() => Ok(certs),
+        _                              => Err(bad(path, "no PEM certificates")),
+    }
+}
+
+/// the first PKCS#8 or RSA key in the file
+fn key<P: AsRef<Path>>(path: P) -> io::Result<PrivateKey> {
+    let pkcs8 = pemfile::pkcs8_private_keys(&mut open(&path)?).unwrap_or_default();
+    let rsa = pemfile::rsa_private_keys(&mut open(&path)?).unwrap_or_default();
+    pkcs8.into_iter().chain(rsa).next().ok_or_else(|| bad(path, "no PEM private key"))
+}
+
+fn roots<P: AsRef<Path>>(path: P) -> io::Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    match roots.add_pem_file(&mut open(&path)?) {
+        Ok((added, _)) if added > 0 => Ok(roots),
+        _                           => Err(bad(path, "no usable CA certificates")),
+    }
+}
+
+/// a TLS acceptor, asking for client certs signed by `client_ca` if there is one
+pub fn acceptor(files: &ServerFiles) -> io::Result<TlsAcceptor> {
+    let verifier = match files.client_ca {
+        Some(ref ca) => AllowAnyAuthenticatedClient::new(roots(ca)?),
+        None         => NoClientAuth::new(),
+    };
+    let mut config = ServerConfig::new(verifier);
+    config.set_single_cert(certs(&files.cert)?, key(&files.key)?)
+        .map_err(|e| bad(&files.cert, &e.to_string()))?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// the client end of TLS, with the name checked against the server cert
+#[derive(Clone)]
+pub struct Connector {
+    inner : TlsConnector,
+    name  : String,
+}
+
+impl Connector {
+    /// `host` is what was connected to, it names the server unless the files say otherwise.
+    /// Certs are checked by DNS name only, so an ip, unix or mem address needs `server_name`.
+    pub fn new(files: &ClientFiles, host: &str) -> io::Result<Self> {
+        let mut config = ClientConfig::new();
+        config.root_store = roots(&files.ca)?;
+        match (&files.cert, &files.key) {
+            (Some(cert), Some(key_file)) => {
+                config.set_single_client_cert(certs(cert)?, key(key_file)?)
+                    .map_err(|e| bad(cert, &e.to_string()))?;
+            },
+            (None, None) => {  },
+            _            => return Err(io::Error::new(io::ErrorKind::InvalidInput, "a client cert needs its key and the other way round")),
+        }
+        // guessing a name for an address would accept any cert of the CA that carries it
+        let host = host.trim_start_matches("tcp:");
+        let name = match files.server_name {
+            Some(ref name)                                               => name.clone(),
+            None if !host.contains(':') && host.parse::<IpAddr>().is_err() => host.to_string(),
+            None                                                         => return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("TLS to {} needs a server name for the certificate, set --tls-server-name", host),
+            )),
+        };
+        DNSNameRef::try_from_ascii_str(&name)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, format!("bad TLS server name {:?}", name)))?;
+        Ok(Self { inner: TlsConnector::from(Arc::new(config)), name })
+    }
+
+    pub async fn connect(&self, io: Conn) -> io::Result<Conn> {
+        let name = DNSNameRef::try_from_ascii_str(&self.name).expect("checked in new");
+        let stream = self.inner.connect(name, io).await?;
+        Ok(Conn::Tls(Box::new(TlsStream::from(stream))))
+    }
+}
+
+/// the server side of TLS if there is an acceptor, `io` as it is otherwise
+pub async fn accept(io: Conn, acceptor: Option<&TlsAcceptor>) -> io::Result<Conn> {
+    match acceptor {
+        Some(acceptor) => Ok(Conn::Tls(Box::new(TlsStream::from(acceptor.accept(io).await?)))),
+        None           => Ok(io),
+    }
+}
@@ -0,0 +1,62 @@
+use std::fmt;
+
+/// topics are dot separated words, e.g. `trades.000001`
+pub fn check_topic(topic: &str) -> Result<(), String> {
+    if topic.split('.').any(|word| word.is_empty() || word == "*" || word == "#") {
+        return Err(format!("bad topic {:?}, expect dot separated words without wildcards", topic));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Word {
+    Exact(String),
+    /// `*`, exactly one word
+    One,
+    /// `#`, any number of words, only last
+    Rest,
+}
+
+/// what a subscriber listens to: `trades.000001`, `trades.*` or `trades.#`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pattern {
+    words: Vec<Word>,
+}
+
+impl Pattern {
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let count = pattern.split('.').count();
+        let words = pattern.split('.').enumerate().map(|(idx, word)| match word {
+            ""                       => Err(format!("bad pattern {:?}, empty word", pattern)),
+            "*"                      => Ok(Word::One),
+            "#" if idx + 1 == count  => Ok(Word::Rest),
+            "#"                      => Err(format!("bad pattern {:?}, # must be last", pattern)),
+            word                     => Ok(Word::Exact(word.to_string())),
+        }).collect::<Result<_, _>>()?;
+        Ok(Self { words })
+    }
+
+    pub fn matches(&self, topic: &str) -> bool {
+        let mut words = topic.split('.');
+        for word in self.words.iter() {
+            match (word, words.next()) {
+                (Word::Rest, _)                            => return true,
+                (Word::One, Some(_))                       => {  },
+                (Word::Exact(w), Some(t)) if w == t        => {  },
+                _                                          => return false,
+            }
+        }
+        words.next().is_none()
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let words: Vec<&str> = self.words.iter().map(|w| match w {
+            Word::Exact(w) => w.as_str(),
+            Word::One      => "*",
+            Word::Rest     => "#",
+        }).collect();
+        write!(f, "{}", words.join("."))
+    }
+}
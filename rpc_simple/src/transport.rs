@@ -0,0 +1,299 @@
+use bytes::Bytes;
+use futures::{channel::mpsc, prelude::*, ready};
+use once_cell::sync::Lazy;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    os::unix::fs::FileTypeExt,
+    path::PathBuf,
+    pin::Pin,
+    str::FromStr,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream, UnixListener, UnixStream},
+};
+use tokio_rustls::TlsStream;
+
+/// where a service listens: `tcp:host:port`, `unix:/path` or `mem:name`.
+/// A bare `host:port` is tcp.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Address {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+    /// only reachable from within the process
+    Mem(String),
+}
+
+impl Address {
+    pub fn port(&self) -> Option<u16> {
+        match self {
+            Address::Tcp(addr) => Some(addr.port()),
+            _                  => None,
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Address::Tcp(addr)  => write!(f, "tcp:{}", addr),
+            Address::Unix(path) => write!(f, "unix:{}", path.display()),
+            Address::Mem(name)  => write!(f, "mem:{}", name),
+        }
+    }
+}
+
+impl FromStr for Address {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let tcp = |addr: &str| {
+            addr.to_socket_addrs()
+                .map_err(|e| format!("{}: {}", s, e))?
+                .next()
+                .map(Address::Tcp)
+                .ok_or_else(|| format!("{}: no address", s))
+        };
+        match s.find(':').map(|pos| (&s[..pos], &s[pos + 1..])) {
+            Some(("unix", path)) if !path.is_empty() => Ok(Address::Unix(PathBuf::from(path))),
+            Some(("mem", name)) if !name.is_empty()  => Ok(Address::Mem(name.to_string())),
+            Some(("unix", _)) | Some(("mem", _))     => Err(format!("{}: missing path or name", s)),
+            Some(("tcp", addr))                      => tcp(addr),
+            _                                        => tcp(s),
+        }
+    }
+}
+
+// as the string, so it reads the same in every codec
+impl Serialize for Address {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Address {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
+/// listening `mem:` names and where their connections go
+static MEM: Lazy<Mutex<HashMap<String, mpsc::UnboundedSender<MemStream>>>> = Lazy::new(Default::default);
+
+/// one end of an in-process connection, written chunks are read on the other end
+pub struct MemStream {
+    name    : String,
+    tx      : mpsc::UnboundedSender<Bytes>,
+    rx      : mpsc::UnboundedReceiver<Bytes>,
+    pending : Bytes, // received, not read yet
+}
+
+impl MemStream {
+    fn pair(name: &str) -> (Self, Self) {
+        let (tx1, rx1) = mpsc::unbounded();
+        let (tx2, rx2) = mpsc::unbounded();
+        let end = |tx, rx| MemStream { name: name.to_string(), tx, rx, pending: Bytes::new() };
+        (end(tx1, rx2), end(tx2, rx1))
+    }
+}
+
+impl AsyncRead for MemStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        while this.pending.is_empty() {
+            match ready!(this.rx.poll_next_unpin(cx)) {
+                Some(bytes) => this.pending = bytes,
+                // the other end is gone
+                None        => return Poll::Ready(Ok(0)),
+            }
+        }
+        let n = buf.len().min(this.pending.len());
+        let rest = this.pending.split_off(n);
+        buf[..n].copy_from_slice(&this.pending);
+        this.pending = rest;
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncWrite for MemStream {
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.tx.unbounded_send(Bytes::copy_from_slice(buf)) {
+            Ok(()) => Poll::Ready(Ok(buf.len())),
+            Err(_) => Poll::Ready(Err(io::ErrorKind::BrokenPipe.into())),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.tx.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// a connection over any of the transports, encrypted or not
+pub enum Conn {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+    Mem(MemStream),
+    Tls(Box<TlsStream<Conn>>),
+}
+
+impl Conn {
+    pub async fn connect(addr: &Address) -> io::Result<Self> {
+        match addr {
+            Address::Tcp(addr)  => Ok(Conn::Tcp(TcpStream::connect(addr).await?)),
+            Address::Unix(path) => Ok(Conn::Unix(UnixStream::connect(path).await?)),
+            Address::Mem(name)  => {
+                let (ours, theirs) = MemStream::pair(name);
+                match MEM.lock().unwrap().get(name) {
+                    Some(listener) if listener.unbounded_send(theirs).is_ok() => Ok(Conn::Mem(ours)),
+                    _ => Err(io::Error::new(io::ErrorKind::ConnectionRefused, format!("nothing listens on mem:{}", name))),
+                }
+            },
+        }
+    }
+
+    /// who is on the other end, for logs
+    pub fn peer(&self) -> String {
+        match self {
+            Conn::Tcp(io)  => io.peer_addr().map(|addr| format!("tcp:{}", addr)).unwrap_or_else(|_| "tcp:?".into()),
+            Conn::Unix(io) => match io.peer_addr().ok().and_then(|addr| addr.as_pathname().map(|p| p.to_path_buf())) {
+                Some(path) => format!("unix:{}", path.display()),
+                None       => "unix:unnamed".into(),
+            },
+            Conn::Mem(io)  => format!("mem:{}", io.name),
+            Conn::Tls(io)  => io.get_ref().0.peer(),
+        }
+    }
+
+    /// the remote ip, unix and mem peers are on this host
+    pub fn peer_ip(&self) -> Option<IpAddr> {
+        match self {
+            Conn::Tcp(io) => io.peer_addr().ok().map(|addr| addr.ip()),
+            Conn::Tls(io) => io.get_ref().0.peer_ip(),
+            _             => None,
+        }
+    }
+}
+
+impl AsyncRead for Conn {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Conn::Tcp(io)  => Pin::new(io).poll_read(cx, buf),
+            Conn::Unix(io) => Pin::new(io).poll_read(cx, buf),
+            Conn::Mem(io)  => Pin::new(io).poll_read(cx, buf),
+            Conn::Tls(io)  => Pin::new(&mut **io).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Conn {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Conn::Tcp(io)  => Pin::new(io).poll_write(cx, buf),
+            Conn::Unix(io) => Pin::new(io).poll_write(cx, buf),
+            Conn::Mem(io)  => Pin::new(io).poll_write(cx, buf),
+            Conn::Tls(io)  => Pin::new(&mut **io).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Conn::Tcp(io)  => Pin::new(io).poll_flush(cx),
+            Conn::Unix(io) => Pin::new(io).poll_flush(cx),
+            Conn::Mem(io)  => Pin::new(io).poll_flush(cx),
+            Conn::Tls(io)  => Pin::new(&mut **io).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Conn::Tcp(io)  => Pin::new(io).poll_shutdown(cx),
+            Conn::Unix(io) => Pin::new(io).poll_shutdown(cx),
+            Conn::Mem(io)  => Pin::new(io).poll_shutdown(cx),
+            Conn::Tls(io)  => Pin::new(&mut **io).poll_shutdown(cx),
+        }
+    }
+}
+
+/// accepts connections on one address; a unix socket file or mem name is given up on drop
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener, PathBuf),
+    Mem(mpsc::UnboundedReceiver<MemStream>, String),
+}
+
+impl Listener {
+    pub async fn bind(addr: &Address) -> io::Result<Self> {
+        match addr {
+            Address::Tcp(addr)  => Ok(Listener::Tcp(TcpListener::bind(addr).await?)),
+            Address::Unix(path) => {
+                // a socket left behind by a listener that died, nobody answers on it; never any other file
+                match fs::symlink_metadata(path) {
+                    Ok(meta) if meta.file_type().is_socket() => {
+                        if UnixStream::connect(path).await.is_ok() {
+                            return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("unix:{} is in use", path.display())));
+                        }
+                        fs::remove_file(path)?;
+                    },
+                    Ok(_) => {
+                        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("unix:{} exists and is not a socket", path.display())));
+                    },
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {  },
+                    Err(e) => return Err(e),
+                }
+                Ok(Listener::Unix(UnixListener::bind(path)?, path.clone()))
+            },
+            Address::Mem(name)  => {
+                let mut mem = MEM.lock().unwrap();
+                if mem.get(name).map_or(false, |listener| !listener.is_closed()) {
+                    return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("mem:{} is in use", name)));
+                }
+                let (tx, rx) = mpsc::unbounded();
+                mem.insert(name.clone(), tx);
+                Ok(Listener::Mem(rx, name.clone()))
+            },
+        }
+    }
+
+    /// where clients connect, with the port picked for `tcp:host:0`
+    pub fn local_addr(&self) -> io::Result<Address> {
+        match self {
+            Listener::Tcp(listener) => listener.local_addr().map(Address::Tcp),
+            Listener::Unix(_, path) => Ok(Address::Unix(path.clone())),
+            Listener::Mem(_, name)  => Ok(Address::Mem(name.clone())),
+        }
+    }
+
+    pub async fn accept(&mut self) -> io::Result<Conn> {
+        match self {
+            Listener::Tcp(listener)     => Ok(Conn::Tcp(listener.accept().await?.0)),
+            Listener::Unix(listener, _) => Ok(Conn::Unix(listener.accept().await?.0)),
+            Listener::Mem(incoming, _)  => match incoming.next().await {
+                Some(io) => Ok(Conn::Mem(io)),
+                // the sender lives in MEM until this is dropped
+                None     => Err(io::ErrorKind::NotConnected.into()),
+            },
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        match self {
+            Listener::Unix(_, path) => { let _ = fs::remove_file(path); },
+            Listener::Mem(_, name)  => { MEM.lock().unwrap().remove(name); },
+            Listener::Tcp(_)        => {  },
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use service::{
+    envelope::{self, Direction, OrderStatus, Trade},
+    journal::{Journal, Retention, StartFrom},
+};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+const KEEP_ALL: Retention = Retention { max_messages: None, max_age: None };
+
+/// topics and sequence numbers of what the journal at `path` kept
+fn kept(path: &Path) -> Vec<(String, u64)> {
+    let mut journal = Journal::open(path, KEEP_ALL).unwrap();
+    journal.since(StartFrom::Seq(0)).into_iter().map(|e| (e.topic, e.seq)).collect()
+}
+
+fn append_raw(path: &Path, text: &str) {
+    OpenOptions::new().append(true).open(path).unwrap().write_all(text.as_bytes()).unwrap();
+}
+
+fn publish(path: &Path, topics: &[&str]) {
+    let mut journal = Journal::open(path, KEEP_ALL).unwrap();
+    for topic in topics {
+        journal.append(topic, "text/plain", b"body".to_vec()).unwrap();
+    }
+}
+
+#[test]
+fn reopen_goes_on_where_it_ended() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("subpub.journal");
+    publish(&path, &["a", "b"]);
+    publish(&path, &["c"]);
+    assert_eq!(kept(&path), vec![("a".into(), 0), ("b".into(), 1), ("c".into(), 2)]);
+}
+
+#[test]
+fn corrupt_line_in_the_middle_is_skipped_not_cut() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("subpub.journal");
+    publish(&path, &["a"]);
+    append_raw(&path, "{\"topic\": \"garbled\n");
+    publish(&path, &["b", "c"]);
+
+    let expect = vec![("a".into(), 0), ("b".into(), 1), ("c".into(), 2)];
+    assert_eq!(kept(&path), expect);
+    // opening did not truncate anything, the later entries are still in the file
+    assert_eq!(kept(&path), expect);
+    publish(&path, &["d"]);
+    assert_eq!(kept(&path).last(), Some(&("d".into(), 3)));
+}
+
+#[test]
+fn torn_last_line_is_cut_off() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("subpub.journal");
+    publish(&path, &["a"]);
+    let whole = fs::metadata(&path).unwrap().len();
+    append_raw(&path, "{\"topic\": \"b\", \"se");
+
+    publish(&path, &["b"]);
+    assert_eq!(kept(&path), vec![("a".into(), 0), ("b".into(), 1)]);
+    assert!(fs::metadata(&path).unwrap().len() > whole);
+}
+
+#[test]
+fn whole_last_entry_without_newline_is_kept() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("subpub.journal");
+    publish(&path, &["a"]);
+    let text = fs::read_to_string(&path).unwrap();
+    fs::write(&path, text.trim_end()).unwrap();
+
+    publish(&path, &["b"]);
+    assert_eq!(kept(&path), vec![("a".into(), 0), ("b".into(), 1)]);
+}
+
+#[test]
+fn json_bodies_are_written_as_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("subpub.journal");
+    let trade = Trade {
+        code      : "000001".into(),
+        exchange  : "SZSE".into(),
+        price     : 12.5,
+        volume    : 300,
+        direction : Direction::Buy,
+        status    : OrderStatus::Pending,
+    };
+    let (content_type, body) = envelope::encode(&trade).unwrap();
+    Journal::open(&path, KEEP_ALL).unwrap().append("trades.000001", &content_type, body.clone()).unwrap();
+
+    let text = fs::read_to_string(&path).unwrap();
+    assert!(text.contains(&format!("\"json\":{}", String::from_utf8(body.clone()).unwrap())), "{}", text);
+    assert!(text.len() < 2 * body.len() + 200, "{}", text);
+
+    let kept = Journal::open(&path, KEEP_ALL).unwrap().since(StartFrom::Seq(0));
+    assert_eq!(kept[0].body, body);
+    assert_eq!(kept[0].decode::<Trade>().unwrap(), trade);
+}
+
+#[test]
+fn other_bodies_read_back_byte_for_byte() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("subpub.journal");
+    let bodies: Vec<Vec<u8>> = vec![
+        vec![0, 159, 146, 150, 255],
+        b"{\n  \"pretty\": true\n}".to_vec(),
+        b" 42 ".to_vec(),
+        b"not json".to_vec(),
+        vec![],
+    ];
+    {
+        let mut journal = Journal::open(&path, KEEP_ALL).unwrap();
+        for body in bodies.iter() {
+            journal.append("raw", "application/octet-stream", body.clone()).unwrap();
+        }
+    }
+    assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), bodies.len());
+    let kept: Vec<Vec<u8>> = Journal::open(&path, KEEP_ALL).unwrap().since(StartFrom::Seq(0)).into_iter().map(|e| e.body).collect();
+    assert_eq!(kept, bodies);
+}
+
+#[test]
+fn bodies_as_byte_arrays_are_still_read() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("subpub.journal");
+    fs::write(
+        &path,
+        "{\"topic\":\"a\",\"seq\":4,\"at\":{\"secs_since_epoch\":1600000000,\"nanos_since_epoch\":0},\
+         \"content_type\":\"text/plain\",\"body\":[104,105]}\n",
+    ).unwrap();
+    let kept = Journal::open(&path, KEEP_ALL).unwrap().since(StartFrom::Seq(0));
+    assert_eq!((kept[0].seq, kept[0].body.as_slice()), (4, &b"hi"[..]));
+}
@@ -0,0 +1,168 @@
+use futures::{
+    future::{self, Ready},
+    prelude::*,
+};
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa};
+use service::{
+    codec::{self, Format},
+    tls::{self, ClientFiles, ServerFiles},
+    transport::Address,
+    World, WorldClient,
+};
+use std::{fs, io, path::PathBuf};
+use tarpc::{
+    client, context,
+    server::{self, Channel},
+};
+use tempfile::TempDir;
+
+#[derive(Clone)]
+struct HelloServer;
+
+impl World for HelloServer {
+    type HelloFut = Ready<String>;
+
+    fn hello(self, _: context::Context, name: String) -> Self::HelloFut {
+        future::ready(format!("Hello, {}!", name))
+    }
+}
+
+/// two CAs, a server cert for localhost and a client cert from each CA, as PEM files in a temp dir
+struct Pki {
+    dir : TempDir,
+}
+
+fn ca(name: &str) -> Certificate {
+    let mut params = CertificateParams::new(vec![]);
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    Certificate::from_params(params).unwrap()
+}
+
+fn leaf(name: &str) -> Certificate {
+    let mut params = CertificateParams::new(vec![name.to_string()]);
+    params.distinguished_name.push(DnType::CommonName, name);
+    Certificate::from_params(params).unwrap()
+}
+
+impl Pki {
+    fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |file: &str, pem: String| fs::write(dir.path().join(file), pem).unwrap();
+
+        let (ca, other_ca) = (ca("rpc_simple test CA"), ca("someone else's CA"));
+        write("ca.pem", ca.serialize_pem().unwrap());
+        write("other-ca.pem", other_ca.serialize_pem().unwrap());
+        for (name, cert, signer) in vec![
+            ("server", leaf("localhost"), &ca),
+            ("client", leaf("rpc-simple-client"), &ca),
+            ("other-client", leaf("rpc-simple-client"), &other_ca),
+        ] {
+            write(&format!("{}.pem", name), cert.serialize_pem_with_signer(signer).unwrap());
+            write(&format!("{}.key", name), cert.serialize_private_key_pem());
+        }
+        Self { dir }
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.dir.path().join(file)
+    }
+
+    fn server(&self, client_ca: bool) -> ServerFiles {
+        ServerFiles {
+            cert      : self.path("server.pem"),
+            key       : self.path("server.key"),
+            client_ca : if client_ca { Some(self.path("ca.pem")) } else { None },
+        }
+    }
+
+    /// trusting the test CA and expecting localhost, with `cert` as the client cert if there is one
+    fn client(&self, cert: Option<&str>) -> ClientFiles {
+        ClientFiles {
+            ca          : self.path("ca.pem"),
+            cert        : cert.map(|name| self.path(&format!("{}.pem", name))),
+            key         : cert.map(|name| self.path(&format!("{}.key", name))),
+            server_name : Some("localhost".into()),
+        }
+    }
+}
+
+/// serves hello over TLS on a free localhost port until the test ends
+async fn serve(files: &ServerFiles) -> io::Result<Address> {
+    let acceptor = tls::acceptor(files)?;
+    let addr: Address = "tcp:127.0.0.1:0".parse().unwrap();
+    let incoming = codec::listen(&addr, vec![Format::Json], Some(acceptor)).await?;
+    let addr = incoming.local_addr().clone();
+    tokio::spawn(
+        incoming
+            .filter_map(|r| future::ready(r.ok()))
+            .map(server::BaseChannel::with_defaults)
+            .for_each_concurrent(None, |channel| channel.respond_with(HelloServer.serve()).execute()),
+    );
+    Ok(addr)
+}
+
+async fn hello(addr: &Address, files: &ClientFiles) -> io::Result<String> {
+    let connector = tls::Connector::new(files, &addr.to_string())?;
+    let transport = codec::connect(addr, &[Format::Json], Some(&connector)).await?;
+    let mut client = WorldClient::new(client::Config::default(), transport).spawn()?;
+    client.hello(context::current(), "tls".into()).await
+}
+
+#[tokio::test]
+async fn hello_over_tls() {
+    let pki = Pki::new();
+    let addr = serve(&pki.server(false)).await.unwrap();
+    assert_eq!(hello(&addr, &pki.client(None)).await.unwrap(), "Hello, tls!");
+}
+
+#[tokio::test]
+async fn hello_over_mutual_tls() {
+    let pki = Pki::new();
+    let addr = serve(&pki.server(true)).await.unwrap();
+    assert_eq!(hello(&addr, &pki.client(Some("client"))).await.unwrap(), "Hello, tls!");
+}
+
+#[tokio::test]
+async fn client_ca_rejects_missing_client_cert() {
+    let pki = Pki::new();
+    let addr = serve(&pki.server(true)).await.unwrap();
+    assert!(hello(&addr, &pki.client(None)).await.is_err());
+}
+
+#[tokio::test]
+async fn client_ca_rejects_cert_of_another_ca() {
+    let pki = Pki::new();
+    let addr = serve(&pki.server(true)).await.unwrap();
+    assert!(hello(&addr, &pki.client(Some("other-client"))).await.is_err());
+}
+
+#[tokio::test]
+async fn server_cert_of_another_ca_is_rejected() {
+    let pki = Pki::new();
+    let addr = serve(&pki.server(false)).await.unwrap();
+    let files = ClientFiles { ca: pki.path("other-ca.pem"), ..pki.client(None) };
+    assert!(hello(&addr, &files).await.is_err());
+}
+
+#[tokio::test]
+async fn address_without_server_name_is_refused() {
+    let pki = Pki::new();
+    let addr = serve(&pki.server(false)).await.unwrap();
+    let files = ClientFiles { server_name: None, ..pki.client(None) };
+    for host in vec![addr.to_string(), "127.0.0.1".into(), "::1".into(), "unix:/tmp/rpc.sock".into(), "mem:hello".into()] {
+        let e = tls::Connector::new(&files, &host).err().expect("guessed a server name");
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput, "{}", host);
+    }
+    // a host name is the server name
+    assert!(tls::Connector::new(&files, "localhost").is_ok());
+}
+
+#[tokio::test]
+async fn server_name_mismatch_fails() {
+    let pki = Pki::new();
+    let addr = serve(&pki.server(false)).await.unwrap();
+    let files = ClientFiles { server_name: Some("example.com".into()), ..pki.client(None) };
+    let e = hello(&addr, &files).await.unwrap_err();
+    assert!(e.to_string().contains("TLS"), "{}", e);
+}
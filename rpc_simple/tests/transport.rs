@@ -0,0 +1,183 @@
+use futures::{
+    channel::mpsc,
+    future::{self, Ready},
+    prelude::*,
+};
+use service::{
+    codec::{self, Format},
+    envelope::{self, Envelope},
+    journal::StartFrom,
+    pubsub::{publisher, subscriber, Publisher},
+    transport::{Address, Listener},
+    World, WorldClient,
+};
+use std::{fs, io, net::SocketAddr, os::unix::net::UnixListener, path::PathBuf, time::Duration};
+use tarpc::{
+    client, context,
+    server::{self, Channel},
+};
+
+use publisher::Publisher as _;
+use subscriber::Subscriber as _;
+
+#[test]
+fn address_from_str() {
+    let tcp: SocketAddr = "127.0.0.1:8000".parse().unwrap();
+    assert_eq!("tcp:127.0.0.1:8000".parse::<Address>().unwrap(), Address::Tcp(tcp));
+    assert_eq!("127.0.0.1:8000".parse::<Address>().unwrap(), Address::Tcp(tcp));
+    assert_eq!(" unix:/tmp/rpc.sock ".parse::<Address>().unwrap(), Address::Unix(PathBuf::from("/tmp/rpc.sock")));
+    assert_eq!("mem:publisher".parse::<Address>().unwrap(), Address::Mem("publisher".into()));
+
+    for addr in vec!["unix:", "mem:"] {
+        let e = addr.parse::<Address>().unwrap_err();
+        assert!(e.contains("missing path or name"), "{}: {}", addr, e);
+    }
+    assert!("tcp:127.0.0.1".parse::<Address>().is_err());
+    assert!("nowhere".parse::<Address>().is_err());
+}
+
+#[test]
+fn address_reads_as_it_prints() {
+    for addr in vec!["tcp:127.0.0.1:8000", "unix:/tmp/rpc.sock", "mem:publisher"] {
+        assert_eq!(addr.parse::<Address>().unwrap().to_string(), addr);
+    }
+}
+
+#[tokio::test]
+async fn bind_replaces_stale_socket_only() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let file = dir.path().join("not-a-socket");
+    fs::write(&file, "keep me").unwrap();
+    let e = Listener::bind(&Address::Unix(file.clone())).await.err().expect("bound over a file");
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "keep me");
+
+    // the file stays when a listener goes away without cleaning up
+    let stale = dir.path().join("stale.sock");
+    drop(UnixListener::bind(&stale).unwrap());
+    let listener = Listener::bind(&Address::Unix(stale.clone())).await.unwrap();
+    let e = Listener::bind(&Address::Unix(stale)).await.err().expect("bound twice");
+    assert_eq!(e.kind(), io::ErrorKind::AddrInUse);
+    drop(listener);
+}
+
+#[tokio::test]
+async fn mem_names_are_taken_until_dropped() {
+    let addr: Address = "mem:taken".parse().unwrap();
+    let listener = Listener::bind(&addr).await.unwrap();
+    assert_eq!(Listener::bind(&addr).await.err().expect("bound twice").kind(), io::ErrorKind::AddrInUse);
+    drop(listener);
+    Listener::bind(&addr).await.unwrap();
+}
+
+#[derive(Clone)]
+struct HelloServer;
+
+impl World for HelloServer {
+    type HelloFut = Ready<String>;
+
+    fn hello(self, _: context::Context, name: String) -> Self::HelloFut {
+        future::ready(format!("Hello, {}!", name))
+    }
+}
+
+/// serves hello on `addr`, returns where it listens
+async fn serve_world(addr: &str) -> Address {
+    let incoming = codec::listen(&addr.parse().unwrap(), vec![Format::Json, Format::Bincode], None).await.unwrap();
+    let addr = incoming.local_addr().clone();
+    tokio::spawn(
+        incoming
+            .filter_map(|r| future::ready(r.ok()))
+            .map(server::BaseChannel::with_defaults)
+            .for_each_concurrent(None, |channel| channel.respond_with(HelloServer.serve()).execute()),
+    );
+    addr
+}
+
+async fn hello(addr: &Address) -> String {
+    let transport = codec::connect(addr, &[Format::Bincode], None).await.unwrap();
+    let mut client = WorldClient::new(client::Config::default(), transport).spawn().unwrap();
+    client.hello(context::current(), "transport".into()).await.unwrap()
+}
+
+#[tokio::test]
+async fn world_over_mem() {
+    let addr = serve_world("mem:world").await;
+    assert_eq!(hello(&addr).await, "Hello, transport!");
+}
+
+#[tokio::test]
+async fn world_over_unix() {
+    let dir = tempfile::tempdir().unwrap();
+    let addr = serve_world(&format!("unix:{}", dir.path().join("world.sock").display())).await;
+    assert_eq!(hello(&addr).await, "Hello, transport!");
+}
+
+/// hands what it receives to the test
+#[derive(Clone)]
+struct Inbox(mpsc::UnboundedSender<Envelope>);
+
+impl subscriber::Subscriber for Inbox {
+    type ReceiveFut = Ready<Result<(), String>>;
+
+    fn receive(self, _: context::Context, envelope: Envelope) -> Self::ReceiveFut {
+        future::ready(self.0.unbounded_send(envelope).map_err(|e| e.to_string()))
+    }
+
+    type PingFut = Ready<()>;
+
+    fn ping(self, _: context::Context) -> Self::PingFut {
+        future::ready(())
+    }
+}
+
+/// a publisher on `publisher_addr`, one subscriber on `subscriber_addr` and one message between them
+async fn publish_and_receive(publisher_addr: &str, subscriber_addr: &str) {
+    let incoming = codec::listen(&publisher_addr.parse().unwrap(), vec![Format::Json], None).await.unwrap();
+    let publisher_addr = incoming.local_addr().clone();
+    let publisher = Publisher::new();
+    tokio::spawn(
+        incoming
+            .filter_map(|r| future::ready(r.ok()))
+            .map(server::BaseChannel::with_defaults)
+            .for_each_concurrent(None, move |channel| channel.respond_with(publisher.clone().serve()).execute()),
+    );
+
+    let (inbox, mut received) = mpsc::unbounded();
+    let incoming = codec::listen(&subscriber_addr.parse().unwrap(), Format::ALL.to_vec(), None).await.unwrap();
+    let subscriber_addr = incoming.local_addr().clone();
+    tokio::spawn(
+        incoming
+            .filter_map(|r| future::ready(r.ok()))
+            .map(server::BaseChannel::with_defaults)
+            .for_each_concurrent(None, move |channel| channel.respond_with(Inbox(inbox.clone()).serve()).execute()),
+    );
+
+    let transport = codec::connect(&publisher_addr, &[Format::Json], None).await.unwrap();
+    let mut client = publisher::PublisherClient::new(client::Config::default(), transport).spawn().unwrap();
+    client.subscribe(context::current(), 7, subscriber_addr, vec!["trades.#".into()], StartFrom::Live)
+        .await.unwrap().unwrap();
+    let (content_type, body) = envelope::encode(&"market opens".to_string()).unwrap();
+    let report = client.publish(context::current(), "trades.000001".into(), content_type, body)
+        .await.unwrap().unwrap();
+    assert_eq!(report.acked, vec![7]);
+
+    let envelope = tokio::time::timeout(Duration::from_secs(5), received.next()).await.unwrap().unwrap();
+    assert_eq!(envelope.topic, "trades.000001");
+    assert_eq!(envelope.decode::<String>().unwrap(), "market opens");
+}
+
+#[tokio::test]
+async fn pubsub_over_mem() {
+    publish_and_receive("mem:pubsub-publisher", "mem:pubsub-subscriber").await;
+}
+
+#[tokio::test]
+async fn pubsub_over_unix() {
+    let dir = tempfile::tempdir().unwrap();
+    publish_and_receive(
+        &format!("unix:{}", dir.path().join("publisher.sock").display()),
+        &format!("unix:{}", dir.path().join("subscriber.sock").display()),
+    ).await;
+}
@@ -0,0 +1,304 @@
+use crate::app::{Interactive, InteractiveWidget};
+use crate::keymap::{self, Binding, Key, Section};
+use crate::Event;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tui::{
+    backend::Backend,
+    terminal::Frame,
+
+    style::{Color, Modifier, Style},
+    widgets::*,
+    layout::*,
+};
+
+/// alerts kept in the panel, the oldest go first
+const MAX_ALERTS: usize = 1000;
+/// snooze from the panel without a duration
+pub const SNOOZE_TIME: Duration = Duration::from_secs(10 * 60);
+
+/// an alert rule in the config, e.g. `{ name = "rejected", table = "trades", when = "status == Error" }`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    pub name  : String,
+    pub table : String,
+    /// `<column> <op> <value>`, op is one of `== != > >= < <= crosses`
+    pub when  : String,
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Eq(String),
+    Ne(String),
+    Gt(f64),
+    Ge(f64),
+    Lt(f64),
+    Le(f64),
+    Crosses(f64),
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub name   : String,
+    pub table  : String,
+    pub column : String,
+    op         : Op,
+}
+
+impl Rule {
+    pub fn parse(config: &RuleConfig) -> Result<Self, String> {
+        let mut parts = config.when.trim().splitn(3, char::is_whitespace);
+        let (column, op, value) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(column), Some(op), Some(value)) => (column, op, value.trim()),
+            _ => return Err(format!("alert {}: expect `<column> <op> <value>`, got {}", config.name, config.when)),
+        };
+        let number = || value.parse::<f64>().map_err(|_| format!("alert {}: {} needs a number, got {}", config.name, op, value));
+        let op = match op {
+            "==" | "="  => Op::Eq(value.to_string()),
+            "!="        => Op::Ne(value.to_string()),
+            ">"         => Op::Gt(number()?),
+            ">="        => Op::Ge(number()?),
+            "<"         => Op::Lt(number()?),
+            "<="        => Op::Le(number()?),
+            "crosses"   => Op::Crosses(number()?),
+            _           => return Err(format!("alert {}: unknown op {}", config.name, op)),
+        };
+        Ok(Self { name: config.name.clone(), table: config.table.clone(), column: column.to_string(), op })
+    }
+
+    fn holds(&self, cell: &str) -> bool {
+        let number = cell.trim().parse::<f64>();
+        match (&self.op, number) {
+            (Op::Eq(value), _)        => cell == value,
+            (Op::Ne(value), _)        => cell != value,
+            (Op::Gt(level), Ok(n))    => n > *level,
+            (Op::Ge(level), Ok(n))    => n >= *level,
+            (Op::Lt(level), Ok(n))    => n < *level,
+            (Op::Le(level), Ok(n))    => n <= *level,
+            _                         => false,
+        }
+    }
+
+    /// true when the rule starts to hold with this change, so a row alerts once and not on every update
+    pub fn fires(&self, old: Option<&str>, new: &str) -> bool {
+        match self.op {
+            Op::Crosses(level) => match (old.map(|o| o.trim().parse::<f64>()), new.trim().parse::<f64>()) {
+                (Some(Ok(before)), Ok(after)) => (before < level) != (after < level),
+                _                             => false,
+            },
+            _ => self.holds(new) && !old.map_or(false, |old| self.holds(old)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub at    : DateTime<Local>,
+    pub rule  : String,
+    pub table : String,
+    pub text  : String,
+    pub acked : bool,
+}
+
+impl Alert {
+    pub fn new(rule: &Rule, text: String) -> Self {
+        Self { at: Local::now(), rule: rule.name.clone(), table: rule.table.clone(), text, acked: false }
+    }
+}
+
+/// fired alerts, newest last, they can be acknowledged and their rule snoozed.
+#[derive(Default)]
+pub struct IAlerts {
+    alerts     : Vec<Alert>,
+    snoozed    : HashMap<String, Instant>, // rule to end of snooze
+    idx_page   : usize,
+    idx_select : usize,
+    window     : Rect,
+}
+
+impl IAlerts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, alert: Alert) {
+        let follow = self.alerts.is_empty() || self.idx_select + 1 == self.alerts.len();
+        self.alerts.push(alert);
+        if self.alerts.len() > MAX_ALERTS {
+            self.alerts.remove(0);
+            self.idx_select = self.idx_select.saturating_sub(1);
+            self.idx_page = self.idx_page.saturating_sub(1);
+        }
+        if follow { self.select_last(); }
+    }
+
+    pub fn unacked(&self) -> usize {
+        self.alerts.iter().filter(|a| !a.acked).count()
+    }
+
+    /// snoozed rules neither flash nor notify
+    pub fn is_snoozed(&mut self, rule: &str) -> bool {
+        let now = Instant::now();
+        self.snoozed.retain(|_, until| *until > now);
+        self.snoozed.contains_key(rule)
+    }
+
+    pub fn snooze(&mut self, rule: &str, duration: Duration) {
+        self.snoozed.insert(rule.to_string(), Instant::now() + duration);
+        for alert in self.alerts.iter_mut().filter(|a| a.rule == rule) {
+            alert.acked = true;
+        }
+    }
+
+    pub fn ack_all(&mut self) -> usize {
+        let count = self.unacked();
+        for alert in self.alerts.iter_mut() {
+            alert.acked = true;
+        }
+        count
+    }
+
+    fn ack_current(&mut self) {
+        if let Some(alert) = self.alerts.get_mut(self.idx_select) {
+            alert.acked = true;
+        }
+    }
+
+    fn snooze_current(&mut self, duration: Duration) -> Result<String, String> {
+        let rule = self.alerts.get(self.idx_select).map(|a| a.rule.clone()).ok_or("no alert selected")?;
+        self.snooze(&rule, duration);
+        Ok(rule)
+    }
+
+    fn get_content_height(&self) -> usize {
+        self.window.height.saturating_sub(2) as usize
+    }
+
+    fn wrap_page(&mut self) {
+        let height = self.get_content_height().max(1);
+        if self.idx_select < self.idx_page {
+            self.idx_page = self.idx_select;
+        } else if self.idx_select >= self.idx_page + height {
+            self.idx_page = self.idx_select + 1 - height;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum AlertKey {
+    Ack,
+    AckAll,
+    Snooze,
+}
+
+const ALERT_KEYS: &[Binding<AlertKey>] = &[
+    Binding { keys: &[Key::Char('a')], action: AlertKey::Ack,    help: "acknowledge alert" },
+    Binding { keys: &[Key::Char('A')], action: AlertKey::AckAll, help: "acknowledge all alerts" },
+    Binding { keys: &[Key::Char('s')], action: AlertKey::Snooze, help: "snooze the rule for 10 minutes" },
+];
+
+impl Interactive for IAlerts {
+    fn select_up(&mut self) {
+        self.idx_select = self.idx_select.saturating_sub(1);
+    }
+
+    fn select_down(&mut self) {
+        if self.idx_select + 1 < self.alerts.len() {
+            self.idx_select += 1;
+        }
+    }
+
+    fn select_first(&mut self) {
+        self.idx_select = 0;
+    }
+
+    fn select_last(&mut self) {
+        self.idx_select = self.alerts.len().saturating_sub(1);
+    }
+
+    fn select_page_up(&mut self) {
+        self.idx_select = self.idx_select.saturating_sub(self.get_content_height());
+    }
+
+    fn select_page_down(&mut self) {
+        self.idx_select = (self.idx_select + self.get_content_height()).min(self.alerts.len().saturating_sub(1));
+    }
+
+    fn select_on_key(&mut self, event: &Event) -> bool {
+        match keymap::lookup(ALERT_KEYS, event) {
+            Some(AlertKey::Ack)    => self.ack_current(),
+            Some(AlertKey::AckAll) => { self.ack_all(); },
+            Some(AlertKey::Snooze) => { let _ = self.snooze_current(SNOOZE_TIME); },
+            None                   => return false,
+        }
+        true
+    }
+
+    fn keys(&self) -> Vec<Section> {
+        vec![Section::new("alerts", ALERT_KEYS)]
+    }
+
+    fn summary(&self) -> Option<String> {
+        Some(format!("{} unacked", self.unacked()))
+    }
+
+    fn click(&mut self, _xpos: u16, ypos: u16) {
+        if ypos >= 1 && self.idx_page + (ypos as usize - 1) < self.alerts.len() {
+            self.idx_select = self.idx_page + ypos as usize - 1;
+        }
+    }
+
+    fn on_command(&mut self, name: &str, args: &[&str]) -> Result<(), String> {
+        match (name, args) {
+            ("ack", [])         => self.ack_current(),
+            ("ack", ["all"])    => { self.ack_all(); },
+            ("snooze", [])      => { self.snooze_current(SNOOZE_TIME)?; },
+            ("snooze", [mins])  => {
+                let mins: u64 = mins.parse().map_err(|_| format!("bad minutes {}", mins))?;
+                self.snooze_current(Duration::from_secs(mins * 60))?;
+            },
+            _ => return Err(format!("bad arguments for {}: {:?}", name, args)),
+        }
+        Ok(())
+    }
+}
+
+impl<B: Backend> InteractiveWidget<B> for IAlerts {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, name: &str, is_active: bool) {
+        self.window = area;
+        self.wrap_page();
+
+        let now = Instant::now();
+        let idx_end = self.alerts.len().min(self.idx_page + self.get_content_height());
+        let mut lines = Vec::with_capacity(idx_end - self.idx_page);
+        for (idx, alert) in self.alerts[self.idx_page..idx_end].iter().enumerate() {
+            let snoozed = self.snoozed.get(&alert.rule).map_or(false, |until| *until > now);
+            let mut style = match (alert.acked, snoozed) {
+                (false, _)    => Style::default().fg(Color::Red).modifier(Modifier::BOLD),
+                (true, false) => Style::default().fg(Color::Gray),
+                (true, true)  => Style::default().fg(Color::DarkGray),
+            };
+            if self.idx_page + idx == self.idx_select { style = style.modifier(Modifier::REVERSED); }
+            lines.push(Text::styled(
+                format!("{} {:<10} {}: {}", alert.at.format("%H:%M:%S"), alert.rule, alert.table, alert.text),
+                style,
+            ));
+        }
+
+        let unacked = self.unacked();
+        let title = if unacked > 0 { format!("{} [{} new]", name, unacked) } else { name.to_string() };
+        f.render_widget(
+            List::new(lines.into_iter())
+            .block(Block::default()
+                .title(&title)
+                .borders(Borders::ALL)
+                .border_style(if is_active { Style::default().fg(Color::Red) } else { Style::default().fg(Color::White) })
+                .title_style(Style::default().fg(Color::Yellow))),
+            area
+        );
+    }
+}
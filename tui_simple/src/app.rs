@@ -1,11 +1,19 @@
 #![allow(dead_code)]
 use crate::Event;
+use crate::alert::{Alert, IAlerts, Rule, RuleConfig};
+use crate::command::{self, Action, AppCommands, Command};
+use crate::config::RowConfig;
+use crate::keymap::{self, Binding, Key, KeyHelp, Section};
+use crate::record::{Entry, Recorder};
+use crate::registry::Registry;
+use crate::session::Session;
+use crate::status::StatusBar;
+use crate::source::Update;
+use crate::remote::{RemoteCommand, Request};
+use crate::chart::IChart;
+use crate::table::ITable;
 
-use crossterm::{
-    event::{self, Event as CEvent, KeyEvent, MouseEvent, KeyCode},
-};
 use std::collections::HashMap;
-use std::ffi::c_void;
 use std::time::{Duration, Instant};
 use std::sync::mpsc;
 use std::any::Any;
@@ -18,44 +26,43 @@ use tui::{
     style::{Color, Modifier, Style},
     widgets::*,
     layout::*,
-    Terminal,
 };
 
-pub trait InteractiveWidget2 {
-    fn select_up2(&mut self) -> u32 { 0 } // up k
-    fn select_down2(&mut self) { } // up k
-}
-
-pub trait InteractiveWidget {
+/// keys, commands and state of a widget, everything but drawing.
+pub trait Interactive: AsAny {
     fn select_up(&mut self) { } // up k
     fn select_down(&mut self) { } // down j
     fn select_first(&mut self) {  } // gg
     fn select_last(&mut self) {  } // G
     fn select_page_up(&mut self) {  } // Ctrl-U
     fn select_page_down(&mut self) {  } // Ctrl-D
-    fn select_on_key(&mut self) {  }
+    fn select_on_key(&mut self, _event: &Event) -> bool { false } // widget own keys, true if used
+    fn capturing(&self) -> bool { false } // wants every key, e.g. while editing a cell
+    fn summary(&self) -> Option<String> { None } // short text for the status bar
     fn click(&mut self, _x: u16, _y: u16) {  } // relative click
-    fn draw<B: Backend>(&mut self, _f: &mut Frame<B>, area: Rect, name: &'static str, is_active: bool) { }
     fn selectable(&self) -> bool { true }
+    fn keys(&self) -> Vec<Section> { Vec::new() } // bindings behind select_on_key, for the help
+    fn on_command(&mut self, name: &str, _args: &[&str]) -> Result<(), String> { // :name args
+        Err(format!("{} not supported by this widget", name))
+    }
+    fn save_state(&self) -> Option<serde_json::Value> { None }
+    fn restore_state(&mut self, _state: serde_json::Value) {  }
+}
+
+/// a widget the app can lay out, kept as `Box<dyn InteractiveWidget<B>>`.
+pub trait InteractiveWidget<B: Backend>: Interactive {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, name: &str, is_active: bool);
 }
 
-macro_rules! dispatch {
-    ($ptr:expr, $target:ty , $fun:ident, ($($arg:expr ),*)) => { {
-        let val: &mut $target = unsafe { &mut *($ptr as *mut $target) };
-        val.$fun($($arg),*)
-
-    } };
-    ($ptr: expr, $tag:expr, [ $( ($kind:expr, $target:ty) ),* ], $fun:ident, [ $($arg:expr),* ]) => {
-        dispatch!(@call_tuple $ptr, $tag, [ $(($kind, $target)),* ], $fun, ($($arg),*))
-    };
-    (@call_tuple $ptr: expr, $tag: expr, [ $( ($kind:expr, $target:ty) ),* ], $fun:ident, $tuple:tt) => {
-        match $tag.as_ref() {
-            $(
-                $kind => { dispatch!($ptr, $target, $fun, $tuple) },
-            )*
-            _ => panic!("select_up not implemented for {}", $tag),
-        }
-    };
+/// lets the app get the concrete widget back, see `App::widget`
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any { self }
+    fn as_any_mut(&mut self) -> &mut dyn Any { self }
 }
 
 #[derive(Clone, Debug)]
@@ -71,348 +78,892 @@ impl Default for Status {
     }
 }
 
-macro_rules! impl_app {
-    ($cls:ty, $map:ident, [ $( ($name:ident, $kind:ty) ),* ] ) => { paste::item! {
-        impl $cls {
-        $(
-            fn $name(&self) -> &$kind {
-                let ptr: *const c_void = *self.$map.get(stringify!($name)).unwrap();
-                unsafe { & *(ptr as *const $kind) }
-            }
+const MAX_HISTORY: usize = 100;
+/// prices kept in the watch list sparkline
+pub const TREND_LEN: usize = 12;
+/// updates applied per tick, a burst is spread over ticks instead of freezing the ui
+const MAX_UPDATES_PER_TICK: usize = 20_000;
+/// how long a row that raised an alert flashes
+const FLASH_TIME: Duration = Duration::from_secs(3);
+/// lines per page in the help
+const HELP_PAGE: usize = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum AppKey {
+    FocusLeft,
+    FocusRight,
+    FocusDown,
+    FocusUp,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    WaitG,
+    Last,
+    Command,
+    Help,
+    Zoom,
+    NextTab,
+    PrevTab,
+    Quit,
+    Click,
+}
 
-            fn [<$name _mut>](&mut self) -> &mut $kind {
-                let ptr: *mut c_void = *self.$map.get(stringify!($name)).unwrap();
-                unsafe { &mut *(ptr as *mut $kind) }
-            }
-        )*
+/// normal mode, checked before the keys of the focused widget
+const NORMAL_KEYS: &[Binding<AppKey>] = &[
+    Binding { keys: &[Key::Ctrl('h')],                          action: AppKey::FocusLeft,  help: "focus widget on the left" },
+    Binding { keys: &[Key::Ctrl('l')],                          action: AppKey::FocusRight, help: "focus widget on the right" },
+    Binding { keys: &[Key::Ctrl('j')],                          action: AppKey::FocusDown,  help: "focus widget below" },
+    Binding { keys: &[Key::Ctrl('k')],                          action: AppKey::FocusUp,    help: "focus widget above" },
+    Binding { keys: &[Key::Char('k'), Key::Up, Key::WheelUp],   action: AppKey::Up,         help: "previous line" },
+    Binding { keys: &[Key::Char('j'), Key::Down, Key::WheelDown], action: AppKey::Down,     help: "next line" },
+    Binding { keys: &[Key::Ctrl('u')],                          action: AppKey::PageUp,     help: "page up" },
+    Binding { keys: &[Key::Ctrl('d')],                          action: AppKey::PageDown,   help: "page down" },
+    Binding { keys: &[Key::Char('g')],                          action: AppKey::WaitG,      help: "wait for the next key, see after g" },
+    Binding { keys: &[Key::Char('G')],                          action: AppKey::Last,       help: "last line" },
+    Binding { keys: &[Key::Char(':')],                          action: AppKey::Command,    help: "command line" },
+    Binding { keys: &[Key::Char('?')],                          action: AppKey::Help,       help: "this help" },
+    Binding { keys: &[Key::Char('z')],                          action: AppKey::Zoom,       help: "focused widget on the whole screen, again to go back" },
+    Binding { keys: &[Key::Char(']')],                          action: AppKey::NextTab,    help: "next tab where widgets share a place" },
+    Binding { keys: &[Key::Char('[')],                          action: AppKey::PrevTab,    help: "previous tab where widgets share a place" },
+    Binding { keys: &[Key::Char('q')],                          action: AppKey::Quit,       help: "save session and quit" },
+    Binding { keys: &[Key::Click],                              action: AppKey::Click,      help: "focus widget, or click inside the focused one" },
+];
+
+#[derive(Clone, Copy, Debug)]
+enum GKey {
+    First,
+}
 
-        unsafe fn drop_map(&mut self) {
-            $(
-                let ptr: *mut c_void = *self.$map.get(stringify!($name)).unwrap();
-                let ptr: *mut $kind = unsafe { ptr as *mut $kind };
-                { ptr.drop_in_place(); }
-            )*
-        }
+const G_KEYS: &[Binding<GKey>] = &[
+    Binding { keys: &[Key::Char('g')], action: GKey::First, help: "first line" },
+];
+
+#[derive(Clone, Copy, Debug)]
+enum PromptKey {
+    Run,
+    Prev,
+    Next,
+    Erase,
+    Clear,
+    Cancel,
+    Type,
+}
+
+const PROMPT_KEYS: &[Binding<PromptKey>] = &[
+    Binding { keys: &[Key::Enter],     action: PromptKey::Run,    help: "run the command" },
+    Binding { keys: &[Key::Up],        action: PromptKey::Prev,   help: "previous command in history" },
+    Binding { keys: &[Key::Down],      action: PromptKey::Next,   help: "next command in history" },
+    Binding { keys: &[Key::Backspace], action: PromptKey::Erase,  help: "delete last character" },
+    Binding { keys: &[Key::Ctrl('w')], action: PromptKey::Clear,  help: "clear the line" },
+    Binding { keys: &[Key::Esc],       action: PromptKey::Cancel, help: "back to normal mode" },
+    Binding { keys: &[Key::Text],      action: PromptKey::Type,   help: "type the command, see commands" },
+];
+
+#[derive(Clone, Copy, Debug)]
+enum HelpKey {
+    Close,
+    Search,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+}
+
+const HELP_KEYS: &[Binding<HelpKey>] = &[
+    Binding { keys: &[Key::Esc, Key::Char('q'), Key::Char('?')],  action: HelpKey::Close,    help: "close the help" },
+    Binding { keys: &[Key::Char('/')],                            action: HelpKey::Search,   help: "search keys and commands" },
+    Binding { keys: &[Key::Char('k'), Key::Up, Key::WheelUp],     action: HelpKey::Up,       help: "scroll up" },
+    Binding { keys: &[Key::Char('j'), Key::Down, Key::WheelDown], action: HelpKey::Down,     help: "scroll down" },
+    Binding { keys: &[Key::Ctrl('u')],                            action: HelpKey::PageUp,   help: "page up" },
+    Binding { keys: &[Key::Ctrl('d')],                            action: HelpKey::PageDown, help: "page down" },
+];
+
+#[derive(Clone, Copy, Debug)]
+enum SearchKey {
+    Done,
+    Cancel,
+    Erase,
+    Type,
+}
+
+const SEARCH_KEYS: &[Binding<SearchKey>] = &[
+    Binding { keys: &[Key::Enter],     action: SearchKey::Done,   help: "keep the search and scroll" },
+    Binding { keys: &[Key::Esc],       action: SearchKey::Cancel, help: "show everything again" },
+    Binding { keys: &[Key::Backspace], action: SearchKey::Erase,  help: "delete last character" },
+    Binding { keys: &[Key::Text],      action: SearchKey::Type,   help: "type what to look for" },
+];
+
+/// the `?` overlay
+#[derive(Default)]
+struct Help {
+    query  : String,
+    typing : bool,
+    scroll : usize,
+}
+
+/// every binding that applies now, then the commands
+fn help_lines(sections: &[Section], query: &str) -> Vec<Text<'static>> {
+    let query = query.to_lowercase();
+    let mut lines = Vec::new();
+    for section in sections.iter() {
+        let in_title = section.title.to_lowercase().contains(&query);
+        let keys: Vec<&KeyHelp> = section.keys.iter()
+            .filter(|k| in_title || k.keys.to_lowercase().contains(&query) || k.help.to_lowercase().contains(&query))
+            .collect();
+        if keys.is_empty() { continue; }
+        lines.push(Text::styled(section.title.clone(), Style::default().fg(Color::Yellow).modifier(Modifier::BOLD)));
+        for key in keys {
+            lines.push(Text::raw(format!("  {:<24} {}", key.keys, key.help)));
         }
-    } }
+    }
+    lines
+}
+
+fn command_section() -> Section {
+    let keys = command::COMMANDS.iter().map(|c| KeyHelp {
+        keys : format!(":{} {}", c.name, c.args).trim_end().to_string(),
+        help : c.help,
+    }).collect();
+    Section { title: "commands".into(), keys }
+}
+
+/// part of `area` in its middle
+fn centered(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let width = (area.width as u32 * percent_x as u32 / 100) as u16;
+    let height = (area.height as u32 * percent_y as u32 / 100) as u16;
+    Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height)
+}
+
+/// one widget placed by the layout config
+struct Panel<B: Backend> {
+    name   : String,
+    title  : String,
+    widget : Box<dyn InteractiveWidget<B>>,
+    row    : usize,
+    width  : u16, // percent of the row
+    min_width  : u16,
+    min_height : u16,
+    priority   : u8,
+    focused_at : u64, // tabs show the one focused last
+    area   : Rect, // where it was drawn last, empty while hidden in tabs
+}
+
+/// widgets sharing one place on screen, more than one are drawn as tabs
+struct Slot {
+    panels : Vec<usize>,
+    width  : u16, // percent of the row
 }
 
-macro_rules! impl_app_dispatch {
-    (ret $self:ident, $map:expr, $focus:expr, [ $( ($tag:expr, $getter:ident) ),* ], $fun:ident, [ $($arg:expr),* ]) => {
-        impl_app_dispatch!(@ret_call_tuple $self, $map, $focus, [ $( ($tag, $getter) ),* ], $fun, ( $($arg),* ))
-    };
-    (@ret_call_tuple $self:ident, $map:expr, $focus:expr, [ $( ($tag:expr, $getter:ident) ),* ], $fun:ident, $tuple:tt) => {
-        match $focus.as_ref() {
-            $( $tag => impl_app_dispatch!(@call $self.$getter(), $fun, $tuple), )*
-            _ => unreachable!()
-        }
-    };
-    (@call $at:expr, $fun:ident, ( $($arg:expr),* )) => {
-       $at.$fun( $($arg),* )
-    };
-    ($self:ident, $map:expr, $focus:expr, [ $( ($tag:expr, $getter:ident) ),* ], $fun:ident, [ $($arg:expr),* ]) => {
-        impl_app_dispatch!(@call_tuple $self, $map, $focus, [ $( ($tag, $getter) ),* ], $fun, ( $($arg),* ))
-    };
-    (@call_tuple $self:ident, $map:expr, $focus:expr, [ $( ($tag:expr, $getter:ident) ),* ], $fun:ident, $tuple:tt) => {
-        match $focus.as_ref() {
-            $( $tag => { impl_app_dispatch!(@call $self.$getter(), $fun, $tuple); }, )*
-            _ => {  }
-        }
-    };
+/// a row of the layout after folding widgets into tabs
+struct RowPlan {
+    height : u16, // percent of the screen
+    slots  : Vec<Slot>,
 }
 
-pub struct App {
-    widgets: HashMap<&'static str, *mut c_void>,
-    widgets_location: HashMap<&'static str, Rect>,
+pub struct App<B: Backend> {
+    panels      : Vec<Panel<B>>,
+    heights     : Vec<u16>, // percent of the screen per row
+    curr        : Option<usize>,
+    focus_count : u64,
+    slots       : Vec<(Rect, Vec<usize>)>, // places drawn last, focus moves between them
+    zoom        : bool,
 
-    pub curr_widget: Option<&'static str>,
     pub status: Status,
     stack_kevent_time: Instant,
 
     receiver: mpsc::Receiver<String>,
+    updates: mpsc::Receiver<Update>,
+    remote: Option<mpsc::Receiver<Request>>,
+
+    pub layout: String,
+    history: Vec<String>,
+    idx_history: Option<usize>,
+
+    bar: StatusBar,
+    rules: Vec<Rule>,
+    help: Option<Help>,
+    quit: bool,
+    recorder: Option<Recorder>,
+    size: Rect, // last drawn, a change is recorded
 }
 
-impl_app!(
-    App, widgets,
-    [
-        (assets, IEmpty), (positions, IEmpty), (orders, IEmpty),
-        (trades, ITable), (logs, IParagraph)
-    ]
-);
-
-/// this is where you set layout and event handler.
-/// just use 'static element. It's clear and easy to access.
-impl InteractiveWidget for App {
-    fn select_page_up(&mut self) {
-        if let Some(curr_tag) = self.curr_widget {
-            impl_app_dispatch!(self, self.widgets, curr_tag, [
-                ("assets", assets_mut), ("positions", positions_mut), ("orders", orders_mut),
-                ("trades", trades_mut), ("logs", logs_mut)
-            ], select_page_up, [  ])
-        }
+fn capitalize(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None    => String::new(),
     }
+}
 
-    fn select_page_down(&mut self) {
-        if let Some(curr_tag) = self.curr_widget {
-            impl_app_dispatch!(self, self.widgets, curr_tag, [
-                ("assets", assets_mut), ("positions", positions_mut), ("orders", orders_mut),
-                ("trades", trades_mut), ("logs", logs_mut)
-            ], select_page_down, [  ])
-        }
+/// `total` cells split by `weights`, the last part takes what rounding leaves over
+fn split(total: u16, weights: &[u16]) -> Vec<u16> {
+    let sum = weights.iter().map(|&w| w as u32).sum::<u32>().max(1);
+    let mut parts: Vec<u16> = weights.iter().map(|&w| (total as u32 * w as u32 / sum) as u16).collect();
+    let used: u16 = parts.iter().sum();
+    if let Some(last) = parts.last_mut() {
+        *last += total - used;
     }
+    parts
+}
 
-    fn select_up(&mut self) {
-        if let Some(curr_tag) = self.curr_widget {
-            impl_app_dispatch!(self, self.widgets, curr_tag, [
-                ("assets", assets_mut), ("positions", positions_mut), ("orders", orders_mut),
-                ("trades", trades_mut), ("logs", logs_mut)
-            ], select_up, [  ])
-        }
+/// columns between `x` and the area, 0 if inside
+fn distance(area: Rect, x: u16) -> u16 {
+    if x < area.left() {
+        area.left() - x
+    } else if x >= area.right() {
+        x + 1 - area.right()
+    } else {
+        0
     }
+}
 
-    fn select_down(&mut self) {
-        if let Some(curr_tag) = self.curr_widget {
-            impl_app_dispatch!(self, self.widgets, curr_tag, [
-                ("assets", assets_mut), ("positions", positions_mut), ("orders", orders_mut),
-                ("trades", trades_mut), ("logs", logs_mut)
-            ], select_down, [  ])
+// 'static as the widgets are boxed trait objects
+impl<B: Backend + 'static> App<B> {
+    /// widgets come from `rows`, each built by its kind in `registry`
+    pub fn new(
+        rx: mpsc::Receiver<String>, updates: mpsc::Receiver<Update>, layout: String,
+        rows: &[RowConfig], registry: &Registry<B>,
+    ) -> Result<Self, String> {
+        let mut slf = Self {
+            panels            : Vec::new(),
+            heights           : Vec::new(),
+            curr              : None,
+            focus_count       : 0,
+            slots             : Vec::new(),
+            zoom              : false,
+            status            : Default::default(),
+            stack_kevent_time : Instant::now(),
+            receiver          : rx,
+            updates,
+            remote            : None,
+            layout,
+            history           : Default::default(),
+            idx_history       : Default::default(),
+            bar               : StatusBar::new(),
+            rules             : Vec::new(),
+            help              : None,
+            quit              : false,
+            recorder          : None,
+            size              : Rect::default(),
+        };
+
+        for (idx, row) in rows.iter().enumerate() {
+            if row.widgets.is_empty() {
+                return Err(format!("row {} of the layout has no widgets", idx + 1));
+            }
+            slf.heights.push(row.height);
+            for config in row.widgets.iter() {
+                if slf.panel_index(&config.name).is_some() {
+                    return Err(format!("two widgets named {}", config.name));
+                }
+                let widget = registry.build(&config.kind, &config.options)
+                    .map_err(|e| format!("widget {}: {}", config.name, e))?;
+                slf.panels.push(Panel {
+                    name   : config.name.clone(),
+                    title  : config.title.clone().unwrap_or_else(|| capitalize(&config.name)),
+                    widget,
+                    row    : idx,
+                    width  : config.width,
+                    min_width  : config.min_width,
+                    min_height : config.min_height,
+                    priority   : config.priority,
+                    focused_at : 0,
+                    area   : Rect::default(),
+                });
+            }
         }
+        Ok(slf)
     }
 
-    fn select_last(&mut self) {
-        if let Some(curr_tag) = self.curr_widget {
-            impl_app_dispatch!(self, self.widgets, curr_tag, [
-                ("assets", assets_mut), ("positions", positions_mut), ("orders", orders_mut),
-                ("trades", trades_mut), ("logs", logs_mut)
-            ], select_last, [  ])
-        }
+    fn panel_index(&self, name: &str) -> Option<usize> {
+        self.panels.iter().position(|p| p.name == name)
     }
 
-    fn select_first(&mut self) {
-        if let Some(curr_tag) = self.curr_widget {
-            impl_app_dispatch!(self, self.widgets, curr_tag, [
-                ("assets", assets_mut), ("positions", positions_mut), ("orders", orders_mut),
-                ("trades", trades_mut), ("logs", logs_mut)
-            ], select_first, [  ])
+    pub fn curr_widget(&self) -> Option<&str> {
+        self.curr.map(|idx| self.panels[idx].name.as_str())
+    }
+
+    /// the widget named `name` if it is a `T`
+    pub fn widget<T: Any>(&self, name: &str) -> Option<&T> {
+        let panel = &self.panels[self.panel_index(name)?];
+        AsAny::as_any(&*panel.widget).downcast_ref()
+    }
+
+    pub fn widget_mut<T: Any>(&mut self, name: &str) -> Option<&mut T> {
+        let idx = self.panel_index(name)?;
+        AsAny::as_any_mut(&mut *self.panels[idx].widget).downcast_mut()
+    }
+
+    /// the first widget that is a `T`, for the ones the app feeds itself
+    fn first_mut<T: Any>(&mut self) -> Option<&mut T> {
+        self.panels.iter_mut().find_map(|p| AsAny::as_any_mut(&mut *p.widget).downcast_mut())
+    }
+
+    fn focused(&self) -> Option<&dyn InteractiveWidget<B>> {
+        self.curr.map(|idx| &*self.panels[idx].widget)
+    }
+
+    fn focused_mut(&mut self) -> Option<&mut (dyn InteractiveWidget<B> + 'static)> {
+        match self.curr {
+            Some(idx) => Some(&mut *self.panels[idx].widget),
+            None      => None,
         }
     }
 
-    fn click(&mut self, xpos: u16, ypos: u16, ) {
-        for (name, rect) in self.widgets_location.iter() {
+    pub fn select_up(&mut self) {
+        if let Some(widget) = self.focused_mut() { widget.select_up(); }
+    }
+
+    pub fn select_down(&mut self) {
+        if let Some(widget) = self.focused_mut() { widget.select_down(); }
+    }
+
+    pub fn select_first(&mut self) {
+        if let Some(widget) = self.focused_mut() { widget.select_first(); }
+    }
+
+    pub fn select_last(&mut self) {
+        if let Some(widget) = self.focused_mut() { widget.select_last(); }
+    }
+
+    pub fn select_page_up(&mut self) {
+        if let Some(widget) = self.focused_mut() { widget.select_page_up(); }
+    }
+
+    pub fn select_page_down(&mut self) {
+        if let Some(widget) = self.focused_mut() { widget.select_page_down(); }
+    }
+
+    pub fn select_on_key(&mut self, event: &Event) -> bool {
+        self.focused_mut().map_or(false, |widget| widget.select_on_key(event))
+    }
+
+    pub fn capturing(&self) -> bool {
+        self.focused().map_or(false, |widget| widget.capturing())
+    }
+
+    pub fn summary(&self) -> Option<String> {
+        self.focused().and_then(|widget| widget.summary())
+    }
+
+    pub fn click(&mut self, xpos: u16, ypos: u16) {
+        for idx in 0..self.panels.len() {
+            let rect = self.panels[idx].area;
             if xpos > rect.left() && xpos < rect.right() && ypos < rect.bottom() && ypos > rect.top() {
-                if self.curr_widget == Some(name) {
-                    info!("Ori posi {} {} {:?}", xpos, ypos, rect);
-                    let xpos = xpos - rect.left(); let ypos = ypos - rect.top();
-                    info!("Ori posi {} {}", xpos, ypos);
-
-                    impl_app_dispatch!(self, self.widgets, name, [
-                        ("assets", assets_mut), ("positions", positions_mut), ("orders", orders_mut),
-                        //("trades", trades_mut),
-                        ("logs", logs_mut)
-                    ], click, [ xpos, ypos  ])
-                } else {
-                    self.curr_widget = Some(name);
+                if self.curr == Some(idx) {
+                    self.panels[idx].widget.click(xpos - rect.left(), ypos - rect.top());
+                } else if self.panels[idx].widget.selectable() {
+                    self.set_focus(idx);
                 }
                 break;
             }
         }
 
-        info!("change foucs pos: {} {} => select: {:?}", xpos, ypos, self.curr_widget);
+        debug!("click {} {} => select: {:?}", xpos, ypos, self.curr_widget());
     }
-}
 
-impl App {
-    pub fn new(rx: mpsc::Receiver<String>) -> Self {
-        let mut slf = Self {
-            widgets           : Default::default(),
-            widgets_location  : Default::default(),
-            curr_widget       : Default::default(),
-            status            : Default::default(),
-            stack_kevent_time : Instant::now(),
-            receiver          : rx,
+    fn widget_command(&mut self, name: &str, args: &[&str]) -> Result<(), String> {
+        match self.focused_mut() {
+            Some(widget) => widget.on_command(name, args),
+            None         => Err("no widget focused".into()),
+        }
+    }
+
+    /// run one `:` command line
+    pub fn execute(&mut self, line: &str) -> Result<(), String> {
+        let mut parts = line.split_whitespace();
+        let name = match parts.next() {
+            Some(name) => name,
+            None       => return Ok(()),
         };
+        let args: Vec<&str> = parts.collect();
+        match command::find(name) {
+            Some(Command { action: Action::Widget, .. })   => self.widget_command(name, &args),
+            Some(Command { action: Action::App(run), .. }) => run(self, &args),
+            None                                           => Err(format!("unknown command: {}", name)),
+        }
+    }
 
-        slf.add_widget("assets", IEmpty::default());
-        slf.add_widget("positions", IEmpty::default());
-        slf.add_widget("orders", IEmpty::default());
+    fn push_history(&mut self, line: &str) {
+        self.idx_history = None;
+        if line.trim().is_empty() || self.history.last().map(|l| l == line).unwrap_or(false) { return; }
+        self.history.push(line.to_string());
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+    }
 
-        let mut trades = ITable::new();
-        trades.add_column(
-            "code", Style::default(), 6,
-            vec!["000001", "000001", "000002"],
-            |_, _| { Style::default().fg(Color::White) }
-        );
-        trades.add_column(
-            "exchange", Style::default(), 8,
-            vec!["SZSE", "SSE", "SZSE"],
-            |_, _| { Style::default().fg(Color::White) }
-        );
-        trades.add_column(
-            "price", Style::default(), 8,
-            vec!["12.23", "12.45", "13.45"],
-            |_, _| { Style::default().fg(Color::White) }
-        );
-        trades.add_column(
-            "volume", Style::default(), 10,
-            vec!["100", "700", "10000"],
-            |_, _| { Style::default().fg(Color::White) }
-        );
-        trades.add_column(
-            "direction", Style::default(), 9,
-            vec!["Buy", "Sell", "Buy"],
-            |r: &str, s: bool| {
-                match (r, s) {
-                    ("Buy", false) => Style::default().fg(Color::Green),
-                    ("Sell", false)   => Style::default().fg(Color::Cyan),
-                    _                  => Style::default().fg(Color::White)
-                }
+    fn history_prev(&mut self) -> Option<String> {
+        if self.history.is_empty() { return None; }
+        let idx = match self.idx_history {
+            Some(idx) => idx.saturating_sub(1),
+            None      => self.history.len() - 1,
+        };
+        self.idx_history = Some(idx);
+        Some(self.history[idx].clone())
+    }
+
+    fn history_next(&mut self) -> Option<String> {
+        match self.idx_history {
+            Some(idx) if idx + 1 < self.history.len() => {
+                self.idx_history = Some(idx + 1);
+                Some(self.history[idx + 1].clone())
+            },
+            Some(_) => { self.idx_history = None; Some(String::new()) },
+            None    => None,
+        }
+    }
+
+    pub fn save_session(&self) -> Session {
+        let mut widgets = HashMap::new();
+        for panel in self.panels.iter() {
+            if let Some(state) = panel.widget.save_state() {
+                widgets.insert(panel.name.clone(), state);
             }
-        );
-        trades.add_column(
-            "status", Style::default(), 8,
-            vec!["Cancel", "Pending", "Error"],
-            |r: &str, s: bool| {
-                match (r, s) {
-                    ("Pending", false) => Style::default().fg(Color::Magenta),
-                    ("Error", false)   => Style::default().fg(Color::Red),
-                    _                  => Style::default().fg(Color::White)
-                }
+        }
+
+        Session {
+            curr_widget : self.curr_widget().map(|n| n.to_string()),
+            widgets,
+            history     : self.history.clone(),
+        }
+    }
+
+    pub fn restore_session(&mut self, session: &Session) {
+        for panel in self.panels.iter_mut() {
+            if let Some(state) = session.widgets.get(&panel.name) {
+                panel.widget.restore_state(state.clone());
             }
-        );
+        }
 
-        slf.add_widget("trades", trades);
-        slf.add_widget("logs", IParagraph::new());
-        slf
+        if let Some(idx) = session.curr_widget.as_ref().and_then(|n| self.panel_index(n)) {
+            self.set_focus(idx);
+        }
+        self.history = session.history.clone();
     }
 
-    fn add_widget<T: InteractiveWidget>(&mut self, name: &'static str, widget: T) {
-        assert!(!self.widgets.contains_key(name), "widget with same name");
-        self.widgets_location.insert(name, Rect::default());
-        self.widgets.insert(name, Box::into_raw(Box::new(widget)) as *mut c_void);
+    fn set_focus(&mut self, idx: usize) {
+        self.focus_count += 1;
+        self.panels[idx].focused_at = self.focus_count;
+        self.curr = Some(idx);
     }
 
-    fn move_left(&mut self) {
-        if let Some(ref mut curr) = self.curr_widget {
-            match *curr {
-                "Trades"    => { *curr = "Logs" }
-                "Logs"      => { *curr = "Trades" }
-                "Assets"    => { *curr = "Orders" }
-                "Positions" => { *curr = "Assets" }
-                "Orders"    => { *curr = "Positions" }
-                _           => {  }
-            }
+    fn focus_first(&mut self) {
+        if let Some(idx) = (0..self.panels.len()).find(|&idx| self.panels[idx].widget.selectable()) {
+            self.set_focus(idx);
         }
     }
 
-    fn move_right(&mut self) {
-        if let Some(ref mut curr) = self.curr_widget {
-            match *curr {
-                "Trades"    => { *curr = "Logs" }
-                "Logs"      => { *curr = "Trades" }
-                "Assets"    => { *curr = "Positions" }
-                "Positions" => { *curr = "Orders" }
-                "Orders"    => { *curr = "Assets" }
-                _           => {  }
-            }
+    /// the tab shown in a place: the focused widget if it is there, else the one focused last
+    fn active_in(&self, panels: &[usize]) -> usize {
+        match self.curr {
+            Some(curr) if panels.contains(&curr) => curr,
+            _ => panels.iter().copied().rev().max_by_key(|&idx| self.panels[idx].focused_at).unwrap_or(panels[0]),
         }
+    }
+
+    /// where focus goes in a place, None if nothing there can be focused
+    fn focus_in(&self, panels: &[usize]) -> Option<usize> {
+        let selectable: Vec<usize> = panels.iter().copied().filter(|&idx| self.panels[idx].widget.selectable()).collect();
+        if selectable.is_empty() { None } else { Some(self.active_in(&selectable)) }
+    }
 
+    fn slot_of(&self, idx: usize) -> Option<usize> {
+        self.slots.iter().position(|(_, panels)| panels.contains(&idx))
+    }
+
+    /// nearest place beside the current one sharing some of its lines, wrapping around
+    fn move_side(&mut self, right: bool) {
+        let curr = match self.curr {
+            Some(curr) => curr,
+            None       => return self.focus_first(),
+        };
+        let from = match self.slot_of(curr) {
+            Some(from) => from,
+            None       => return,
+        };
+        let area = self.slots[from].0;
+        let candidates: Vec<(Rect, usize)> = self.slots.iter().enumerate()
+            .filter(|&(s, (rect, _))| s != from && rect.top() < area.bottom() && area.top() < rect.bottom())
+            .filter_map(|(_, (rect, panels))| self.focus_in(panels).map(|idx| (*rect, idx)))
+            .collect();
+        let beside = candidates.iter()
+            .filter(|(rect, _)| if right { rect.left() >= area.right() } else { rect.right() <= area.left() })
+            .min_by_key(|(rect, _)| if right { rect.left() - area.right() } else { area.left() - rect.right() });
+        let wrapped = if right {
+            candidates.iter().min_by_key(|(rect, _)| rect.left())
+        } else {
+            candidates.iter().max_by_key(|(rect, _)| rect.left())
+        };
+        if let Some(&(_, idx)) = beside.or(wrapped) {
+            self.set_focus(idx);
+        }
+    }
+
+    /// nearest place above or below, closest to the middle of the current one, wrapping around
+    fn move_row(&mut self, down: bool) {
+        let curr = match self.curr {
+            Some(curr) => curr,
+            None       => return self.focus_first(),
+        };
+        let from = match self.slot_of(curr) {
+            Some(from) => from,
+            None       => return,
+        };
+        let area = self.slots[from].0;
+        let middle = area.left() + area.width / 2;
+        let candidates: Vec<(Rect, usize)> = self.slots.iter().enumerate()
+            .filter(|&(s, _)| s != from)
+            .filter_map(|(_, (rect, panels))| self.focus_in(panels).map(|idx| (*rect, idx)))
+            .collect();
+        let next = candidates.iter()
+            .filter(|(rect, _)| if down { rect.top() >= area.bottom() } else { rect.bottom() <= area.top() })
+            .min_by_key(|(rect, _)| {
+                let gap = if down { rect.top() - area.bottom() } else { area.top() - rect.bottom() };
+                (gap, distance(*rect, middle))
+            });
+        // from the edge of the screen to the farthest row on the other side
+        let wrapped = candidates.iter()
+            .filter(|(rect, _)| if down { rect.bottom() <= area.top() } else { rect.top() >= area.bottom() })
+            .min_by_key(|(rect, _)| (if down { rect.top() } else { u16::MAX - rect.bottom() }, distance(*rect, middle)));
+        if let Some(&(_, idx)) = next.or(wrapped) {
+            self.set_focus(idx);
+        }
+    }
+
+    /// focus the next widget sharing the place of the focused one
+    fn cycle_tab(&mut self, forward: bool) {
+        let curr = match self.curr {
+            Some(curr) => curr,
+            None       => return,
+        };
+        let tabs: Vec<usize> = match self.slot_of(curr) {
+            Some(slot) => self.slots[slot].1.iter().copied().filter(|&idx| self.panels[idx].widget.selectable()).collect(),
+            None       => return,
+        };
+        if let Some(pos) = tabs.iter().position(|&idx| idx == curr) {
+            let next = if forward { (pos + 1) % tabs.len() } else { (pos + tabs.len() - 1) % tabs.len() };
+            self.set_focus(tabs[next]);
+        }
+    }
+
+    fn move_left(&mut self) {
+        self.move_side(false);
+    }
+
+    fn move_right(&mut self) {
+        self.move_side(true);
     }
 
     fn move_up(&mut self) {
-        if let Some(ref mut curr) = self.curr_widget {
-            match *curr {
-                "Assets"    => { *curr = "Trades" }
-                "Positions" => { *curr = "Trades" }
-                "Orders"    => { *curr = "Logs" }
-
-                "Trades"    => { *curr = "Assets" }
-                "Logs"      => { *curr = "Positions" }
-                _           => {  }
+        self.move_row(false);
+    }
+
+    fn move_down(&mut self) {
+        self.move_row(true);
+    }
+
+    /// check `rules` against every row added or changed in their tables
+    pub fn set_alerts(&mut self, rules: &[RuleConfig]) -> Result<(), String> {
+        let mut parsed = Vec::with_capacity(rules.len());
+        for config in rules.iter() {
+            let rule = Rule::parse(config)?;
+            let table = self.table_mut(&rule.table).ok_or_else(|| format!("alert {}: no table named {}", rule.name, rule.table))?;
+            if table.column_index(&rule.column).is_none() {
+                return Err(format!("alert {}: {} has no column {}", rule.name, rule.table, rule.column));
             }
+            table.track_changes();
+            parsed.push(rule);
         }
+        self.rules = parsed;
+        Ok(())
     }
 
-    fn move_down(&mut self) {
-        if let Some(ref mut curr) = self.curr_widget {
-            match *curr {
-                "Assets"    => { *curr = "Trades" }
-                "Positions" => { *curr = "Trades" }
-                "Orders"    => { *curr = "Logs" }
-
-                "Trades"    => { *curr = "Assets" }
-                "Logs"      => { *curr = "Positions" }
-                _           => {  }
+    /// flash, notify and list rows that made a rule fire since the last check
+    fn check_alerts(&mut self) {
+        if self.rules.is_empty() { return; }
+        let mut fired = Vec::new();
+        for panel in self.panels.iter_mut() {
+            let rules: Vec<&Rule> = self.rules.iter().filter(|r| r.table == panel.name).collect();
+            if rules.is_empty() { continue; }
+            let table = match AsAny::as_any_mut(&mut *panel.widget).downcast_mut::<ITable>() {
+                Some(table) => table,
+                None        => continue,
+            };
+            for change in table.take_changes() {
+                for rule in rules.iter() {
+                    let col = match table.column_index(&rule.column) {
+                        Some(col) => col,
+                        None      => continue,
+                    };
+                    if rule.fires(change.old.as_ref().map(|row| row[col].as_str()), &change.new[col]) {
+                        fired.push((change.row, Alert::new(rule, change.new.join(" "))));
+                    }
+                }
+            }
+        }
+
+        for (row, alert) in fired {
+            if self.first_mut::<IAlerts>().map_or(false, |alerts| alerts.is_snoozed(&alert.rule)) {
+                continue;
             }
+            if let Some(table) = self.table_mut(&alert.table) { table.flash(row, FLASH_TIME); }
+            self.bar.notify(format!("{}: {}", alert.rule, alert.text), Style::default().fg(Color::Black).bg(Color::Yellow));
+            info!(target: "alert", "{} on {}: {}", alert.rule, alert.table, alert.text);
+            if let Some(alerts) = self.first_mut::<IAlerts>() { alerts.push(alert); }
+        }
+        let unacked = self.first_mut::<IAlerts>().map_or(0, |alerts| alerts.unacked());
+        self.bar.set_alerts(unacked);
+    }
+
+    /// from now on events, updates and size changes go to `recorder`
+    pub fn set_recorder(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+        self.size = Rect::default();
+    }
+
+    fn record(&mut self, entry: Entry) {
+        if let Some(ref mut recorder) = self.recorder {
+            recorder.record(entry);
         }
     }
 
-    pub fn draw<B: Backend>(&mut self, mut f: Frame<B>) {
-        let height = f.size().height;
-        if height < 2 {
-            // give us a lager screen
+    pub fn draw(&mut self, mut f: Frame<B>) {
+        if f.size() != self.size {
+            self.size = f.size();
+            self.record(Entry::Resize(self.size.width, self.size.height));
+        }
+        let size = f.size();
+        if size.width == 0 || size.height == 0 {
             return;
         }
+        let body = Rect { height: size.height - 1, ..size };
+        let bar = Rect { y: size.bottom() - 1, height: 1, ..size };
+
+        for panel in self.panels.iter_mut() {
+            panel.area = Rect::default();
+        }
+        if body.height > 0 {
+            self.slots = self.plan(body);
+            match self.curr {
+                Some(curr) if self.zoom => self.draw_panel(&mut f, curr, body),
+                _                       => for slot in 0..self.slots.len() { self.draw_slot(&mut f, slot); },
+            }
+        }
 
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(0)
-            .constraints([Constraint::Length(height - 1), Constraint::Length(1)].as_ref())
-            .split(f.size());
+        if self.help.is_some() {
+            self.draw_help(&mut f, body);
+        }
 
-        {
-            let msg = match self.status {
-                Status::Insert(ref input) => { vec![
+        // drawn last so row counts see the views the widgets just rebuilt
+        match self.status {
+            Status::Insert(ref input) => {
+                let msg = [
                     Text::styled(":", Style::default().fg(Color::White)),
                     Text::styled(input, Style::default().fg(Color::White))
-                ] },
-                _ => {vec![
-                    Text::styled("help: ", Style::default().fg(Color::Blue)),
-                    Text::raw("hjkl: left/down/up/right, gg: first, G: last"),
-                ] }
-            };
-            f.render_widget(Paragraph::new(msg.iter()), chunks[1])
+                ];
+                f.render_widget(Paragraph::new(msg.iter()), bar)
+            },
+            _ => {
+                if self.focused().map_or(false, |w| AsAny::as_any(w).is::<IParagraph>()) { self.bar.read_errors(); }
+                let segments = self.status_segments();
+                self.bar.draw(&mut f, bar, segments, &Self::hint());
+            },
+        }
+    }
+
+    fn priority(&self, panels: &[usize]) -> u8 {
+        panels.iter().map(|&idx| self.panels[idx].priority).max().unwrap_or(0)
+    }
+
+    fn min_width(&self, panels: &[usize]) -> u16 {
+        panels.iter().map(|&idx| self.panels[idx].min_width).max().unwrap_or(0)
+    }
+
+    fn min_height(&self, row: &RowPlan) -> u16 {
+        row.slots.iter().flat_map(|s| s.panels.iter()).map(|&idx| self.panels[idx].min_height).max().unwrap_or(0)
+    }
+
+    /// places of the panels in `area`. While a row is lower than its widgets need, the row with
+    /// the lowest priority joins its neighbour; while a place is narrower than its widgets need,
+    /// the place with the lowest priority in that row joins its weaker neighbour, both as tabs.
+    fn plan(&self, area: Rect) -> Vec<(Rect, Vec<usize>)> {
+        let mut rows: Vec<RowPlan> = self.heights.iter().enumerate().map(|(row, &height)| RowPlan {
+            height,
+            slots: (0..self.panels.len())
+                .filter(|&idx| self.panels[idx].row == row)
+                .map(|idx| Slot { panels: vec![idx], width: self.panels[idx].width })
+                .collect(),
+        }).collect();
+
+        loop {
+            let heights = split(area.height, &rows.iter().map(|r| r.height).collect::<Vec<_>>());
+            let short = rows.iter().zip(heights.iter()).any(|(row, &height)| height < self.min_height(row));
+            if short && rows.len() > 1 {
+                let weakest = (0..rows.len())
+                    .min_by_key(|&r| rows[r].slots.iter().map(|s| self.priority(&s.panels)).max().unwrap_or(0))
+                    .unwrap_or(0);
+                let removed = rows.remove(weakest);
+                // the row below takes it, the last row goes into the one above
+                let into = &mut rows[weakest.min(rows.len() - 1)];
+                into.height += removed.height;
+                let slot = (0..into.slots.len()).min_by_key(|&s| self.priority(&into.slots[s].panels)).unwrap_or(0);
+                let panels = &mut into.slots[slot].panels;
+                panels.extend(removed.slots.into_iter().flat_map(|s| s.panels));
+                panels.sort_unstable();
+                continue;
+            }
+
+            let mut folded = false;
+            for row in rows.iter_mut().filter(|row| row.slots.len() > 1) {
+                let widths = split(area.width, &row.slots.iter().map(|s| s.width).collect::<Vec<_>>());
+                if row.slots.iter().zip(widths.iter()).all(|(slot, &width)| width >= self.min_width(&slot.panels)) {
+                    continue;
+                }
+                let weakest = (0..row.slots.len()).min_by_key(|&s| self.priority(&row.slots[s].panels)).unwrap_or(0);
+                let removed = row.slots.remove(weakest);
+                let into = match (weakest.checked_sub(1), weakest < row.slots.len()) {
+                    (Some(left), true) if self.priority(&row.slots[weakest].panels) >= self.priority(&row.slots[left].panels) => left,
+                    (Some(left), false) => left,
+                    _                   => weakest,
+                };
+                let slot = &mut row.slots[into];
+                slot.width += removed.width;
+                slot.panels.extend(removed.panels);
+                slot.panels.sort_unstable();
+                folded = true;
+                break;
+            }
+            if !folded { break; }
         }
 
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(0)
-            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
-            .split(chunks[0]);
+        let heights = split(area.height, &rows.iter().map(|r| r.height).collect::<Vec<_>>());
+        let mut places = Vec::new();
+        let mut y = area.y;
+        for (row, height) in rows.into_iter().zip(heights) {
+            let widths = split(area.width, &row.slots.iter().map(|s| s.width).collect::<Vec<_>>());
+            let mut x = area.x;
+            for (slot, width) in row.slots.into_iter().zip(widths) {
+                places.push((Rect::new(x, y, width, height), slot.panels));
+                x += width;
+            }
+            y += height;
+        }
+        places
+    }
 
-        {
-            let chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints(
-                    [Constraint::Percentage(20), Constraint::Percentage(40), Constraint::Percentage(40)].as_ref())
-                .split(chunks[0]);
+    /// one place of the layout, with a line of tabs on top when widgets share it
+    fn draw_slot(&mut self, f: &mut Frame<B>, slot: usize) {
+        let (area, panels) = self.slots[slot].clone();
+        let active = self.active_in(&panels);
+        if panels.len() < 2 || area.height < 2 {
+            return self.draw_panel(f, active, area);
+        }
 
-            let is_select = self.curr_widget.map(|t| t == "assets").unwrap_or(false);
-            self.assets_mut().draw(&mut f, chunks[0], "Assets", is_select);
-            *self.widgets_location.get_mut("assets").unwrap() = chunks[0];
+        let titles: Vec<&str> = panels.iter().map(|&idx| self.panels[idx].title.as_str()).collect();
+        let highlight = if self.curr == Some(active) {
+            Style::default().fg(Color::Black).bg(Color::Yellow)
+        } else {
+            Style::default().modifier(Modifier::REVERSED)
+        };
+        f.render_widget(
+            Tabs::default()
+                .titles(&titles[..])
+                .select(panels.iter().position(|&idx| idx == active).unwrap_or(0))
+                .style(Style::default().fg(Color::Gray))
+                .highlight_style(highlight),
+            Rect { height: 1, ..area },
+        );
+        self.draw_panel(f, active, Rect { y: area.y + 1, height: area.height - 1, ..area });
+    }
 
-            let is_select = self.curr_widget.map(|t| t == "positions").unwrap_or(false);
-            self.positions_mut().draw(&mut f, chunks[1], "Positions", is_select);
-            *self.widgets_location.get_mut("positions").unwrap() = chunks[1];
+    fn draw_panel(&mut self, f: &mut Frame<B>, idx: usize, area: Rect) {
+        let is_select = self.curr == Some(idx);
+        let panel = &mut self.panels[idx];
+        panel.area = area;
+        // inside the borders nothing would be left
+        if area.width > 2 && area.height > 2 {
+            panel.widget.draw(f, area, &panel.title, is_select);
+        }
+    }
 
-            let is_select = self.curr_widget.map(|t| t == "orders").unwrap_or(false);
-            self.orders_mut().draw(&mut f, chunks[2], "Orders", is_select);
-            *self.widgets_location.get_mut("orders").unwrap() = chunks[2];
+    /// shown in the status bar when there is nothing to tell
+    fn hint() -> String {
+        let wanted = [AppKey::Help, AppKey::Command, AppKey::Quit];
+        let hints: Vec<String> = NORMAL_KEYS.iter()
+            .filter(|b| wanted.contains(&b.action))
+            .map(|b| format!("{}: {}", b.keys[0].label(), b.help))
+            .collect();
+        hints.join(", ")
+    }
+
+    /// keys of every mode, of the focused widget and the commands
+    fn help_sections(&self) -> Vec<Section> {
+        let mut sections = vec![
+            Section::new("normal", NORMAL_KEYS),
+            Section::new("after g", G_KEYS),
+            Section::new("command line", PROMPT_KEYS),
+        ];
+        if let Some(idx) = self.curr {
+            let panel = &self.panels[idx];
+            for mut section in panel.widget.keys() {
+                section.title = format!("{}: {}", panel.title, section.title);
+                sections.push(section);
+            }
         }
+        sections.push(Section::new("help", HELP_KEYS));
+        sections.push(Section::new("help search", SEARCH_KEYS));
+        sections.push(command_section());
+        sections
+    }
 
-        {
-            let chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
-                .split(chunks[1]);
+    fn draw_help(&mut self, f: &mut Frame<B>, area: Rect) {
+        let sections = self.help_sections();
+        let help = match self.help.as_mut() {
+            Some(help) => help,
+            None       => return,
+        };
+        let lines = help_lines(&sections, &help.query);
+        help.scroll = help.scroll.min(lines.len().saturating_sub(1));
 
-            let is_select = self.curr_widget.map(|t| t == "trades").unwrap_or(false);
-            self.trades_mut().draw(&mut f, chunks[0], "Trades", is_select);
-            *self.widgets_location.get_mut("trades").unwrap() = chunks[0];
+        let title = match (help.query.is_empty(), help.typing) {
+            (_, true)      => format!("Help /{}_", help.query),
+            (false, false) => format!("Help /{}/", help.query),
+            (true, false)  => "Help".to_string(),
+        };
+        let area = centered(area, 80, 80);
+        f.render_widget(Clear, area);
+        f.render_widget(
+            List::new(lines.into_iter().skip(help.scroll))
+            .block(Block::default()
+                .title(&title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan))
+                .title_style(Style::default().fg(Color::Yellow))),
+            area
+        );
+    }
 
-            let is_select = self.curr_widget.map(|t| t == "logs").unwrap_or(false);
-            self.logs_mut().draw(&mut f, chunks[1], "Logs", is_select);
-            *self.widgets_location.get_mut("logs").unwrap() = chunks[1];
+    fn status_segments(&self) -> Vec<(String, Style)> {
+        let mode = match self.status {
+            _ if self.help.is_some()           => ("HELP", Color::Cyan),
+            Status::Normal if self.capturing() => ("EDIT", Color::Yellow),
+            Status::Normal                     => ("NORMAL", Color::Blue),
+            Status::WaitG                      => ("g", Color::Blue),
+            Status::Insert(_)                  => ("COMMAND", Color::Magenta),
+        };
+        let mut segments = vec![(mode.0.to_string(), Style::default().fg(Color::Black).bg(mode.1))];
+        if let Some(name) = self.curr_widget() {
+            segments.push((name.to_string(), Style::default().fg(Color::Yellow)));
+        }
+        if self.zoom {
+            segments.push(("zoom".to_string(), Style::default().fg(Color::Cyan)));
+        }
+        if let Some(summary) = self.summary() {
+            segments.push((summary, Style::default().fg(Color::White)));
         }
+        segments
     }
 
     fn refresh_log(&mut self) {
@@ -429,11 +980,131 @@ impl App {
                 Style::default().fg(Color::Gray)
             };
 
-            self.logs_mut().add_styled(msg, style);
-            self.logs_mut().select_last();
+            self.bar.on_log(&msg);
+            if let Some(logs) = self.first_mut::<IParagraph>() {
+                logs.add_styled(msg, style);
+                logs.select_last();
+            }
         }
     }
 
+    fn refresh_source(&mut self) {
+        let mut updates = Vec::new();
+        while updates.len() < MAX_UPDATES_PER_TICK {
+            match self.updates.try_recv() {
+                Ok(update)                            => updates.push(update),
+                Err(mpsc::TryRecvError::Empty)        => break,
+                Err(mpsc::TryRecvError::Disconnected) => { self.bar.source_closed(); break; },
+            }
+        }
+        if !updates.is_empty() { self.bar.source_alive(); }
+        for update in updates {
+            if let Err(e) = self.apply_update(update) {
+                warn!("drop update: {}", e);
+            }
+        }
+    }
+
+    fn apply_update(&mut self, update: Update) -> Result<(), String> {
+        if self.recorder.is_some() { self.record(Entry::Update(update.clone())); }
+        self.push_row(&update.table, update.row.clone())?;
+        if update.table == "trades" {
+            self.feed_trade(&update.row);
+        }
+        Ok(())
+    }
+
+    pub fn set_remote(&mut self, rx: mpsc::Receiver<Request>) {
+        self.remote = Some(rx);
+    }
+
+    fn refresh_remote(&mut self) {
+        let requests: Vec<Request> = match self.remote {
+            Some(ref rx) => rx.try_iter().collect(),
+            None         => return,
+        };
+        for Request { cmd, reply } in requests {
+            debug!("remote: {:?}", cmd);
+            let _ = reply.send(self.on_remote(cmd).into());
+        }
+    }
+
+    fn on_remote(&mut self, cmd: RemoteCommand) -> Result<(), String> {
+        match cmd {
+            RemoteCommand::PushRow { table, row } => self.apply_update(Update { table, row }),
+            RemoteCommand::Log { line, level }    => {
+                let level: log::Level = match level {
+                    Some(level) => level.parse().map_err(|_| format!("bad level {}", level))?,
+                    None        => log::Level::Info,
+                };
+                log::log!(target: "remote", level, "{}", line);
+                Ok(())
+            },
+            // push_row records itself as an update, these are recorded here
+            RemoteCommand::Focus { ref widget }   => {
+                if self.recorder.is_some() { self.record(Entry::Remote(cmd.clone())); }
+                self.execute(&format!("focus {}", widget))
+            },
+            RemoteCommand::Command { ref line }   => {
+                if self.recorder.is_some() { self.record(Entry::Remote(cmd.clone())); }
+                self.execute(line)
+            },
+        }
+    }
+
+    /// trades also drive the chart and the watch list, when the layout has them
+    fn feed_trade(&mut self, row: &[String]) {
+        let trades = match self.widget::<ITable>("trades") {
+            Some(trades) => trades,
+            None         => return,
+        };
+        let (code, price, volume) = match (trades.column_index("code"), trades.column_index("price"), trades.column_index("volume")) {
+            (Some(c), Some(p), Some(v)) => (row[c].clone(), row[p].parse::<f64>(), row[v].parse::<f64>()),
+            _                           => return,
+        };
+        let (price, volume) = match (price, volume) {
+            (Ok(price), Ok(volume)) if price.is_finite() && volume.is_finite() => (price, volume),
+            _ => { warn!("trade without numeric price or volume: {:?}", row); return; },
+        };
+
+        let chart = match self.first_mut::<IChart>() {
+            Some(chart) => chart,
+            None        => return,
+        };
+        chart.push(&code, price, volume);
+        let trend: Vec<String> = chart.recent(&code, TREND_LEN).iter().map(|p| p.to_string()).collect();
+        let total = chart.total_volume(&code);
+        if let Some(watch) = self.widget_mut::<ITable>("watch") {
+            watch.upsert_row(0, vec![code, format!("{:.2}", price), format!("{}", total), trend.join(" ")]);
+        }
+    }
+
+    /// any table of the layout takes rows by its widget name
+    fn table_mut(&mut self, name: &str) -> Option<&mut ITable> {
+        self.widget_mut::<ITable>(name)
+    }
+
+    pub fn push_row(&mut self, table: &str, row: Vec<String>) -> Result<(), String> {
+        let target = self.table_mut(table).ok_or_else(|| format!("no table named {}", table))?;
+        if row.len() != target.columns() {
+            return Err(format!("{} expects {} columns, got {}", table, target.columns(), row.len()));
+        }
+        target.add_row(row);
+        Ok(())
+    }
+
+    fn on_tick(&mut self) {
+        self.refresh_remote();
+        self.refresh_source();
+        self.check_alerts();
+        self.refresh_log();
+    }
+
+    /// `q` was pressed in normal mode
+    pub fn quitting(&self) -> bool {
+        self.quit
+    }
+
     fn timeout_waitg(&mut self) {
         if self.stack_kevent_time.elapsed() > Duration::from_secs(2) {
             self.status = Status::Normal
@@ -441,36 +1112,97 @@ impl App {
     }
 
     pub fn on_event(&mut self, event: Event) {
-        match (self.status.clone(), event.clone()) {
-            (Status::Normal, Event::CtrlKey('l'))      => { self.move_right(); },
-            (Status::Normal, Event::CtrlKey('h'))      => { self.move_left(); },
-            (Status::Normal, Event::CtrlKey('j'))      => { self.move_down(); },
-            (Status::Normal, Event::CtrlKey('k'))      => { self.move_up(); },
-            (Status::Normal, Event::CtrlKey('u'))      => { self.select_page_up(); },
-            (Status::Normal, Event::CtrlKey('d'))      => { self.select_page_down(); },
-            (Status::Normal, Event::CharKey(':'))      => { self.status = Status::Insert("".into()); },
-            (Status::Normal, Event::CharKey('G'))      => { self.select_last(); },
-            (Status::Normal, Event::CharKey('g'))      => { self.status = Status::WaitG; },
-            (Status::WaitG,  Event::CharKey('g'))      => { self.select_first(); self.status = Status::Normal; },
-            (Status::Normal, Event::CharKey('j'))      => { self.select_down(); },
-            (Status::Normal, Event::CharKey('k'))      => { self.select_up(); },
-            (Status::WaitG,  Event::Tick)              => { self.timeout_waitg(); self.refresh_log(); },
-            (Status::Normal, Event::Tick)              => { self.refresh_log(); }
-            (Status::Insert(_), Event::Tick)           => { self.refresh_log() }
-            (Status::Normal, Event::Up)                => { self.select_up() },
-            (Status::Normal, Event::Down)              => { self.select_down() },
-            (Status::Normal, Event::ScrollUp(_, _))    => { self.select_up(); },
-            (Status::Normal, Event::ScrollDown(_, _))  => { self.select_down(); },
-            (Status::Normal, Event::Press(xpos, ypos)) => { self.click(xpos, ypos); },
-            (Status::Insert(mut m), Event::CharKey(c)) => { m.push(c); self.status = Status::Insert(m); },
-            (Status::Insert(m), Event::Enter)          => { info!("got input {}", m); self.status = Status::Normal; },
-            (Status::Insert(mut m), Event::Backspace)  => { m.pop(); self.status = Status::Insert(m); },
-            (Status::Insert(_), Event::CtrlKey('w'))   => { self.status = Status::Insert("".into()); },
-            (Status::Insert(_), Event::Esc)            => { self.status = Status::Normal; },
-            _                                          => {
-                //self.debug(format!("Got key: {:?} status: {:?}", event, self.status));
-                debug!("Got key: {:?} status: {:?}", event, self.status);
+        if self.recorder.is_some() { self.record(Entry::Event(event.clone())); }
+        if event == Event::Tick {
+            if let Status::WaitG = self.status { self.timeout_waitg(); }
+            self.on_tick();
+            return;
+        }
+        if self.help.is_some() {
+            self.on_help_key(&event);
+            return;
+        }
+
+        match self.status.clone() {
+            Status::Normal if self.capturing() => { self.select_on_key(&event); },
+            Status::Normal                     => match keymap::lookup(NORMAL_KEYS, &event) {
+                Some(key)                           => self.on_normal_key(key, &event),
+                None if self.select_on_key(&event) => {  },
+                None                                => debug!("Got key: {:?} status: {:?}", event, self.status),
+            },
+            Status::WaitG                      => match keymap::lookup(G_KEYS, &event) {
+                Some(GKey::First) => { self.select_first(); self.status = Status::Normal; },
+                None              => debug!("Got key: {:?} status: {:?}", event, self.status),
+            },
+            Status::Insert(line)               => match keymap::lookup(PROMPT_KEYS, &event) {
+                Some(key) => self.on_prompt_key(key, line, &event),
+                None      => debug!("Got key: {:?} status: {:?}", event, self.status),
+            },
+        }
+    }
+
+    fn on_normal_key(&mut self, key: AppKey, event: &Event) {
+        match key {
+            AppKey::FocusLeft  => self.move_left(),
+            AppKey::FocusRight => self.move_right(),
+            AppKey::FocusDown  => self.move_down(),
+            AppKey::FocusUp    => self.move_up(),
+            AppKey::Up         => self.select_up(),
+            AppKey::Down       => self.select_down(),
+            AppKey::PageUp     => self.select_page_up(),
+            AppKey::PageDown   => self.select_page_down(),
+            AppKey::WaitG      => { self.stack_kevent_time = Instant::now(); self.status = Status::WaitG; },
+            AppKey::Last       => self.select_last(),
+            AppKey::Command    => { self.idx_history = None; self.status = Status::Insert("".into()); },
+            AppKey::Help       => self.help = Some(Help::default()),
+            AppKey::Zoom       => self.zoom = !self.zoom && self.curr.is_some(),
+            AppKey::NextTab    => self.cycle_tab(true),
+            AppKey::PrevTab    => self.cycle_tab(false),
+            AppKey::Quit       => self.quit = true,
+            AppKey::Click      => if let Event::Press(xpos, ypos) = *event { self.click(xpos, ypos); },
+        }
+    }
+
+    fn on_prompt_key(&mut self, key: PromptKey, mut line: String, event: &Event) {
+        match key {
+            PromptKey::Run    => {
+                info!("got input {}", line);
+                self.push_history(&line);
+                if let Err(e) = self.execute(&line) { warn!("{}: {}", line, e); }
+                self.status = Status::Normal;
+            },
+            PromptKey::Prev   => if let Some(m) = self.history_prev() { self.status = Status::Insert(m); },
+            PromptKey::Next   => if let Some(m) = self.history_next() { self.status = Status::Insert(m); },
+            PromptKey::Erase  => { line.pop(); self.status = Status::Insert(line); },
+            PromptKey::Clear  => self.status = Status::Insert("".into()),
+            PromptKey::Cancel => self.status = Status::Normal,
+            PromptKey::Type   => if let Event::CharKey(c) = *event { line.push(c); self.status = Status::Insert(line); },
+        }
+    }
+
+    fn on_help_key(&mut self, event: &Event) {
+        let help = match self.help.as_mut() {
+            Some(help) => help,
+            None       => return,
+        };
+        if help.typing {
+            match keymap::lookup(SEARCH_KEYS, event) {
+                Some(SearchKey::Done)   => help.typing = false,
+                Some(SearchKey::Cancel) => { help.typing = false; help.query.clear(); },
+                Some(SearchKey::Erase)  => { help.query.pop(); },
+                Some(SearchKey::Type)   => if let Event::CharKey(c) = *event { help.query.push(c); help.scroll = 0; },
+                None                    => {  },
             }
+            return;
+        }
+        match keymap::lookup(HELP_KEYS, event) {
+            Some(HelpKey::Close)    => self.help = None,
+            Some(HelpKey::Search)   => { help.typing = true; help.query.clear(); help.scroll = 0; },
+            Some(HelpKey::Up)       => help.scroll = help.scroll.saturating_sub(1),
+            Some(HelpKey::Down)     => help.scroll += 1,
+            Some(HelpKey::PageUp)   => help.scroll = help.scroll.saturating_sub(HELP_PAGE),
+            Some(HelpKey::PageDown) => help.scroll += HELP_PAGE,
+            None                    => {  },
         }
     }
 }
@@ -534,7 +1266,7 @@ impl IParagraph {
     }
 }
 
-impl InteractiveWidget for IParagraph {
+impl Interactive for IParagraph {
     fn select_up(&mut self) {
         if self.idx_page > 0 {
             self.idx_page -= 1;
@@ -604,7 +1336,10 @@ impl InteractiveWidget for IParagraph {
         }
     }
 
-    fn draw<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, name: &'static str, is_active: bool) {
+}
+
+impl<B: Backend> InteractiveWidget<B> for IParagraph {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, name: &str, is_active: bool) {
         self.window = area;
 
         if self.get_content_width() == 0 { return; }
@@ -640,221 +1375,33 @@ impl InteractiveWidget for IParagraph {
     }
 }
 
+/// keeps a cell of the layout blank
 #[derive(Default)]
-pub struct ITable {
-    content    : Vec<(String, Style, usize, Vec<String>, Box<dyn Fn(&str, bool) -> Style>)>,
-
-    width      : usize,
-    height     : usize,
-
-    idx_page   : usize,
-    idx_select : usize,
+pub struct IEmpty;
 
-    window     : Rect,
+impl Interactive for IEmpty {
+    fn selectable(&self) -> bool { false }
 }
 
-fn default_highlight(_: &str, is_select: bool) -> Style {
-    if is_select {
-        Style::default().fg(Color::Green)
-    } else {
-        Style::default().fg(Color::White)
-    }
+impl<B: Backend> InteractiveWidget<B> for IEmpty {
+    fn draw(&mut self, _f: &mut Frame<B>, _area: Rect, _name: &str, _is_active: bool) {  }
 }
 
-impl ITable {
-    pub fn new() -> Self {
-        let mut slf: Self = Default::default();
-        //slf.add_column("col1", Style::default().fg(Color::Gray), 8, vec![], default_highlight);
-        //slf.add_column("col2", Style::default().fg(Color::Gray), 8, vec![], default_highlight);
-        //for i in 0..100 {
-            //slf.add_row(vec![format!("row{}1", i), format!("row{}2", i)]);
-        //}
-        slf
-    }
-
-    pub fn add_column<F, S1, S2>(&mut self, header: S1, hstyle: Style, width: usize, mut column: Vec<S2>, f: F)
-        where F : Fn(&str, bool) -> Style + 'static,
-              S1 : ToString,
-              S2 : ToString,
-    {
-        let mut align_column: Vec<String>;
-        if self.content.len() == 0 { self.height = column.len(); }
-        if self.height < column.len() {
-            align_column = column.into_iter().take(self.height).map(|s| s.to_string()).collect();
-        } else {
-            let left = self.height - column.len();
-            align_column = column.into_iter().map(|s| s.to_string()).collect();
-            for _ in 0..left {
-                align_column.push("".into());
-            }
-        }
-
-        self.width += 1;
-        let f : Box<dyn Fn(&str, bool) -> Style> = Box::new(f);
-        self.content.push((header.to_string(), hstyle, width, align_column, f));
-    }
-
-    pub fn add_row<S: ToString>(&mut self, rows: Vec<S>) {
-        assert!(rows.len() == self.width);
-        for ((_, _, _, row, _), ref nval) in self.content.iter_mut().zip(rows.into_iter()) {
-            row.push(nval.to_string());
-        }
-        self.height += 1;
-    }
-
-    pub fn remove_col(&mut self, idx: usize) {
-        assert!(self.width > idx);
-        self.content.remove(idx);
-        self.width -= 1;
-    }
-
-    pub fn remove_row(&mut self, idx: usize) {
-        assert!(self.height > idx);
-        for (_, _, _, row, _) in self.content.iter_mut() {
-            row.remove(idx);
+impl<B: Backend + 'static> AppCommands for App<B> {
+    fn focus(&mut self, name: &str) -> Result<(), String> {
+        match self.panel_index(name) {
+            Some(idx) if self.panels[idx].widget.selectable() => { self.set_focus(idx); Ok(()) },
+            Some(_)                                           => Err(format!("{} can not be focused", name)),
+            None                                              => Err(format!("no widget named {}", name)),
         }
-        self.height -= 1;
     }
 
-    fn wrap_page(&mut self) {
-        if self.idx_select < self.idx_page {
-            self.idx_page = self.idx_select;
+    fn zoom(&mut self, on: bool) -> Result<(), String> {
+        if on && self.curr.is_none() {
+            return Err("no widget focused".into());
         }
-
-        if self.idx_select >= self.idx_page + self.get_content_height() {
-            self.idx_page = self.idx_select - self.get_content_height();
-            if self.idx_page + 1 < self.height { self.idx_page += 1; }
-        }
-    }
-
-    fn wrap_select(&mut self) {
-        if self.idx_select < self.idx_page {
-            self.idx_select = self.idx_page;
-        }
-
-        let maxreach = self.idx_page + self.get_content_height();
-        if maxreach > 0 && self.idx_select > maxreach {
-            self.idx_select = maxreach - 1;
-        }
-    }
-
-    fn get_content_width(&self) -> usize {
-        if self.window.width > 2 {
-            (self.window.width - 2) as _
-        } else {
-            0
-        }
-    }
-
-    fn get_content_height(&self) -> usize {
-        if self.window.height > 3 {
-            (self.window.height - 3) as _
-        } else {
-            0
-        }
-    }
-}
-
-impl InteractiveWidget for ITable {
-    fn select_up(&mut self) {
-        if self.idx_select > 0 {
-            self.idx_select -= 1;
-            self.wrap_page();
-        }
-    }
-
-    fn select_down(&mut self) {
-        if self.idx_select < self.height - 1 {
-            self.idx_select += 1;
-            self.wrap_page();
-        }
-    }
-
-    fn select_first(&mut self) {
-        if self.idx_select != 0 {
-            self.idx_select = 0;
-            self.wrap_page();
-        }
-    }
-
-    fn select_last(&mut self) {
-        if self.idx_select != self.height - 1 {
-            self.idx_select = self.height - 1;
-            self.wrap_page();
-        }
-    }
-
-    fn select_page_up(&mut self) {
-        if self.idx_page != 0 {
-            let shift = self.get_content_height().min(self.idx_page);
-            if shift > 0 {
-                self.idx_page -= shift;
-            } else {
-                self.idx_page -= 0;
-            }
-            self.wrap_select();
-        }
-    }
-
-    fn select_page_down(&mut self) {
-        if self.get_content_height() > 0 && self.height > 0 && self.idx_page < self.height {
-            self.idx_page = (self.idx_page + self.get_content_height() - 1).min(self.height-1);
-            self.wrap_select();
-        }
-    }
-
-    fn select_on_key(&mut self) {  }
-
-    fn click(&mut self, _xpos: u16, ypos: u16, ) {
-        if ypos > 1 && ypos < self.window.height {
-            self.idx_select = self.idx_page + ypos as usize - 2;
-        }
-    }
-
-    fn draw<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, name: &'static str, is_active: bool) {
-        self.window = area;
-
-        let block = Block::default()
-            .title(name)
-            .borders(Borders::ALL)
-            .border_style(if is_active { Style::default().fg(Color::Red) } else { Style::default().fg(Color::White) })
-            .title_style(Style::default().fg(Color::Yellow));
-        f.render_widget(block, area);
-
-        let constraints: Vec<_> = self.content.iter()
-            .map(|(_, _, len, _, _)| Constraint::Length(*len as u16 + 1))
-            .collect();
-        let tablerows = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints(constraints)
-            .split(block.inner(area));
-
-        let idx_end = self.height.min(self.idx_page+self.get_content_height());
-
-        for (idx, (header, headerstyle, _, rows, stylefun)) in self.content.iter().enumerate() {
-            let rowchunk = tablerows[idx];
-            let mut rowlist = Vec::with_capacity(rows.len());
-            rowlist.push(Text::styled(header, *headerstyle));
-            for (idx, row) in rows[self.idx_page..idx_end].iter().enumerate() {
-                let abs_idx = idx + self.idx_page;
-                let is_select = self.idx_select == abs_idx;
-                let style = stylefun(row, is_select);
-                rowlist.push(Text::styled(row, style));
-            }
-
-            f.render_widget(List::new(rowlist.into_iter()), rowchunk);
-        }
-    }
-}
-
-#[derive(Default)]
-pub struct IEmpty;
-impl InteractiveWidget for IEmpty {  }
-
-impl Drop for App {
-    fn drop(&mut self) {
-        unsafe { self.drop_map(); }
-        // no obj need manually drop ??
+        self.zoom = on;
+        Ok(())
     }
 }
 
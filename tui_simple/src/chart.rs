@@ -0,0 +1,397 @@
+use crate::app::{Interactive, InteractiveWidget};
+use crate::keymap::{self, Binding, Key, Section};
+use crate::Event;
+
+use std::collections::HashMap;
+use tui::{
+    backend::Backend,
+    terminal::Frame,
+
+    style::{Color, Style},
+    symbols,
+    widgets::*,
+    widgets::canvas::{Canvas, Line},
+    layout::*,
+};
+
+const SPARK: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// render values as one line of block characters, lowest value is `▁`, a NaN or infinity is a blank.
+pub fn sparkline(values: &[f64]) -> String {
+    let (lo, hi) = bounds(values);
+    values.iter()
+        .map(|v| match v.is_finite() {
+            true  => SPARK[((((v - lo) / (hi - lo)) * (SPARK.len() - 1) as f64).round() as usize).min(SPARK.len() - 1)],
+            false => ' ',
+        })
+        .collect()
+}
+
+/// min and max of the finite values, never equal so they can be used as axis bounds
+fn bounds(values: &[f64]) -> (f64, f64) {
+    let finite = values.iter().cloned().filter(|v| v.is_finite());
+    let lo = finite.clone().fold(f64::INFINITY, f64::min);
+    let hi = finite.fold(f64::NEG_INFINITY, f64::max);
+    if !lo.is_finite() || !hi.is_finite() {
+        (0.0, 1.0)
+    } else if hi - lo < 1e-9 {
+        (lo - 0.01, hi + 0.01)
+    } else {
+        (lo, hi)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChartMode {
+    Line,
+    Candle,
+    Volume,
+}
+
+impl ChartMode {
+    fn next(self) -> Self {
+        match self {
+            ChartMode::Line   => ChartMode::Candle,
+            ChartMode::Candle => ChartMode::Volume,
+            ChartMode::Volume => ChartMode::Line,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ChartMode::Line   => "line",
+            ChartMode::Candle => "candle",
+            ChartMode::Volume => "volume",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Candle {
+    pub open   : f64,
+    pub high   : f64,
+    pub low    : f64,
+    pub close  : f64,
+    pub volume : f64,
+}
+
+/// trades of one instrument, in arrival order
+#[derive(Default)]
+pub struct Series {
+    prices  : Vec<f64>,
+    volumes : Vec<f64>,
+    total   : f64, // sum of volumes
+}
+
+impl Series {
+    /// every `size` trades make one candle, the last one may be partial
+    pub fn candles(&self, size: usize) -> Vec<Candle> {
+        self.prices.chunks(size).zip(self.volumes.chunks(size))
+            .map(|(prices, volumes)| Candle {
+                open   : prices[0],
+                high   : prices.iter().cloned().fold(f64::NEG_INFINITY, f64::max),
+                low    : prices.iter().cloned().fold(f64::INFINITY, f64::min),
+                close  : prices[prices.len() - 1],
+                volume : volumes.iter().sum(),
+            })
+            .collect()
+    }
+}
+
+/// price chart of one instrument at a time, fed with trades.
+pub struct IChart {
+    series   : HashMap<String, Series>,
+    codes    : Vec<String>, // in order of first trade
+    curr     : Option<String>,
+
+    mode     : ChartMode,
+    bar_size : usize, // trades per candle
+    zoom     : usize, // points or candles in view
+    offset   : usize, // points or candles hidden on the right, 0 follows the latest
+
+    window   : Rect,
+}
+
+impl Default for IChart {
+    fn default() -> Self {
+        Self {
+            series   : Default::default(),
+            codes    : Default::default(),
+            curr     : None,
+            mode     : ChartMode::Line,
+            bar_size : 5,
+            zoom     : 60,
+            offset   : 0,
+            window   : Rect::default(),
+        }
+    }
+}
+
+impl IChart {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_bar_size(&mut self, size: usize) {
+        self.bar_size = size.max(1);
+        self.offset = 0;
+    }
+
+    pub fn set_zoom(&mut self, zoom: usize) {
+        self.zoom = zoom.max(4).min(4096);
+    }
+
+    pub fn push(&mut self, code: &str, price: f64, volume: f64) {
+        if !self.series.contains_key(code) {
+            self.codes.push(code.to_string());
+            if self.curr.is_none() { self.curr = Some(code.to_string()); }
+        }
+        let series = self.series.entry(code.to_string()).or_default();
+        series.prices.push(price);
+        series.volumes.push(volume);
+        series.total += volume;
+    }
+
+    /// up to `count` latest prices of `code`
+    pub fn recent(&self, code: &str, count: usize) -> &[f64] {
+        match self.series.get(code) {
+            Some(series) => &series.prices[series.prices.len().saturating_sub(count)..],
+            None         => &[],
+        }
+    }
+
+    pub fn series(&self, code: &str) -> Option<&Series> {
+        self.series.get(code)
+    }
+
+    pub fn total_volume(&self, code: &str) -> f64 {
+        self.series.get(code).map(|s| s.total).unwrap_or(0.0)
+    }
+
+    pub fn show(&mut self, code: &str) -> Result<(), String> {
+        if !self.series.contains_key(code) {
+            return Err(format!("no trades for {}", code));
+        }
+        self.curr = Some(code.to_string());
+        self.offset = 0;
+        Ok(())
+    }
+
+    fn cycle_code(&mut self, forward: bool) {
+        if self.codes.is_empty() { return; }
+        let idx = self.curr.as_ref().and_then(|c| self.codes.iter().position(|x| x == c)).unwrap_or(0);
+        let idx = if forward { (idx + 1) % self.codes.len() } else { (idx + self.codes.len() - 1) % self.codes.len() };
+        self.curr = Some(self.codes[idx].clone());
+        self.offset = 0;
+    }
+
+    /// how many points or candles the current mode has
+    fn len(&self) -> usize {
+        match self.curr.as_ref().and_then(|c| self.series.get(c)) {
+            Some(series) if self.mode == ChartMode::Line => series.prices.len(),
+            Some(series)                                 => (series.prices.len() + self.bar_size - 1) / self.bar_size,
+            None                                         => 0,
+        }
+    }
+
+    fn range(&self, len: usize) -> (usize, usize) {
+        let end = len - self.offset.min(len);
+        (end.saturating_sub(self.zoom), end)
+    }
+
+    fn pan(&mut self, left: bool, step: usize) {
+        let step = step.max(1);
+        if left {
+            self.offset = (self.offset + step).min(self.len().saturating_sub(1));
+        } else {
+            self.offset = self.offset.saturating_sub(step);
+        }
+    }
+
+    fn draw_line<B: Backend>(&self, f: &mut Frame<B>, block: Block, area: Rect, series: &Series) {
+        let (start, end) = self.range(series.prices.len());
+        let shown = &series.prices[start..end];
+        let points: Vec<(f64, f64)> = shown.iter().enumerate()
+            .map(|(idx, price)| ((start + idx) as f64, *price))
+            .collect();
+        let (lo, hi) = bounds(shown);
+        let xlabels = [start.to_string(), end.to_string()];
+        let ylabels = [format!("{:.2}", lo), format!("{:.2}", hi)];
+        let datasets = [
+            Dataset::default()
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Cyan))
+                .data(&points)
+        ];
+
+        f.render_widget(
+            Chart::default()
+            .block(block)
+            .x_axis(Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([start as f64, end.saturating_sub(1).max(start + 1) as f64])
+                .labels(&xlabels))
+            .y_axis(Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .bounds([lo, hi])
+                .labels(&ylabels))
+            .datasets(&datasets),
+            area
+        );
+    }
+
+    fn draw_candle<B: Backend>(&self, f: &mut Frame<B>, block: Block, area: Rect, series: &Series) {
+        let candles = series.candles(self.bar_size);
+        let (start, end) = self.range(candles.len());
+        let shown = &candles[start..end];
+        let extremes: Vec<f64> = shown.iter().map(|c| c.low).chain(shown.iter().map(|c| c.high)).collect();
+        let (lo, hi) = bounds(&extremes);
+
+        f.render_widget(
+            Canvas::default()
+            .block(block)
+            .x_bounds([0.0, self.zoom as f64])
+            .y_bounds([lo, hi])
+            .paint(|ctx| {
+                for (idx, candle) in shown.iter().enumerate() {
+                    let x = idx as f64 + 0.5;
+                    let color = if candle.close >= candle.open { Color::Green } else { Color::Red };
+                    ctx.draw(&Line { x1: x, y1: candle.low, x2: x, y2: candle.high, color });
+                    let (bottom, top) = (candle.open.min(candle.close), candle.open.max(candle.close));
+                    for dx in [-0.3, -0.15, 0.15, 0.3].iter() {
+                        ctx.draw(&Line { x1: x + dx, y1: bottom, x2: x + dx, y2: top, color });
+                    }
+                }
+            }),
+            area
+        );
+    }
+
+    fn draw_volume<B: Backend>(&self, f: &mut Frame<B>, block: Block, area: Rect, series: &Series) {
+        let candles = series.candles(self.bar_size);
+        let (start, end) = self.range(candles.len());
+        let data: Vec<(&str, u64)> = candles[start..end].iter().map(|c| ("", c.volume as u64)).collect();
+        let inner = block.inner(area);
+        let bar_width = if data.is_empty() { 1 } else { (inner.width as usize / data.len()).max(2) - 1 };
+
+        f.render_widget(
+            BarChart::default()
+            .block(block)
+            .data(&data)
+            .bar_width(bar_width as u16)
+            .bar_gap(1)
+            .style(Style::default().fg(Color::Yellow))
+            .value_style(Style::default().fg(Color::Black).bg(Color::Yellow)),
+            area
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum ChartKey {
+    ZoomIn,
+    ZoomOut,
+    Older,
+    Newer,
+    Latest,
+    Mode,
+    Next,
+    Prev,
+}
+
+const CHART_KEYS: &[Binding<ChartKey>] = &[
+    Binding { keys: &[Key::Char('+'), Key::Char('=')], action: ChartKey::ZoomIn,  help: "zoom in" },
+    Binding { keys: &[Key::Char('-')],                 action: ChartKey::ZoomOut, help: "zoom out" },
+    Binding { keys: &[Key::Char('h'), Key::Left],      action: ChartKey::Older,   help: "pan to older trades" },
+    Binding { keys: &[Key::Char('l'), Key::Right],     action: ChartKey::Newer,   help: "pan to newer trades" },
+    Binding { keys: &[Key::Char('0')],                 action: ChartKey::Latest,  help: "jump to the latest trades" },
+    Binding { keys: &[Key::Char('m')],                 action: ChartKey::Mode,    help: "next chart mode: line, candle, volume" },
+    Binding { keys: &[Key::Char('n')],                 action: ChartKey::Next,    help: "next code" },
+    Binding { keys: &[Key::Char('p')],                 action: ChartKey::Prev,    help: "previous code" },
+];
+
+impl Interactive for IChart {
+    fn select_first(&mut self) { // oldest
+        self.offset = self.len().saturating_sub(self.zoom);
+    }
+
+    fn select_last(&mut self) { // latest, and keep following it
+        self.offset = 0;
+    }
+
+    fn select_page_up(&mut self) {
+        self.pan(true, self.zoom);
+    }
+
+    fn select_page_down(&mut self) {
+        self.pan(false, self.zoom);
+    }
+
+    fn select_on_key(&mut self, event: &Event) -> bool {
+        match keymap::lookup(CHART_KEYS, event) {
+            Some(ChartKey::ZoomIn)  => self.zoom = (self.zoom / 2).max(4),
+            Some(ChartKey::ZoomOut) => self.zoom = (self.zoom * 2).min(4096),
+            Some(ChartKey::Older)   => self.pan(true, self.zoom / 4),
+            Some(ChartKey::Newer)   => self.pan(false, self.zoom / 4),
+            Some(ChartKey::Latest)  => self.offset = 0,
+            Some(ChartKey::Mode)    => { self.mode = self.mode.next(); self.offset = 0; },
+            Some(ChartKey::Next)    => self.cycle_code(true),
+            Some(ChartKey::Prev)    => self.cycle_code(false),
+            None                    => return false,
+        }
+        true
+    }
+
+    fn keys(&self) -> Vec<Section> {
+        vec![Section::new("chart", CHART_KEYS)]
+    }
+
+    fn on_command(&mut self, name: &str, args: &[&str]) -> Result<(), String> {
+        match (name, args) {
+            ("series", [code])    => self.show(code)?,
+            ("chartmode", [mode]) => {
+                self.mode = match *mode {
+                    "line"   => ChartMode::Line,
+                    "candle" => ChartMode::Candle,
+                    "volume" => ChartMode::Volume,
+                    _        => return Err(format!("unknown chart mode {}", mode)),
+                };
+                self.offset = 0;
+            },
+            ("bars", [size]) => {
+                let size: usize = size.parse().map_err(|_| format!("bad bar size {}", size))?;
+                if size == 0 { return Err("bar size must be positive".into()); }
+                self.set_bar_size(size);
+            },
+            _ => return Err(format!("bad arguments for {}: {:?}", name, args)),
+        }
+        Ok(())
+    }
+}
+
+impl<B: Backend> InteractiveWidget<B> for IChart {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, name: &str, is_active: bool) {
+        self.window = area;
+
+        let title = match self.curr {
+            Some(ref code) if self.offset > 0 => format!("{} {} {} -{}", name, code, self.mode.name(), self.offset),
+            Some(ref code)                    => format!("{} {} {}", name, code, self.mode.name()),
+            None                              => name.to_string(),
+        };
+        let block = Block::default()
+            .title(&title)
+            .borders(Borders::ALL)
+            .border_style(if is_active { Style::default().fg(Color::Red) } else { Style::default().fg(Color::White) })
+            .title_style(Style::default().fg(Color::Yellow));
+
+        match self.curr.as_ref().and_then(|c| self.series.get(c)) {
+            Some(series) => match self.mode {
+                ChartMode::Line   => self.draw_line(f, block, area, series),
+                ChartMode::Candle => self.draw_candle(f, block, area, series),
+                ChartMode::Volume => self.draw_volume(f, block, area, series),
+            },
+            None => f.render_widget(block, area),
+        }
+    }
+}
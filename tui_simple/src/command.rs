@@ -0,0 +1,58 @@
+/// what commands may do to the app itself, whatever backend it draws on
+pub trait AppCommands {
+    fn focus(&mut self, name: &str) -> Result<(), String>;
+    /// the focused widget on the whole screen
+    fn zoom(&mut self, on: bool) -> Result<(), String>;
+}
+
+pub enum Action {
+    /// forwarded to the focused widget through `Interactive::on_command`
+    Widget,
+    App(fn(&mut dyn AppCommands, &[&str]) -> Result<(), String>),
+}
+
+/// everything that can be typed after `:`.
+pub struct Command {
+    pub name   : &'static str,
+    pub args   : &'static str,
+    pub help   : &'static str,
+    pub action : Action,
+}
+
+pub const COMMANDS: &[Command] = &[
+    Command { name: "focus",     args: "<widget>",            help: "focus widget by name",            action: Action::App(focus) },
+    Command { name: "zoom",      args: "[off]",               help: "focused widget on whole screen",  action: Action::App(zoom) },
+    Command { name: "group",     args: "<column>...",         help: "group rows by columns",           action: Action::Widget },
+    Command { name: "nogroup",   args: "",                    help: "ungroup rows",                    action: Action::Widget },
+    Command { name: "agg",       args: "<column> <kind>",     help: "count|sum|avg|min|max|vwap <vol>", action: Action::Widget },
+    Command { name: "noagg",     args: "",                    help: "remove aggregates and footer",    action: Action::Widget },
+    Command { name: "cancel",    args: "",                    help: "cancel selected pending orders",  action: Action::Widget },
+    Command { name: "remove",    args: "",                    help: "remove selected rows",            action: Action::Widget },
+    Command { name: "export",    args: "<path>",              help: "write selected rows as csv",      action: Action::Widget },
+    Command { name: "undo",      args: "",                    help: "revert the latest cell edit",     action: Action::Widget },
+    Command { name: "ack",       args: "[all]",               help: "acknowledge the selected alert",  action: Action::Widget },
+    Command { name: "snooze",    args: "[minutes]",           help: "mute the rule of selected alert", action: Action::Widget },
+    Command { name: "copy",      args: "",                    help: "copy selected rows to clipboard", action: Action::Widget },
+    Command { name: "series",    args: "<code>",              help: "show code in chart",              action: Action::Widget },
+    Command { name: "chartmode", args: "line|candle|volume",  help: "switch chart kind",               action: Action::Widget },
+    Command { name: "bars",      args: "<trades>",            help: "trades per candle",               action: Action::Widget },
+];
+
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+fn focus(app: &mut dyn AppCommands, args: &[&str]) -> Result<(), String> {
+    match args {
+        [name] => app.focus(name),
+        _      => Err("usage: focus <widget>".into()),
+    }
+}
+
+fn zoom(app: &mut dyn AppCommands, args: &[&str]) -> Result<(), String> {
+    match args {
+        []      => app.zoom(true),
+        ["off"] => app.zoom(false),
+        _       => Err("usage: zoom [off]".into()),
+    }
+}
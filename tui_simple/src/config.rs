@@ -0,0 +1,224 @@
+use crate::alert::RuleConfig;
+
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use structopt::StructOpt;
+
+pub const DEFAULT_CONFIG: &str = "tui_simple.toml";
+
+/// command line, each option falls back to its environment variable.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "tui_simple", about = "terminal dashboard for orders and trades")]
+pub struct Opts {
+    /// config file [default: tui_simple.toml if present]
+    #[structopt(short, long, env = "TUI_CONFIG", parse(from_os_str))]
+    pub config: Option<PathBuf>,
+
+    /// log file [default: output.log]
+    #[structopt(long, env = "TUI_LOG_FILE", parse(from_os_str))]
+    pub log_file: Option<PathBuf>,
+
+    /// off, error, warn, info, debug or trace [default: debug]
+    #[structopt(short = "L", long, env = "RUST_LOG")]
+    pub log_level: Option<log::LevelFilter>,
+
+    /// milliseconds between ticks, at least 100 [default: 250]
+    #[structopt(short, long, env = "TUI_TICK_RATE")]
+    pub tick_rate: Option<u64>,
+
+    /// layout name, also keys the saved session [default: default]
+    #[structopt(short, long, env = "TUI_LAYOUT")]
+    pub layout: Option<String>,
+
+    /// where rows come from: `demo`, `gen:<rows>` or `file:<path>` [default: demo]
+    #[structopt(short, long, env = "TUI_SOURCE")]
+    pub source: Option<String>,
+
+    /// unix socket for remote control, off if not set
+    #[structopt(long, env = "TUI_SOCKET", parse(from_os_str))]
+    pub socket: Option<PathBuf>,
+
+    /// ignore the saved session state
+    #[structopt(long)]
+    pub fresh: bool,
+
+    /// print the widget kinds the layout can use and their options, then exit
+    #[structopt(long)]
+    pub widgets: bool,
+
+    /// write keys, data updates and terminal size to this file, for bug reports
+    #[structopt(long, env = "TUI_RECORD", parse(from_os_str))]
+    pub record: Option<PathBuf>,
+
+    /// play a recorded file instead of reading the data source and the keyboard
+    #[structopt(long, parse(from_os_str))]
+    pub replay: Option<PathBuf>,
+
+    /// replay without a terminal and print the last screen
+    #[structopt(long, requires = "replay")]
+    pub headless: bool,
+
+    /// replay speed, 2 is twice as fast, 0 as fast as possible [default: 1]
+    #[structopt(long, requires = "replay")]
+    pub speed: Option<f64>,
+}
+
+/// the config file, every key is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    pub log_file  : Option<PathBuf>,
+    pub log_level : Option<String>,
+    pub tick_rate : Option<u64>,
+    pub layout    : Option<String>,
+    pub source    : Option<String>,
+    pub socket    : Option<PathBuf>,
+    pub rows      : Option<Vec<RowConfig>>,
+    pub alerts    : Option<Vec<RuleConfig>>,
+}
+
+/// one row of the screen, rows split the height and widgets split their row, both in percent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RowConfig {
+    pub height  : u16,
+    pub widgets : Vec<WidgetConfig>,
+}
+
+/// one widget of the layout, keys other than these are options checked by the widget kind.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WidgetConfig {
+    pub name    : String,
+    pub kind    : String,
+    pub width   : u16,
+    #[serde(default)]
+    pub title   : Option<String>,
+    /// columns below which the widget gives up its place and becomes a tab of a neighbour
+    #[serde(default = "default_min_width")]
+    pub min_width  : u16,
+    /// lines below which its row joins the next row as tabs
+    #[serde(default = "default_min_height")]
+    pub min_height : u16,
+    /// widgets with the lowest priority turn into tabs first
+    #[serde(default)]
+    pub priority   : u8,
+    #[serde(flatten)]
+    pub options : toml::value::Table,
+}
+
+fn default_min_width() -> u16 { 16 }
+
+fn default_min_height() -> u16 { 6 }
+
+impl WidgetConfig {
+    fn new(name: &str, kind: &str, width: u16, priority: u8) -> Self {
+        Self {
+            name       : name.into(),
+            kind       : kind.into(),
+            width,
+            title      : None,
+            min_width  : default_min_width(),
+            min_height : default_min_height(),
+            priority,
+            options    : Default::default(),
+        }
+    }
+}
+
+/// assets, watch, positions, chart and orders on top, trades, logs and alerts below.
+/// Trades stay on screen longest, logs and the placeholders go into tabs first.
+fn default_rows() -> Vec<RowConfig> {
+    vec![
+        RowConfig { height: 40, widgets: vec![
+            WidgetConfig::new("assets", "assets", 15, 0),
+            WidgetConfig::new("watch", "watch", 15, 2),
+            WidgetConfig::new("positions", "positions", 25, 1),
+            WidgetConfig::new("chart", "chart", 30, 2),
+            WidgetConfig::new("orders", "orders", 15, 0),
+        ] },
+        RowConfig { height: 60, widgets: vec![
+            WidgetConfig::new("trades", "trades", 50, 3),
+            WidgetConfig::new("logs", "logs", 30, 0),
+            WidgetConfig::new("alerts", "alerts", 20, 1),
+        ] },
+    ]
+}
+
+impl FileConfig {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let text = std::fs::read_to_string(path.as_ref())
+            .map_err(|e| format!("{}: {}", path.as_ref().display(), e))?;
+        let config = toml::from_str(&text)
+            .map_err(|e| format!("{}: {}", path.as_ref().display(), e))?;
+        Ok(config)
+    }
+}
+
+/// resolved settings: command line, then environment, then config file, then defaults.
+#[derive(Debug)]
+pub struct Config {
+    pub log_file  : PathBuf,
+    pub log_level : log::LevelFilter,
+    pub tick_rate : Duration,
+    pub layout    : String,
+    pub source    : String,
+    pub socket    : Option<PathBuf>,
+    pub rows      : Vec<RowConfig>,
+    pub alerts    : Vec<RuleConfig>,
+    pub fresh     : bool,
+    pub widgets   : bool,
+    pub record    : Option<PathBuf>,
+    pub replay    : Option<PathBuf>,
+    pub headless  : bool,
+    pub speed     : f64,
+}
+
+impl Config {
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        Self::merge(Opts::from_args())
+    }
+
+    pub fn merge(opts: Opts) -> Result<Self, Box<dyn Error>> {
+        // an explicit config must exist, the default one is optional
+        let file = match opts.config {
+            Some(ref path)                                      => FileConfig::load(path)?,
+            None if Path::new(DEFAULT_CONFIG).exists()          => FileConfig::load(DEFAULT_CONFIG)?,
+            None                                                => FileConfig::default(),
+        };
+
+        let log_level = match (opts.log_level, file.log_level) {
+            (Some(level), _)    => level,
+            (None, Some(level)) => level.parse().map_err(|_| format!("bad log_level in config: {}", level))?,
+            (None, None)        => log::LevelFilter::Debug,
+        };
+
+        let tick_rate = opts.tick_rate.or(file.tick_rate).unwrap_or(250);
+        if tick_rate < 100 {
+            return Err(format!("tick rate {}ms is too small, use at least 100", tick_rate).into());
+        }
+
+        let speed = opts.speed.unwrap_or(1.0);
+        if speed.is_nan() || speed < 0.0 {
+            return Err(format!("bad replay speed {}", speed).into());
+        }
+
+        Ok(Self {
+            log_file  : opts.log_file.or(file.log_file).unwrap_or_else(|| "output.log".into()),
+            log_level,
+            tick_rate : Duration::from_millis(tick_rate),
+            layout    : opts.layout.or(file.layout).unwrap_or_else(|| "default".into()),
+            source    : opts.source.or(file.source).unwrap_or_else(|| "demo".into()),
+            socket    : opts.socket.or(file.socket),
+            rows      : file.rows.unwrap_or_else(default_rows),
+            alerts    : file.alerts.unwrap_or_default(),
+            fresh     : opts.fresh,
+            widgets   : opts.widgets,
+            record    : opts.record,
+            replay    : opts.replay,
+            headless  : opts.headless,
+            speed,
+        })
+    }
+}
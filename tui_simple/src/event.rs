@@ -0,0 +1,82 @@
+use crossterm::event::{self, Event as CEvent, KeyEvent, MouseEvent, KeyCode, KeyModifiers, MouseButton};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc;
+use std::thread;
+
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub enum Event {
+    Tick,
+    CharKey(char),
+    CtrlKey(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    ScrollUp(u16, u16),
+    ScrollDown(u16, u16),
+    Press(u16, u16),
+    Enter,
+    Backspace,
+    Esc,
+
+    Unsupported(String),
+}
+
+impl From<CEvent> for Event {
+    fn from(event: CEvent) -> Self {
+        match event {
+            CEvent::Key(KeyEvent { code: KeyCode::Char(c), modifiers: KeyModifiers::NONE })    => Self::CharKey(c),
+            CEvent::Key(KeyEvent { code: KeyCode::Char(c), modifiers: KeyModifiers::SHIFT })   => Self::CharKey(c),
+            CEvent::Key(KeyEvent { code: KeyCode::Char(c), modifiers: KeyModifiers::CONTROL }) => Self::CtrlKey(c),
+
+            CEvent::Key(KeyEvent { code: KeyCode::Up, modifiers: _ })                          => Self::Up,
+            CEvent::Key(KeyEvent { code: KeyCode::Down, modifiers: _ })                        => Self::Down,
+            CEvent::Key(KeyEvent { code: KeyCode::Left, modifiers: _ })                        => Self::Left,
+            CEvent::Key(KeyEvent { code: KeyCode::Right, modifiers: _ })                       => Self::Right,
+            CEvent::Key(KeyEvent { code: KeyCode::Enter, modifiers: _ })                       => Self::Enter,
+            CEvent::Key(KeyEvent { code: KeyCode::Backspace, modifiers: _ })                   => Self::Backspace,
+            CEvent::Key(KeyEvent { code: KeyCode::Esc, modifiers: _ })                         => Self::Esc,
+            CEvent::Mouse(MouseEvent::Down(MouseButton::Left, xpos, ypos, KeyModifiers::NONE)) =>
+                Self::Press(xpos, ypos),
+            CEvent::Mouse(MouseEvent::ScrollDown(xpos, ypos, KeyModifiers::NONE))              =>
+                Self::ScrollDown(xpos, ypos),
+            CEvent::Mouse(MouseEvent::ScrollUp(xpos, ypos, KeyModifiers::NONE))                =>
+                Self::ScrollUp(xpos, ypos),
+            e                                                                                  =>
+                Self::Unsupported(format!("{:?}", e)),
+        }
+    }
+}
+
+pub struct Events {
+    rx: mpsc::Receiver<Event>,
+}
+
+impl Events {
+    pub fn new(tick_rate: std::time::Duration) -> Self {
+        assert!(tick_rate >= std::time::Duration::from_millis(100), "tick_rate to small");
+        let (tx, rx) = mpsc::channel();
+        {
+            let tx = tx.clone();
+            thread::spawn(move || {
+                let mut last_tick = std::time::Instant::now();
+                loop {
+                    if event::poll(tick_rate-last_tick.elapsed()).unwrap() {
+                        if let Ok(event) = event::read() {
+                            tx.send(event.into()).unwrap();
+                        }
+                    }
+                    if last_tick.elapsed() >= tick_rate {
+                        let _ = tx.send(Event::Tick);
+                        last_tick = std::time::Instant::now();
+                    }
+                }
+            })
+        };
+        Self { rx }
+    }
+
+    pub fn next(&self) -> Result<Event, mpsc::RecvError> {
+        self.rx.recv()
+    }
+}
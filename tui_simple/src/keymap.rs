@@ -0,0 +1,97 @@
+use crate::Event;
+
+/// one key of a binding, matched against events and printed in the help
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    /// any printable character, for text input
+    Text,
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Backspace,
+    Esc,
+    Click,
+    WheelUp,
+    WheelDown,
+}
+
+impl Key {
+    pub fn matches(self, event: &Event) -> bool {
+        match (self, event) {
+            (Key::Char(k), Event::CharKey(c))       => k == *c,
+            (Key::Ctrl(k), Event::CtrlKey(c))       => k == *c,
+            (Key::Text, Event::CharKey(_))          => true,
+            (Key::Up, Event::Up)                    => true,
+            (Key::Down, Event::Down)                => true,
+            (Key::Left, Event::Left)                => true,
+            (Key::Right, Event::Right)              => true,
+            (Key::Enter, Event::Enter)              => true,
+            (Key::Backspace, Event::Backspace)      => true,
+            (Key::Esc, Event::Esc)                  => true,
+            (Key::Click, Event::Press(_, _))        => true,
+            (Key::WheelUp, Event::ScrollUp(_, _))   => true,
+            (Key::WheelDown, Event::ScrollDown(_, _)) => true,
+            _                                       => false,
+        }
+    }
+
+    pub fn label(self) -> String {
+        match self {
+            Key::Char(' ') => "space".into(),
+            Key::Char(c)   => c.to_string(),
+            Key::Ctrl(c)   => format!("Ctrl-{}", c.to_ascii_uppercase()),
+            Key::Text      => "text".into(),
+            Key::Up        => "Up".into(),
+            Key::Down      => "Down".into(),
+            Key::Left      => "Left".into(),
+            Key::Right     => "Right".into(),
+            Key::Enter     => "Enter".into(),
+            Key::Backspace => "Backspace".into(),
+            Key::Esc       => "Esc".into(),
+            Key::Click     => "click".into(),
+            Key::WheelUp   => "wheel up".into(),
+            Key::WheelDown => "wheel down".into(),
+        }
+    }
+}
+
+/// keys that run `action`. Handlers look their action up here, so the help built from
+/// the same table always tells what the keys do.
+pub struct Binding<A: 'static> {
+    pub keys   : &'static [Key],
+    pub action : A,
+    pub help   : &'static str,
+}
+
+/// first binding with a key matching `event`
+pub fn lookup<A: Copy>(map: &[Binding<A>], event: &Event) -> Option<A> {
+    map.iter().find(|b| b.keys.iter().any(|k| k.matches(event))).map(|b| b.action)
+}
+
+/// one line of the help
+#[derive(Clone, Debug)]
+pub struct KeyHelp {
+    pub keys : String,
+    pub help : &'static str,
+}
+
+/// bindings of one mode or widget, as shown in the help
+#[derive(Clone, Debug)]
+pub struct Section {
+    pub title : String,
+    pub keys  : Vec<KeyHelp>,
+}
+
+impl Section {
+    pub fn new<S: ToString, A>(title: S, map: &[Binding<A>]) -> Self {
+        let keys = map.iter().map(|b| KeyHelp {
+            keys : b.keys.iter().map(|k| k.label()).collect::<Vec<_>>().join(" "),
+            help : b.help,
+        }).collect();
+        Self { title: title.to_string(), keys }
+    }
+}
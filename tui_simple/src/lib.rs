@@ -0,0 +1,21 @@
+//! widgets for a terminal trading dashboard, and the app that lays them out.
+//!
+//! Third party widgets implement `app::InteractiveWidget` and are registered by kind in a
+//! `registry::Registry`, the layout config then places them like the built in ones.
+pub mod alert;
+pub mod app;
+pub mod chart;
+pub mod command;
+pub mod config;
+pub mod event;
+pub mod keymap;
+pub mod registry;
+pub mod record;
+pub mod remote;
+pub mod session;
+pub mod source;
+pub mod status;
+pub mod table;
+pub mod tree;
+
+pub use event::Event;
@@ -1,16 +1,24 @@
-#![feature(concat_idents)]
-mod app;
-
-use app::{App, Status};
+use tui_simple::{
+    app::{App, Status},
+    config::Config,
+    event::Events,
+    record::{self, Recorder, Replay, Start},
+    registry::Registry,
+    remote,
+    session,
+    source::{Source, Update},
+};
 
 use log::{debug, warn};
+use std::error::Error;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 use std::io::{self, Read, Write};
 use std::sync::{Arc, RwLock, Mutex};
 use std::thread;
 use std::sync::mpsc;
-use tui::{ backend::{CrosstermBackend, Backend}, Terminal };
+use tui::{ backend::{CrosstermBackend, Backend, TestBackend}, Terminal };
 use fern::colors::{Color, ColoredLevelConfig};
 
 use crossterm::{
@@ -18,96 +26,84 @@ use crossterm::{
     execute,
     ExecutableCommand,
     cursor,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetSize},
 };
 
-#[derive(Debug, Eq, PartialEq, Clone)]
-pub enum Event {
-    Tick,
-    CharKey(char),
-    CtrlKey(char),
-    Up,
-    Down,
-    Left,
-    Right,
-    ScrollUp(u16, u16),
-    ScrollDown(u16, u16),
-    Press(u16, u16),
-    Enter,
-    Backspace,
-    Esc,
-
-    Unsupported(String),
+/// the app as it was when the recording started, fed by the replay instead of a data source
+fn replay_app<B: Backend + 'static>(replay: &Replay, logs: mpsc::Receiver<String>, updates: mpsc::Receiver<Update>)
+    -> Result<App<B>, Box<dyn Error>>
+{
+    let registry = Registry::builtin();
+    let mut app = App::new(logs, updates, replay.start.layout.clone(), &replay.start.rows, &registry)
+        .map_err(|e| format!("bad layout in recording: {}", e))?;
+    app.set_alerts(&replay.start.alerts).map_err(|e| format!("bad alerts in recording: {}", e))?;
+    app.restore_session(&replay.start.session);
+    Ok(app)
 }
 
-impl From<CEvent> for Event {
-    fn from(event: CEvent) -> Self {
-        match event {
-            CEvent::Key(KeyEvent { code: KeyCode::Char(c), modifiers: KeyModifiers::NONE })    => Self::CharKey(c),
-            CEvent::Key(KeyEvent { code: KeyCode::Char(c), modifiers: KeyModifiers::SHIFT })   => Self::CharKey(c),
-            CEvent::Key(KeyEvent { code: KeyCode::Char(c), modifiers: KeyModifiers::CONTROL }) => Self::CtrlKey(c),
-
-            CEvent::Key(KeyEvent { code: KeyCode::Up, modifiers: _ })                          => Self::Up,
-            CEvent::Key(KeyEvent { code: KeyCode::Down, modifiers: _ })                        => Self::Down,
-            CEvent::Key(KeyEvent { code: KeyCode::Left, modifiers: _ })                        => Self::Left,
-            CEvent::Key(KeyEvent { code: KeyCode::Right, modifiers: _ })                       => Self::Right,
-            CEvent::Key(KeyEvent { code: KeyCode::Enter, modifiers: _ })                       => Self::Enter,
-            CEvent::Key(KeyEvent { code: KeyCode::Backspace, modifiers: _ })                   => Self::Backspace,
-            CEvent::Key(KeyEvent { code: KeyCode::Esc, modifiers: _ })                         => Self::Esc,
-            CEvent::Mouse(MouseEvent::Down(MouseButton::Left, xpos, ypos, KeyModifiers::NONE)) =>
-                Self::Press(xpos, ypos),
-            CEvent::Mouse(MouseEvent::ScrollDown(xpos, ypos, KeyModifiers::NONE))              =>
-                Self::ScrollDown(xpos, ypos),
-            CEvent::Mouse(MouseEvent::ScrollUp(xpos, ypos, KeyModifiers::NONE))                =>
-                Self::ScrollUp(xpos, ypos),
-            e                                                                                  =>
-                Self::Unsupported(format!("{:?}", e)),
-        }
-    }
+/// no terminal at all, prints the last screen
+fn replay_headless(replay: &Replay, speed: f64, logs: mpsc::Receiver<String>) -> Result<(), Box<dyn Error>> {
+    let (width, height) = replay.size().unwrap_or((80, 24));
+    let mut terminal = Terminal::new(TestBackend::new(width, height))?;
+    let (tx, updates) = mpsc::channel();
+    let mut app = replay_app(replay, logs, updates)?;
+
+    replay.run(
+        &mut terminal, &mut app, tx, speed,
+        |wait| { thread::sleep(wait); true },
+        |terminal, width, height| { *terminal = Terminal::new(TestBackend::new(width, height))?; Ok(()) },
+    )?;
+    print!("{}", record::dump(terminal.backend().buffer()));
+    Ok(())
 }
 
-pub struct Events {
-    rx: mpsc::Receiver<Event>,
-}
+/// on this terminal, `q` stops early and any key leaves the last screen
+fn replay_visible(replay: &Replay, speed: f64, logs: mpsc::Receiver<String>) -> Result<(), Box<dyn Error>> {
+    let (tx, updates) = mpsc::channel();
+    let mut app = replay_app(replay, logs, updates)?;
+
+    enable_raw_mode()?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    terminal.backend_mut().execute(EnterAlternateScreen)?;
+    terminal.clear()?;
+    terminal.hide_cursor()?;
 
-impl Events {
-    pub fn new(tick_rate: std::time::Duration) -> Self {
-        assert!(tick_rate >= std::time::Duration::from_millis(100), "tick_rate to small");
-        let (tx, rx) = mpsc::channel();
-        {
-            let tx = tx.clone();
-            thread::spawn(move || {
-                let mut last_tick = std::time::Instant::now();
-                loop {
-                    if event::poll(tick_rate-last_tick.elapsed()).unwrap() {
-                        if let Ok(event) = event::read() {
-                            tx.send(event.into()).unwrap();
-                        }
-                    }
-                    if last_tick.elapsed() >= tick_rate {
-                        let _ = tx.send(Event::Tick);
-                        last_tick = std::time::Instant::now();
-                    }
+    let mut stopped = false;
+    let result = replay.run(
+        &mut terminal, &mut app, tx, speed,
+        |wait| {
+            let until = Instant::now() + wait;
+            while let Ok(true) = event::poll(until.saturating_duration_since(Instant::now())) {
+                if let Ok(CEvent::Key(KeyEvent { code: KeyCode::Char('q'), .. })) = event::read() {
+                    stopped = true;
+                    return false;
                 }
-            })
-        };
-        Self { rx }
+            }
+            true
+        },
+        // only some terminals follow, the rest replays at their own size
+        |terminal, width, height| { terminal.backend_mut().execute(SetSize(width, height))?; Ok(()) },
+    );
+    if result.is_ok() && !stopped {
+        let _ = event::read();
     }
 
-    pub fn next(&self) -> Result<Event, mpsc::RecvError> {
-        self.rx.recv()
-    }
+    disable_raw_mode()?;
+    terminal.backend_mut().execute(LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    dotenv::dotenv()?;
-    let level: log::LevelFilter = std::env::var("RUST_LOG").unwrap_or("debug".to_string()).parse()?;
+    dotenv::dotenv().ok();
+    let config = Config::load()?;
+    let source: Source = config.source.parse()?;
 
     //let colors = ColoredLevelConfig::new().info(Color::Green);
 
     let (tx, rx) = mpsc::channel();
     fern::Dispatch::new()
-        .level(level)
+        .level(config.log_level)
         // .chain(
         //     fern::Dispatch::new()
         //     .format(move |out, message, record| {
@@ -132,11 +128,60 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         message
                 ))
             })
-            .chain(fern::log_file("output.log")?)
+            .chain(fern::log_file(&config.log_file)?)
             .chain(tx)
         )
         .apply()?;
 
+    let registry = Registry::builtin();
+    if config.widgets {
+        print!("{}", registry.describe());
+        return Ok(());
+    }
+    if let Some(ref path) = config.replay {
+        let replay = Replay::load(path)?;
+        return if config.headless {
+            replay_headless(&replay, config.speed, rx)
+        } else {
+            replay_visible(&replay, config.speed, rx)
+        };
+    }
+
+    let mut app = App::new(rx, source.spawn(), config.layout.clone(), &config.rows, &registry)
+        .map_err(|e| format!("bad layout: {}", e))?;
+    app.set_alerts(&config.alerts)?;
+
+    let mut sessions = session::SessionFile::load(session::SESSION_FILE).unwrap_or_else(|e| {
+        warn!("ignore broken {}: {}", session::SESSION_FILE, e);
+        Default::default()
+    });
+    if !config.fresh {
+        if let Some(saved) = sessions.get(&app.layout) {
+            app.restore_session(saved);
+        }
+    }
+
+    if let Some(ref path) = config.record {
+        let start = Start {
+            layout  : app.layout.clone(),
+            rows    : config.rows.clone(),
+            alerts  : config.alerts.clone(),
+            session : app.save_session(),
+        };
+        let recorder = Recorder::create(path, start).map_err(|e| format!("{}: {}", path.display(), e))?;
+        app.set_recorder(recorder);
+    }
+
+    // before the terminal is taken over, so a socket in use is reported on a usable screen
+    let _remote = match config.socket {
+        Some(ref path) => {
+            let (server, rx) = remote::RemoteServer::bind(path)?;
+            app.set_remote(rx);
+            Some(server)
+        },
+        None => None,
+    };
+
     enable_raw_mode()?;
     let backend = CrosstermBackend::new(io::stdout());
     let mut terminal = Terminal::new(backend)?;
@@ -145,20 +190,24 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     terminal.clear()?;
     terminal.hide_cursor()?;
 
-    let events = Events::new(std::time::Duration::from_millis(250));
-    let mut app = App::new(rx); let mut cursor_show = false; let mut app_state_insert = false;
+    let events = Events::new(config.tick_rate);
+    let mut cursor_show = false; let mut app_state_insert = false;
+
     loop {
         terminal.draw(|f| app.draw(f))?;
 
-        match events.next()? {
-            Event::CharKey('q') if !app_state_insert => {
-                disable_raw_mode()?;
-                terminal.backend_mut().execute(LeaveAlternateScreen)?;
-                terminal.backend_mut().execute(event::DisableMouseCapture)?;
-                terminal.show_cursor()?;
-                break;
+        app.on_event(events.next()?);
+        if app.quitting() {
+            disable_raw_mode()?;
+            terminal.backend_mut().execute(LeaveAlternateScreen)?;
+            terminal.backend_mut().execute(event::DisableMouseCapture)?;
+            terminal.show_cursor()?;
+
+            sessions.insert(app.layout.clone(), app.save_session());
+            if let Err(e) = sessions.save(session::SESSION_FILE) {
+                warn!("failed to save {}: {}", session::SESSION_FILE, e);
             }
-            e => app.on_event(e),
+            break;
         }
         match app.status {
             Status::Insert(ref m) => {
@@ -0,0 +1,169 @@
+use crate::Event;
+use crate::alert::RuleConfig;
+use crate::app::App;
+use crate::config::RowConfig;
+use crate::remote::{RemoteCommand, Request};
+use crate::session::Session;
+use crate::source::Update;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tui::{backend::Backend, buffer::Buffer, Terminal};
+
+/// what the app started with, a replay needs the same layout and session to end up in the same state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Start {
+    pub layout  : String,
+    pub rows    : Vec<RowConfig>,
+    #[serde(default)]
+    pub alerts  : Vec<RuleConfig>,
+    pub session : Session,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Entry {
+    Start(Start),
+    /// written before the app handles it, so the file ends with the event that crashed
+    Event(Event),
+    /// data going into a table, from the source or remote control
+    Update(Update),
+    /// a remote control focus or command, run on the tick it came in
+    Remote(RemoteCommand),
+    Resize(u16, u16),
+}
+
+/// one json line of a recording, `at` is milliseconds since the recording started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub at    : u64,
+    pub entry : Entry,
+}
+
+pub struct Recorder {
+    out   : BufWriter<File>,
+    start : Instant,
+}
+
+impl Recorder {
+    pub fn create<P: AsRef<Path>>(path: P, start: Start) -> std::io::Result<Self> {
+        let mut slf = Self { out: BufWriter::new(File::create(path)?), start: Instant::now() };
+        slf.record(Entry::Start(start));
+        Ok(slf)
+    }
+
+    /// flushed right away, the app may be about to panic
+    pub fn record(&mut self, entry: Entry) {
+        let record = Record { at: self.start.elapsed().as_millis() as u64, entry };
+        let written = serde_json::to_writer(&mut self.out, &record).map_err(std::io::Error::from)
+            .and_then(|_| self.out.write_all(b"\n"))
+            .and_then(|_| self.out.flush());
+        if let Err(e) = written {
+            warn!("recording failed: {}", e);
+        }
+    }
+}
+
+/// a recording read back, see `Replay::run`
+pub struct Replay {
+    pub start : Start,
+    records   : Vec<Record>,
+}
+
+impl Replay {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut records = Vec::new();
+        for (idx, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| format!("{}: {}", path.display(), e))?;
+            if line.trim().is_empty() { continue; }
+            match serde_json::from_str::<Record>(&line) {
+                Ok(record) => records.push(record),
+                // the last line is cut short if the app died while writing it
+                Err(e)     => { warn!("{}:{}: {}", path.display(), idx + 1, e); break; },
+            }
+        }
+
+        match records.first() {
+            Some(Record { entry: Entry::Start(start), .. }) => {
+                let start = start.clone();
+                records.remove(0);
+                Ok(Self { start, records })
+            },
+            _ => Err(format!("{} is not a recording", path.display())),
+        }
+    }
+
+    /// first recorded terminal size
+    pub fn size(&self) -> Option<(u16, u16)> {
+        self.records.iter().find_map(|r| match r.entry {
+            Entry::Resize(width, height) => Some((width, height)),
+            _                            => None,
+        })
+    }
+
+    /// feed the recording through `app` the way main does, drawing before every event.
+    /// `wait` is given the time until the next event, it returns false to stop early.
+    /// `resize` adapts the terminal to a recorded size.
+    pub fn run<B, W, R>(&self, terminal: &mut Terminal<B>, app: &mut App<B>, updates: mpsc::Sender<Update>,
+                        speed: f64, mut wait: W, mut resize: R) -> Result<(), Box<dyn Error>>
+        where B: Backend + 'static,
+              W: FnMut(Duration) -> bool,
+              R: FnMut(&mut Terminal<B>, u16, u16) -> Result<(), Box<dyn Error>>,
+    {
+        // remote requests go in the way they came, nobody waits for the replies
+        let (remote, requests) = mpsc::channel();
+        app.set_remote(requests);
+        let (reply, _) = mpsc::channel();
+        let request = |cmd: &RemoteCommand| Request { cmd: cmd.clone(), reply: reply.clone() };
+
+        let begin = Instant::now();
+        let mut idx = 0;
+        while idx < self.records.len() {
+            let record = &self.records[idx];
+            idx += 1;
+            match record.entry {
+                Entry::Resize(width, height) => resize(terminal, width, height)?,
+                Entry::Event(ref event)      => {
+                    if speed > 0.0 {
+                        let due = Duration::from_millis((record.at as f64 / speed) as u64);
+                        if !wait(due.checked_sub(begin.elapsed()).unwrap_or_default()) { break; }
+                    }
+                    terminal.draw(|f| app.draw(f))?;
+                    // updates and requests the app took while handling this event were written after it
+                    while let Some(record) = self.records.get(idx) {
+                        match record.entry {
+                            Entry::Update(ref update) => { let _ = updates.send(update.clone()); },
+                            Entry::Remote(ref cmd)    => { let _ = remote.send(request(cmd)); },
+                            _                         => break,
+                        }
+                        idx += 1;
+                    }
+                    app.on_event(event.clone());
+                },
+                Entry::Update(ref update)    => { let _ = updates.send(update.clone()); },
+                Entry::Remote(ref cmd)       => { let _ = remote.send(request(cmd)); },
+                Entry::Start(_)              => warn!("skip second start in recording"),
+            }
+        }
+        terminal.draw(|f| app.draw(f))?;
+        Ok(())
+    }
+}
+
+/// the screen as text, for headless replays
+pub fn dump(buffer: &Buffer) -> String {
+    let mut text = String::new();
+    for y in 0..buffer.area.height {
+        let line: String = (0..buffer.area.width).map(|x| buffer.get(buffer.area.x + x, buffer.area.y + y).symbol.as_str()).collect();
+        text.push_str(line.trim_end());
+        text.push('\n');
+    }
+    text
+}
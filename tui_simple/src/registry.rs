@@ -0,0 +1,289 @@
+use crate::alert::IAlerts;
+use crate::app::{IEmpty, IParagraph, InteractiveWidget, TREND_LEN};
+use crate::chart::IChart;
+use crate::status::NOTIFY;
+use crate::table::{Aggregate, CellType, ITable, default_highlight};
+use crate::tree::{ITree, TreeNode};
+
+use log::info;
+use std::collections::BTreeMap;
+use tui::{
+    backend::Backend,
+    style::{Color, Style},
+};
+
+/// keys of one widget in the layout config, without name, kind, width and title
+pub type Options = toml::value::Table;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FieldKind {
+    Integer,
+    Bool,
+    Text,
+    TextList,
+}
+
+impl FieldKind {
+    fn matches(self, value: &toml::Value) -> bool {
+        match (self, value) {
+            (FieldKind::Integer, toml::Value::Integer(_))  => true,
+            (FieldKind::Bool, toml::Value::Boolean(_))     => true,
+            (FieldKind::Text, toml::Value::String(_))      => true,
+            (FieldKind::TextList, toml::Value::Array(arr)) => arr.iter().all(|v| v.is_str()),
+            _                                              => false,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            FieldKind::Integer  => "integer",
+            FieldKind::Bool     => "bool",
+            FieldKind::Text     => "string",
+            FieldKind::TextList => "list of strings",
+        }
+    }
+}
+
+/// one option a widget kind accepts, options are checked against these before the widget is built
+#[derive(Clone, Copy, Debug)]
+pub struct Field {
+    pub name     : &'static str,
+    pub kind     : FieldKind,
+    pub required : bool,
+    pub help     : &'static str,
+}
+
+pub type Build<B> = Box<dyn Fn(&Options) -> Result<Box<dyn InteractiveWidget<B>>, String>>;
+
+pub struct Factory<B: Backend> {
+    pub help   : &'static str,
+    pub schema : &'static [Field],
+    build      : Build<B>,
+}
+
+/// widget kinds by name, the layout config builds its widgets from here.
+pub struct Registry<B: Backend> {
+    factories: BTreeMap<String, Factory<B>>,
+}
+
+impl<B: Backend> Default for Registry<B> {
+    fn default() -> Self {
+        Self { factories: BTreeMap::new() }
+    }
+}
+
+impl<B: Backend + 'static> Registry<B> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// a registry with every widget that comes with this crate
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.add("table", "rows pushed by the data source or remote control", TABLE_SCHEMA, Box::new(build_table::<B>));
+        registry.add("assets", "placeholder for the asset panel", &[], plain(IEmpty::default));
+        registry.add("orders", "placeholder for the order panel", &[], plain(IEmpty::default));
+        registry.add("trades", "orders and trades, price and volume can be amended", &[], plain(trades_table));
+        registry.add("watch", "last price, volume and trend per code, fed by trades", &[], plain(watch_table));
+        registry.add("positions", "positions by account and strategy", &[], plain(positions_tree));
+        registry.add("chart", "price chart of one code at a time, fed by trades", CHART_SCHEMA, Box::new(build_chart::<B>));
+        registry.add("logs", "the application log", &[], plain(IParagraph::new));
+        registry.add("alerts", "alerts raised by the rules in the config", &[], plain(IAlerts::new));
+        registry.add("empty", "leaves its space blank", &[], plain(IEmpty::default));
+        registry
+    }
+
+    fn add(&mut self, kind: &str, help: &'static str, schema: &'static [Field], build: Build<B>) {
+        self.factories.insert(kind.to_string(), Factory { help, schema, build });
+    }
+
+    pub fn register<F>(&mut self, kind: &str, help: &'static str, schema: &'static [Field], build: F) -> Result<(), String>
+        where F: Fn(&Options) -> Result<Box<dyn InteractiveWidget<B>>, String> + 'static
+    {
+        if self.factories.contains_key(kind) {
+            return Err(format!("widget kind {} is already registered", kind));
+        }
+        self.add(kind, help, schema, Box::new(build));
+        Ok(())
+    }
+
+    pub fn build(&self, kind: &str, options: &Options) -> Result<Box<dyn InteractiveWidget<B>>, String> {
+        let factory = self.factories.get(kind).ok_or_else(|| {
+            let kinds: Vec<&str> = self.factories.keys().map(|k| k.as_str()).collect();
+            format!("unknown widget kind {}, expect one of {}", kind, kinds.join(", "))
+        })?;
+        check(kind, factory.schema, options)?;
+        (factory.build)(options)
+    }
+
+    /// kinds and their options, for `--widgets`
+    pub fn describe(&self) -> String {
+        let mut text = String::new();
+        for (kind, factory) in self.factories.iter() {
+            text.push_str(&format!("{:<12} {}\n", kind, factory.help));
+            for field in factory.schema.iter() {
+                let required = if field.required { ", required" } else { "" };
+                text.push_str(&format!("    {:<14} {}{}: {}\n", field.name, field.kind.name(), required, field.help));
+            }
+        }
+        text
+    }
+}
+
+fn check(kind: &str, schema: &[Field], options: &Options) -> Result<(), String> {
+    for (key, value) in options.iter() {
+        match schema.iter().find(|f| f.name == key) {
+            Some(field) if field.kind.matches(value) => {  },
+            Some(field) => return Err(format!("{}: {} should be {}", kind, key, field.kind.name())),
+            None        => {
+                let names: Vec<&str> = schema.iter().map(|f| f.name).collect();
+                return Err(format!("{} has no option {}, it takes [{}]", kind, key, names.join(", ")));
+            },
+        }
+    }
+    match schema.iter().find(|f| f.required && !options.contains_key(f.name)) {
+        Some(field) => Err(format!("{} needs option {}", kind, field.name)),
+        None        => Ok(()),
+    }
+}
+
+const TABLE_SCHEMA: &[Field] = &[
+    Field { name: "columns",      kind: FieldKind::TextList, required: true,  help: "column headers" },
+    Field { name: "column_width", kind: FieldKind::Integer,  required: false, help: "width of every column, 8 if not set" },
+    Field { name: "key",          kind: FieldKind::Text,     required: false, help: "column whose value identifies a row" },
+];
+
+const CHART_SCHEMA: &[Field] = &[
+    Field { name: "bar_size", kind: FieldKind::Integer, required: false, help: "trades per candle" },
+    Field { name: "zoom",     kind: FieldKind::Integer, required: false, help: "points or candles in view" },
+];
+
+/// kinds without options
+fn plain<B: Backend + 'static, W: InteractiveWidget<B> + 'static>(make: fn() -> W) -> Build<B> {
+    Box::new(move |_: &Options| Ok(Box::new(make()) as Box<dyn InteractiveWidget<B>>))
+}
+
+fn build_table<B: Backend>(options: &Options) -> Result<Box<dyn InteractiveWidget<B>>, String> {
+    let columns: Vec<&str> = options["columns"].as_array().into_iter().flatten().filter_map(|v| v.as_str()).collect();
+    if columns.is_empty() { return Err("table: columns is empty".into()); }
+    let width = options.get("column_width").and_then(|v| v.as_integer()).unwrap_or(8).max(1) as usize;
+
+    let mut table = ITable::new();
+    for header in columns.iter() {
+        table.add_column(*header, Style::default(), width, Vec::<&str>::new(), default_highlight);
+    }
+    if let Some(key) = options.get("key").and_then(|v| v.as_str()) {
+        let col = table.column_index(key).ok_or_else(|| format!("table: key {} is not a column", key))?;
+        table.set_key(col);
+    }
+    Ok(Box::new(table))
+}
+
+fn build_chart<B: Backend>(options: &Options) -> Result<Box<dyn InteractiveWidget<B>>, String> {
+    let mut chart = IChart::new();
+    if let Some(size) = options.get("bar_size").and_then(|v| v.as_integer()) {
+        if size < 1 { return Err("chart: bar_size must be positive".into()); }
+        chart.set_bar_size(size as usize);
+    }
+    if let Some(zoom) = options.get("zoom").and_then(|v| v.as_integer()) {
+        if zoom < 4 { return Err("chart: zoom must be at least 4".into()); }
+        chart.set_zoom(zoom as usize);
+    }
+    Ok(Box::new(chart))
+}
+
+pub fn watch_table() -> ITable {
+    let mut watch = ITable::new();
+    watch.add_column("code", Style::default(), 6, Vec::<&str>::new(), default_highlight);
+    watch.add_column("last", Style::default(), 8, Vec::<&str>::new(), default_highlight);
+    watch.add_column("volume", Style::default(), 8, Vec::<&str>::new(), default_highlight);
+    watch.add_column("trend", Style::default(), TREND_LEN, Vec::<&str>::new(), |_, _| Style::default().fg(Color::Cyan));
+    let trend = watch.column_index("trend").expect("watch has a trend column");
+    watch.set_sparkline(trend, true);
+    watch
+}
+
+pub fn positions_tree() -> ITree {
+    let mut positions = ITree::new();
+    positions.add_root(
+        TreeNode::new("account 8800001").style(Style::default().fg(Color::Yellow)).expanded()
+        .child(TreeNode::new("arbitrage").lazy())
+        .child(TreeNode::new("momentum").lazy())
+    );
+    positions.set_loader(|path: &[&str]| {
+        let rows: &[(&str, &str)] = match path {
+            [_, "arbitrage"] => &[("000001.SZSE", "long 300"), ("000001.SSE", "short 300")],
+            [_, "momentum"]  => &[("000002.SZSE", "long 10000")],
+            _                => &[],
+        };
+        rows.iter().map(|(code, pos)| TreeNode::new(format!("{:<12} {}", code, pos))).collect()
+    });
+    positions
+}
+
+pub fn trades_table() -> ITable {
+    let mut trades = ITable::new();
+    trades.add_column(
+        "code", Style::default(), 6,
+        Vec::<&str>::new(),
+        |_, _| { Style::default().fg(Color::White) }
+    );
+    trades.add_column(
+        "exchange", Style::default(), 8,
+        Vec::<&str>::new(),
+        |_, _| { Style::default().fg(Color::White) }
+    );
+    trades.add_column(
+        "price", Style::default(), 8,
+        Vec::<&str>::new(),
+        |_, _| { Style::default().fg(Color::White) }
+    );
+    trades.add_column(
+        "volume", Style::default(), 10,
+        Vec::<&str>::new(),
+        |_, _| { Style::default().fg(Color::White) }
+    );
+    trades.add_column(
+        "direction", Style::default(), 9,
+        Vec::<&str>::new(),
+        |r: &str, s: bool| {
+            match (r, s) {
+                ("Buy", false) => Style::default().fg(Color::Green),
+                ("Sell", false)   => Style::default().fg(Color::Cyan),
+                _                  => Style::default().fg(Color::White)
+            }
+        }
+    );
+    trades.add_column(
+        "status", Style::default(), 8,
+        Vec::<&str>::new(),
+        |r: &str, s: bool| {
+            match (r, s) {
+                ("Pending", false) => Style::default().fg(Color::Magenta),
+                ("Error", false)   => Style::default().fg(Color::Red),
+                _                  => Style::default().fg(Color::White)
+            }
+        }
+    );
+
+    let column = |header: &str| trades.column_index(header).expect("trades has all its columns");
+    let (code, exchange, price, volume, status) =
+        (column("code"), column("exchange"), column("price"), column("volume"), column("status"));
+    trades.set_aggregate(code, Aggregate::Count);
+    trades.set_aggregate(price, Aggregate::Vwap { price, volume });
+    trades.set_aggregate(volume, Aggregate::Sum(volume));
+    trades.set_editable(price, CellType::Decimal);
+    trades.set_editable(volume, CellType::Integer);
+    trades.on_change(move |change, row| {
+        if row[status] != "Pending" {
+            return Err(format!("only pending orders can be amended, this one is {}", row[status]));
+        }
+        info!(target: NOTIFY, "amend {} {} {}: {} -> {}", row[code], row[exchange], change.header, change.old, change.new);
+        Ok(())
+    });
+    trades.on_cancel(move |row| match row[status].as_str() {
+        "Pending" => Some((status, "Cancel".to_string())),
+        _         => None,
+    });
+    trades
+}
@@ -0,0 +1,126 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// one json object per line, e.g. `{"cmd": "command", "line": "group status"}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum RemoteCommand {
+    PushRow { table: String, row: Vec<String> },
+    Log { line: String, #[serde(default)] level: Option<String> },
+    Focus { widget: String },
+    /// same as typing `:line` in the app
+    Command { line: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    pub ok    : bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error : Option<String>,
+}
+
+impl Response {
+    fn error<S: ToString>(e: S) -> Self {
+        Response { ok: false, error: Some(e.to_string()) }
+    }
+}
+
+impl From<Result<(), String>> for Response {
+    fn from(res: Result<(), String>) -> Self {
+        match res {
+            Ok(())   => Response { ok: true, error: None },
+            Err(e)   => Response::error(e),
+        }
+    }
+}
+
+/// a command waiting for the app, answered through `reply`
+pub struct Request {
+    pub cmd   : RemoteCommand,
+    pub reply : mpsc::Sender<Response>,
+}
+
+/// the app only looks at requests on tick, so give it a few of them
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// listening socket, the file is removed on drop
+pub struct RemoteServer {
+    path: PathBuf,
+}
+
+impl RemoteServer {
+    pub fn bind<P: AsRef<Path>>(path: P) -> io::Result<(Self, mpsc::Receiver<Request>)> {
+        let path = path.as_ref().to_path_buf();
+        // a socket left over from a crashed run, nobody answers on it; never any other file
+        match fs::symlink_metadata(&path) {
+            Ok(meta) if meta.file_type().is_socket() => {
+                if UnixStream::connect(&path).is_ok() {
+                    return Err(io::Error::new(io::ErrorKind::AddrInUse, format!("{} is in use", path.display())));
+                }
+                fs::remove_file(&path)?;
+            },
+            Ok(_) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} exists and is not a socket", path.display())));
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {  },
+            Err(e) => return Err(e),
+        }
+        let listener = UnixListener::bind(&path)?;
+        info!("remote control on {}", path.display());
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let tx = tx.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = serve(stream, tx) { warn!("remote client: {}", e); }
+                        });
+                    },
+                    Err(e) => warn!("remote accept: {}", e),
+                }
+            }
+        });
+        Ok((Self { path }, rx))
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn serve(stream: UnixStream, tx: mpsc::Sender<Request>) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() { continue; }
+
+        let response = match serde_json::from_str::<RemoteCommand>(&line) {
+            Ok(cmd) => {
+                let (reply, rx) = mpsc::channel();
+                if tx.send(Request { cmd, reply }).is_err() {
+                    return Ok(()); // app is gone
+                }
+                rx.recv_timeout(REPLY_TIMEOUT)
+                    .unwrap_or_else(|_| Response::error("app did not answer"))
+            },
+            Err(e)  => Response::error(format!("bad request: {}", e)),
+        };
+
+        let mut text = serde_json::to_string(&response).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        text.push('\n');
+        writer.write_all(text.as_bytes())?;
+    }
+    Ok(())
+}
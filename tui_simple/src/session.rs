@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const SESSION_FILE: &str = "session.json";
+
+/// ui state of one layout, restored on next start.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub curr_widget : Option<String>,
+    pub widgets     : HashMap<String, serde_json::Value>,
+    pub history     : Vec<String>,
+}
+
+/// all saved sessions, keyed by layout name.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SessionFile {
+    layouts: HashMap<String, Session>,
+}
+
+impl SessionFile {
+    /// a missing file is not an error, it just means nothing was saved yet.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, text)
+    }
+
+    pub fn get(&self, layout: &str) -> Option<&Session> {
+        self.layouts.get(layout)
+    }
+
+    pub fn insert(&mut self, layout: String, session: Session) {
+        self.layouts.insert(layout, session);
+    }
+}
@@ -0,0 +1,113 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// one row for a named table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Update {
+    pub table : String,
+    pub row   : Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Source {
+    /// a few made up trades
+    Demo,
+    /// made up trades as fast as the app takes them, to try large tables
+    Generate(usize),
+    /// json lines of `Update`, followed like `tail -f`
+    File(PathBuf),
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        if uri == "demo" {
+            Ok(Source::Demo)
+        } else if uri.starts_with("gen:") {
+            let rows = uri["gen:".len()..].parse().map_err(|_| format!("bad row count in {}", uri))?;
+            Ok(Source::Generate(rows))
+        } else if uri.starts_with("file:") {
+            Ok(Source::File(uri["file:".len()..].into()))
+        } else {
+            Err(format!("unknown data source {}, expect `demo`, `gen:<rows>` or `file:<path>`", uri))
+        }
+    }
+}
+
+impl Source {
+    pub fn spawn(self) -> mpsc::Receiver<Update> {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            match self {
+                Source::Demo           => demo(tx),
+                Source::Generate(rows) => generate(rows, tx),
+                Source::File(path)     => follow(path, tx),
+            }
+        });
+        rx
+    }
+}
+
+fn demo(tx: mpsc::Sender<Update>) {
+    let rows = vec![
+        vec!["000001", "SZSE", "12.23", "100",   "Buy",  "Cancel"],
+        vec!["000001", "SSE",  "12.45", "700",   "Sell", "Pending"],
+        vec!["000002", "SZSE", "13.45", "10000", "Buy",  "Error"],
+    ];
+    for row in rows {
+        let row = row.into_iter().map(String::from).collect();
+        if tx.send(Update { table: "trades".into(), row }).is_err() { return; }
+    }
+}
+
+fn generate(rows: usize, tx: mpsc::Sender<Update>) {
+    const CODES: [&str; 4] = ["000001", "000002", "600000", "600519"];
+    const STATUS: [&str; 4] = ["Pending", "Filled", "Cancel", "Error"];
+    for i in 0..rows {
+        // cheap deterministic noise, no need for a random generator
+        let noise = ((i as u64).wrapping_mul(2_654_435_761) >> 7) as usize % 1000;
+        let row = vec![
+            CODES[i % CODES.len()].to_string(),
+            if i % 3 == 0 { "SSE" } else { "SZSE" }.to_string(),
+            format!("{:.2}", 10.0 + (i % CODES.len()) as f64 + noise as f64 / 100.0),
+            ((noise + 1) * 100).to_string(),
+            if noise % 2 == 0 { "Buy" } else { "Sell" }.to_string(),
+            STATUS[noise % STATUS.len()].to_string(),
+        ];
+        if tx.send(Update { table: "trades".into(), row }).is_err() { return; }
+    }
+}
+
+fn follow(path: PathBuf, tx: mpsc::Sender<Update>) {
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e)   => { warn!("can not open data source {}: {}", path.display(), e); return; },
+    };
+
+    let mut reader = BufReader::new(file);
+    let mut line = String::new();
+    loop {
+        match reader.read_line(&mut line) {
+            Ok(0)  => thread::sleep(Duration::from_millis(500)), // wait for more
+            Ok(_) if !line.ends_with('\n') => {  }, // writer is in the middle of a line
+            Ok(_)  => {
+                if !line.trim().is_empty() {
+                    match serde_json::from_str::<Update>(line.trim()) {
+                        Ok(update) => if tx.send(update).is_err() { return; },
+                        Err(e)     => warn!("skip bad line in {}: {}", path.display(), e),
+                    }
+                }
+                line.clear();
+            },
+            Err(e) => { warn!("stop reading {}: {}", path.display(), e); return; },
+        }
+    }
+}
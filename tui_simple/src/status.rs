@@ -0,0 +1,126 @@
+use std::time::{Duration, Instant};
+use tui::{
+    backend::Backend,
+    terminal::Frame,
+
+    style::{Color, Style},
+    widgets::*,
+    layout::*,
+};
+
+/// log target for messages the user should see in the status bar, e.g.
+/// `info!(target: NOTIFY, "exported {} rows", count)`. Warnings and errors are shown anyway.
+pub const NOTIFY: &str = "notify";
+
+const TOAST_TIME: Duration = Duration::from_secs(5);
+/// a source without updates for this long is shown as idle
+const IDLE_TIME: Duration = Duration::from_secs(10);
+
+/// bottom line: mode, focused widget and its rows on the left, the latest notification in the
+/// middle, data source, unread errors and a clock on the right.
+#[derive(Default)]
+pub struct StatusBar {
+    toast       : Option<(String, Style, Instant)>, // text, style, shown until
+    errors      : usize, // error lines not looked at in the logs yet
+    alerts      : usize, // alerts not acknowledged yet
+    last_update : Option<Instant>,
+    closed      : bool, // the source is done or gone
+}
+
+/// `[time level target] message` as formatted in main
+fn split_log_line(line: &str) -> Option<(&str, &str, &str)> {
+    let end = line.find("] ")?;
+    let mut head = line.get(1..end)?.split_whitespace();
+    let (_time, level, target) = (head.next()?, head.next()?, head.next()?);
+    Some((level, target, line[end + 2..].trim_end()))
+}
+
+impl StatusBar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn notify<S: ToString>(&mut self, text: S, style: Style) {
+        self.toast = Some((text.to_string(), style, Instant::now() + TOAST_TIME));
+    }
+
+    /// look at every log line, errors are counted until the logs are read
+    pub fn on_log(&mut self, line: &str) {
+        match split_log_line(line) {
+            Some(("ERROR", _, message))  => {
+                self.errors += 1;
+                self.notify(message, Style::default().fg(Color::White).bg(Color::Red));
+            },
+            Some(("WARN", _, message))   => self.notify(message, Style::default().fg(Color::Yellow)),
+            Some((_, NOTIFY, message))   => self.notify(message, Style::default().fg(Color::Green)),
+            _                            => {  },
+        }
+    }
+
+    pub fn read_errors(&mut self) {
+        self.errors = 0;
+    }
+
+    pub fn set_alerts(&mut self, count: usize) {
+        self.alerts = count;
+    }
+
+    pub fn source_alive(&mut self) {
+        self.last_update = Some(Instant::now());
+    }
+
+    pub fn source_closed(&mut self) {
+        self.closed = true;
+    }
+
+    fn source_segment(&self) -> (String, Style) {
+        match self.last_update {
+            _ if self.closed                         => ("source done".into(), Style::default().fg(Color::Gray)),
+            None                                     => ("source waiting".into(), Style::default().fg(Color::Yellow)),
+            Some(at) if at.elapsed() < IDLE_TIME     => ("source live".into(), Style::default().fg(Color::Green)),
+            Some(at)                                 =>
+                (format!("source idle {}s", at.elapsed().as_secs()), Style::default().fg(Color::Yellow)),
+        }
+    }
+
+    /// `left` are the segments the app knows about, `hint` fills the middle when there is no notification
+    pub fn draw<B: Backend>(&mut self, f: &mut Frame<B>, area: Rect, left: Vec<(String, Style)>, hint: &str) {
+        if self.toast.as_ref().map_or(false, |(_, _, until)| *until < Instant::now()) {
+            self.toast = None;
+        }
+
+        let mut right = vec![self.source_segment()];
+        if self.errors > 0 {
+            right.push((format!("{} errors", self.errors), Style::default().fg(Color::White).bg(Color::Red)));
+        }
+        if self.alerts > 0 {
+            right.push((format!("{} alerts", self.alerts), Style::default().fg(Color::Black).bg(Color::Yellow)));
+        }
+        right.push((chrono::Local::now().format("%H:%M:%S").to_string(), Style::default().fg(Color::Gray)));
+
+        let mut left_text = vec![];
+        for (text, style) in left.into_iter() {
+            left_text.push(Text::styled(format!(" {} ", text), style));
+            left_text.push(Text::raw(" "));
+        }
+        match self.toast {
+            Some((ref text, style, _)) => left_text.push(Text::styled(text.clone(), style)),
+            None                       =>
+                left_text.push(Text::styled(hint.to_string(), Style::default().fg(Color::DarkGray))),
+        }
+
+        let width: usize = right.iter().map(|(text, _)| text.chars().count() + 1).sum();
+        let mut right_text = vec![];
+        for (text, style) in right.into_iter() {
+            right_text.push(Text::raw(" "));
+            right_text.push(Text::styled(text, style));
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(width as u16)].as_ref())
+            .split(area);
+        f.render_widget(Paragraph::new(left_text.iter()), chunks[0]);
+        f.render_widget(Paragraph::new(right_text.iter()).alignment(Alignment::Right), chunks[1]);
+    }
+}
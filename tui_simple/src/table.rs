@@ -0,0 +1,1529 @@
+#![allow(dead_code)]
+use crate::app::{Interactive, InteractiveWidget};
+use crate::chart::sparkline;
+use crate::keymap::{self, Binding, Key, Section};
+use crate::status::NOTIFY;
+use crate::Event;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::Write;
+use std::time::{Duration, Instant};
+use tui::{
+    backend::Backend,
+    terminal::Frame,
+
+    style::{Color, Modifier, Style},
+    widgets::*,
+    layout::*,
+};
+
+/// table of text cells that stays responsive with hundreds of thousands of rows.
+///
+/// Rows live in a slab and keep their id until removed, so the selection and the
+/// key index never shift. The view only holds row ids and is rebuilt lazily, at
+/// most once per frame; rows changed since are merged into the sorted ones rather
+/// than sorting again, footer totals follow the changed rows only, and only the
+/// lines on screen are turned into `Text`.
+#[derive(Default)]
+pub struct ITable {
+    content    : Vec<Column>,
+
+    rows       : Vec<Option<Vec<String>>>, // slab indexed by row id, None once removed
+    order      : Vec<usize>, // row ids in insertion order, may still hold removed ones
+    dead       : Vec<usize>, // removed ids still in `order`
+    free       : Vec<usize>, // removed ids ready for reuse
+    len        : usize,
+
+    key        : Option<usize>, // column the index is built on
+    index      : HashMap<String, usize>, // key cell to row id
+
+    idx_page   : usize, // index into view
+    idx_select : usize, // index into view
+    restore    : Option<(usize, usize)>, // saved (idx_select, idx_page) waiting for the rows to arrive
+
+    view       : Vec<Line>, // group and row lines, only built when rows are grouped
+    dirty      : bool, // view and footer need a rebuild
+    ordered    : Vec<usize>, // row ids passing the filter, in sort order, the lines unless grouped
+    matched    : Vec<bool>, // by row id, alive and passing the filter
+    touched    : Vec<usize>, // rows added, changed or removed since, merged into `ordered`
+    cached     : bool, // `ordered`, `matched` and `totals` are valid but for `touched`
+    rebuilds   : usize, // times every row was filtered and sorted
+    totals     : Vec<Totals>, // one per aggregate, over the rows in `ordered`
+    counted    : Vec<bool>, // by row id, cells included in `totals`
+    footer     : Vec<String>, // aggregates over all shown rows, one per column
+    sort       : Option<(usize, bool)>, // (column, descending)
+    filter     : Option<(Option<usize>, String)>, // (column or any column, lowercase text)
+
+    group_by   : Vec<usize>,
+    collapsed  : HashSet<Vec<String>>, // group keys, outermost first
+    aggregates : Vec<(usize, Aggregate)>, // shown under column in group lines and the footer
+
+    selected   : BTreeSet<usize>, // rows picked with space or a finished visual range
+    visual     : Option<usize>,   // row id anchoring the running visual range
+
+    idx_col    : usize, // cell cursor, only shown when some column is editable
+    editing    : Option<Edit>,
+    undo       : Vec<CellChange>, // accepted edits, latest last
+    on_change  : Option<Box<dyn FnMut(&CellChange, &[String]) -> Result<(), String>>>,
+    on_cancel  : Option<Box<dyn Fn(&[String]) -> Option<(usize, String)>>>,
+
+    changes    : Option<Vec<RowChange>>, // kept only once someone asked for them
+    flashes    : HashMap<usize, Instant>, // row id to end of its flash
+
+    window     : Rect,
+}
+
+const MAX_UNDO: usize = 100;
+/// a flashing row switches between normal and alert colors this often
+const FLASH_PERIOD: Duration = Duration::from_millis(500);
+
+struct Column {
+    header    : String,
+    hstyle    : Style,
+    width     : usize,
+    style     : Box<dyn Fn(&str, bool) -> Style>,
+    sparkline : bool, // cells are space separated numbers, drawn as a sparkline
+    kind      : Option<CellType>, // None for read only
+}
+
+/// what an editable column accepts
+#[derive(Clone, Debug)]
+pub enum CellType {
+    Text,
+    Integer,
+    Decimal,
+    /// one of the listed values
+    Choice(Vec<String>),
+}
+
+impl CellType {
+    /// the value to store, or why `text` does not fit
+    pub fn validate(&self, text: &str) -> Result<String, String> {
+        let text = text.trim();
+        match self {
+            CellType::Text           => Ok(text.to_string()),
+            CellType::Integer        => text.parse::<i64>()
+                .map(|v| v.to_string())
+                .map_err(|_| format!("{} is not an integer", text)),
+            CellType::Decimal        => match text.parse::<f64>() {
+                Ok(v) if v.is_finite() => Ok(text.to_string()),
+                _                      => Err(format!("{} is not a number", text)),
+            },
+            CellType::Choice(values) => values.iter()
+                .find(|v| v.eq_ignore_ascii_case(text))
+                .cloned()
+                .ok_or_else(|| format!("expect one of {}", values.join(", "))),
+        }
+    }
+}
+
+/// one accepted edit, undo applies it backwards
+#[derive(Clone, Debug)]
+pub struct CellChange {
+    pub row    : usize, // row id
+    pub col    : usize,
+    pub header : String,
+    pub old    : String,
+    pub new    : String,
+}
+
+/// a row added or changed since the last `ITable::take_changes`
+#[derive(Clone, Debug)]
+pub struct RowChange {
+    pub row : usize, // row id
+    pub old : Option<Vec<String>>, // None for a new row
+    pub new : Vec<String>,
+}
+
+/// a cell being edited in place
+struct Edit {
+    row   : usize,
+    col   : usize,
+    text  : String,
+    error : Option<String>, // why the last enter was refused
+}
+
+impl Edit {
+    fn input(&mut self, key: EditKey, event: &Event) {
+        match (key, event) {
+            (EditKey::Type, Event::CharKey(c)) => self.text.push(*c),
+            (EditKey::Erase, _)                => { self.text.pop(); },
+            (EditKey::Clear, _)                => self.text.clear(),
+            _                                  => return,
+        }
+        self.error = None;
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum TableKey {
+    Visual,
+    Toggle,
+    SelectAll,
+    ClearSelection,
+    Enter,
+    Edit,
+    ColumnLeft,
+    ColumnRight,
+    Undo,
+}
+
+const TABLE_KEYS: &[Binding<TableKey>] = &[
+    Binding { keys: &[Key::Char('V')],            action: TableKey::Visual,         help: "start or end a visual range" },
+    Binding { keys: &[Key::Char(' ')],            action: TableKey::Toggle,         help: "select or unselect row" },
+    Binding { keys: &[Key::Ctrl('a')],            action: TableKey::SelectAll,      help: "select all shown rows" },
+    Binding { keys: &[Key::Esc],                  action: TableKey::ClearSelection, help: "clear selection" },
+    Binding { keys: &[Key::Enter],                action: TableKey::Enter,          help: "fold group, or edit cell in editable tables" },
+    Binding { keys: &[Key::Char('i')],            action: TableKey::Edit,           help: "edit cell" },
+    Binding { keys: &[Key::Char('h'), Key::Left], action: TableKey::ColumnLeft,     help: "previous column, editable tables only" },
+    Binding { keys: &[Key::Char('l'), Key::Right],action: TableKey::ColumnRight,    help: "next column, editable tables only" },
+    Binding { keys: &[Key::Char('u')],            action: TableKey::Undo,           help: "undo the latest edit" },
+];
+
+#[derive(Clone, Copy, Debug)]
+enum EditKey {
+    Commit,
+    Cancel,
+    Erase,
+    Clear,
+    Type,
+}
+
+const EDIT_KEYS: &[Binding<EditKey>] = &[
+    Binding { keys: &[Key::Enter],     action: EditKey::Commit, help: "check and save the cell" },
+    Binding { keys: &[Key::Esc],       action: EditKey::Cancel, help: "leave the cell unchanged" },
+    Binding { keys: &[Key::Backspace], action: EditKey::Erase,  help: "delete last character" },
+    Binding { keys: &[Key::Ctrl('w')], action: EditKey::Clear,  help: "clear the cell" },
+    Binding { keys: &[Key::Text],      action: EditKey::Type,   help: "type the new value" },
+];
+
+/// one line of the table body
+#[derive(Clone, Debug)]
+enum Line {
+    Row(usize),
+    /// group header, `key` holds the values of all group columns down to this level
+    Group { key: Vec<String>, count: usize, cells: Vec<String> },
+}
+
+impl Line {
+    fn same(&self, other: &Line) -> bool {
+        match (self, other) {
+            (Line::Row(a), Line::Row(b))                             => a == b,
+            (Line::Group { key: a, .. }, Line::Group { key: b, .. }) => a == b,
+            _                                                        => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Aggregate {
+    Count,
+    Sum(usize),
+    Avg(usize),
+    Min(usize),
+    Max(usize),
+    /// volume weighted average of price
+    Vwap { price: usize, volume: usize },
+}
+
+impl Aggregate {
+    /// the `:agg` arguments after the header
+    fn spec(&self, table: &ITable) -> String {
+        match *self {
+            Aggregate::Count               => "count".into(),
+            Aggregate::Sum(_)              => "sum".into(),
+            Aggregate::Avg(_)              => "avg".into(),
+            Aggregate::Min(_)              => "min".into(),
+            Aggregate::Max(_)              => "max".into(),
+            Aggregate::Vwap { volume, .. } => format!("vwap {}", table.content[volume].header),
+        }
+    }
+
+    fn compute(&self, table: &ITable, rows: &[usize]) -> String {
+        let mut totals = Totals::default();
+        for cells in rows.iter().filter_map(|&id| table.rows[id].as_ref()) {
+            self.add(&mut totals, cells);
+        }
+        self.value(&totals)
+    }
+
+    fn add(&self, totals: &mut Totals, cells: &[String]) {
+        totals.rows += 1;
+        match *self {
+            Aggregate::Count => {  },
+            Aggregate::Sum(col) | Aggregate::Avg(col) | Aggregate::Min(col) | Aggregate::Max(col) => {
+                if let Some(v) = number(&cells[col]) {
+                    totals.count += 1;
+                    totals.sum += v;
+                    totals.min = Some(totals.min.map_or(v, |m| m.min(v)));
+                    totals.max = Some(totals.max.map_or(v, |m| m.max(v)));
+                }
+            },
+            Aggregate::Vwap { price, volume } => {
+                if let (Some(p), Some(v)) = (number(&cells[price]), number(&cells[volume])) {
+                    totals.count += 1;
+                    totals.sum += p * v;
+                    totals.volume += v;
+                }
+            },
+        }
+    }
+
+    /// take back what `add` counted for the same cells
+    fn remove(&self, totals: &mut Totals, cells: &[String]) {
+        totals.rows -= 1;
+        match *self {
+            Aggregate::Count => {  },
+            Aggregate::Sum(col) | Aggregate::Avg(col) | Aggregate::Min(col) | Aggregate::Max(col) => {
+                if let Some(v) = number(&cells[col]) {
+                    totals.count -= 1;
+                    totals.sum -= v;
+                    if totals.min == Some(v) || totals.max == Some(v) { totals.stale = true; }
+                }
+            },
+            Aggregate::Vwap { price, volume } => {
+                if let (Some(p), Some(v)) = (number(&cells[price]), number(&cells[volume])) {
+                    totals.count -= 1;
+                    totals.sum -= p * v;
+                    totals.volume -= v;
+                }
+            },
+        }
+        if totals.count == 0 {
+            *totals = Totals { rows: totals.rows, ..Totals::default() };
+        }
+    }
+
+    fn value(&self, totals: &Totals) -> String {
+        match *self {
+            Aggregate::Count               => totals.rows.to_string(),
+            Aggregate::Sum(_)              => if totals.count > 0 { totals.sum.to_string() } else { "0".into() },
+            Aggregate::Avg(_)              => if totals.count > 0 { format!("{:.2}", totals.sum / totals.count as f64) } else { "".into() },
+            Aggregate::Min(_)              => totals.min.map(|v| v.to_string()).unwrap_or_default(),
+            Aggregate::Max(_)              => totals.max.map(|v| v.to_string()).unwrap_or_default(),
+            Aggregate::Vwap { .. }         => if totals.volume > 0.0 { format!("{:.2}", totals.sum / totals.volume) } else { "".into() },
+        }
+    }
+}
+
+/// running state of one aggregate, rows can be added and taken out again
+#[derive(Clone, Debug, Default)]
+struct Totals {
+    rows   : usize,
+    count  : usize, // rows with numbers in the aggregated columns
+    sum    : f64, // price times volume for vwap
+    volume : f64,
+    min    : Option<f64>,
+    max    : Option<f64>,
+    stale  : bool, // a row holding min or max was taken out, count every row again
+}
+
+/// a cell aggregates take part in, NaN and infinity would spoil every total they touch
+fn number(cell: &str) -> Option<f64> {
+    cell.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+/// what a table remembers between sessions, columns are saved by header.
+#[derive(Default, Serialize, Deserialize)]
+struct ITableState {
+    idx_page   : usize,
+    idx_select : usize,
+    #[serde(default)]
+    group_by   : Vec<String>,
+    /// (header, aggregate as `:agg` takes it), None in sessions from before they were saved
+    #[serde(default)]
+    aggregates : Option<Vec<(String, String)>>,
+}
+
+pub fn default_highlight(_: &str, is_select: bool) -> Style {
+    if is_select {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default().fg(Color::White)
+    }
+}
+
+/// set the system clipboard through the terminal, works over ssh in most terminals
+fn osc52_copy(text: &str) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", base64::encode(text))?;
+    stdout.flush()
+}
+
+/// a cell parsed once before sorting, numbers order before text
+#[derive(PartialEq, PartialOrd)]
+enum SortKey<'a> {
+    Number(f64),
+    Text(&'a str),
+}
+
+impl<'a> SortKey<'a> {
+    fn new(cell: &'a str) -> Self {
+        cell.parse().map(SortKey::Number).unwrap_or(SortKey::Text(cell))
+    }
+}
+
+fn sort_order(a: &SortKey, b: &SortKey, desc: bool) -> Ordering {
+    let ord = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+    if desc { ord.reverse() } else { ord }
+}
+
+/// numbers compare as numbers, everything else as text
+fn compare_cell(a: &str, b: &str) -> Ordering {
+    SortKey::new(a).partial_cmp(&SortKey::new(b)).unwrap_or(Ordering::Equal)
+}
+
+impl ITable {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// `column` fills the existing rows in insertion order, the first column creates the rows
+    pub fn add_column<F, S1, S2>(&mut self, header: S1, hstyle: Style, width: usize, column: Vec<S2>, f: F)
+        where F : Fn(&str, bool) -> Style + 'static,
+              S1 : ToString,
+              S2 : ToString,
+    {
+        let first = self.content.is_empty();
+        self.content.push(Column {
+            header    : header.to_string(),
+            hstyle,
+            width,
+            style     : Box::new(f),
+            sparkline : false,
+            kind      : None,
+        });
+
+        if first {
+            for cell in column {
+                self.insert(vec![cell.to_string()]);
+            }
+        } else {
+            let mut column = column.into_iter();
+            for &id in self.order.iter() {
+                if let Some(row) = self.rows[id].as_mut() {
+                    row.push(column.next().map(|s| s.to_string()).unwrap_or_default());
+                }
+            }
+        }
+        self.invalidate();
+    }
+
+    /// append a row, returns its id
+    pub fn add_row<S: ToString>(&mut self, row: Vec<S>) -> usize {
+        assert!(row.len() == self.columns());
+        self.insert(row.into_iter().map(|s| s.to_string()).collect())
+    }
+
+    fn insert(&mut self, row: Vec<String>) -> usize {
+        let key = self.key.map(|col| row[col].clone());
+        let id = match self.free.pop() {
+            Some(id) => { self.rows[id] = Some(row); id },
+            None     => { self.rows.push(Some(row)); self.rows.len() - 1 },
+        };
+        if let Some(key) = key { self.index.insert(key, id); }
+        self.record_change(id, None);
+        self.order.push(id);
+        self.len += 1;
+
+        // appending to a plain view keeps it in order, skip the rebuild
+        if !self.dirty && self.sort.is_none() && self.group_by.is_empty() && self.aggregates.is_empty() {
+            let shown = self.row_matches(id);
+            self.matched.resize(self.rows.len(), false);
+            self.matched[id] = shown;
+            if shown {
+                self.ordered.push(id);
+                self.apply_restore();
+            }
+        } else {
+            self.mark(id);
+            self.dirty = true;
+        }
+        id
+    }
+
+    pub fn remove_col(&mut self, idx: usize) {
+        assert!(self.columns() > idx);
+        self.content.remove(idx);
+        for row in self.rows.iter_mut().flatten() {
+            row.remove(idx);
+        }
+        match self.sort {
+            Some((col, _)) if col == idx => self.sort = None,
+            Some((col, desc)) if col > idx => self.sort = Some((col - 1, desc)),
+            _ => {  }
+        }
+        if let Some((Some(col), _)) = self.filter {
+            if col == idx { self.filter = None; }
+        }
+        if let Some((Some(ref mut col), _)) = self.filter {
+            if *col > idx { *col -= 1; }
+        }
+        match self.key {
+            Some(col) if col == idx => { self.key = None; self.index.clear(); },
+            Some(col) if col > idx  => self.key = Some(col - 1),
+            _ => {  }
+        }
+        self.group_by = self.group_by.iter().filter(|&&c| c != idx).map(|&c| if c > idx { c - 1 } else { c }).collect();
+        self.aggregates.clear();
+        self.invalidate();
+    }
+
+    /// remove a row by id, the ids of other rows stay valid
+    pub fn remove_row(&mut self, id: usize) -> Result<(), String> {
+        if self.row(id).is_none() { return Err(format!("no row with id {}", id)); }
+        self.uncount(id);
+        let row = self.rows[id].take().unwrap_or_default();
+        if let Some(col) = self.key {
+            if self.index.get(&row[col]) == Some(&id) { self.index.remove(&row[col]); }
+        }
+        self.selected.remove(&id);
+        if self.visual == Some(id) { self.visual = None; }
+        self.flashes.remove(&id);
+        self.undo.retain(|change| change.row != id);
+        if self.editing.as_ref().map_or(false, |edit| edit.row == id) { self.editing = None; }
+        self.len -= 1;
+        self.dead.push(id);
+        self.dirty = true;
+        self.mark(id);
+
+        // compact once half of `order` is dead, this keeps removal amortised O(1)
+        if self.dead.len() > self.len {
+            let rows = &self.rows;
+            self.order.retain(|&id| rows[id].is_some());
+            self.free.append(&mut self.dead);
+            // ids get reused from here on, the cached order can not tell a new row from the removed one
+            self.invalidate();
+        }
+        Ok(())
+    }
+
+    pub fn remove_by_key(&mut self, key: &str) -> bool {
+        match self.key.and_then(|_| self.index.get(key).cloned()) {
+            Some(id) => self.remove_row(id).is_ok(),
+            None     => false,
+        }
+    }
+
+    /// how often the view filtered and sorted every row, a burst of updates should merge instead
+    pub fn rebuilds(&self) -> usize {
+        self.rebuilds
+    }
+
+    /// aggregates over all shown rows, one cell per column, none without aggregates
+    pub fn footer(&mut self) -> &[String] {
+        self.ensure_view();
+        &self.footer
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn columns(&self) -> usize {
+        self.content.len()
+    }
+
+    pub fn column_index(&self, header: &str) -> Option<usize> {
+        self.content.iter().position(|c| c.header == header)
+    }
+
+    pub fn row(&self, id: usize) -> Option<&[String]> {
+        self.rows.get(id).and_then(|row| row.as_ref()).map(|row| row.as_slice())
+    }
+
+    fn cell(&self, id: usize, col: usize) -> &str {
+        self.rows[id].as_ref().map(|row| row[col].as_str()).unwrap_or("")
+    }
+
+    pub fn set_cell<S: ToString>(&mut self, id: usize, col: usize, value: S) {
+        let value = value.to_string();
+        if self.key == Some(col) {
+            let old = self.cell(id, col).to_string();
+            if self.index.get(&old) == Some(&id) { self.index.remove(&old); }
+            self.index.insert(value.clone(), id);
+        }
+        let old = match self.changes { Some(_) => self.rows[id].clone(), None => None };
+        self.uncount(id);
+        if let Some(row) = self.rows[id].as_mut() {
+            row[col] = value;
+        }
+        if old.is_some() { self.record_change(id, old); }
+        self.touch(id);
+    }
+
+    fn record_change(&mut self, id: usize, old: Option<Vec<String>>) {
+        if let (Some(changes), Some(new)) = (self.changes.as_mut(), self.rows[id].as_ref()) {
+            changes.push(RowChange { row: id, old, new: new.clone() });
+        }
+    }
+
+    /// start keeping added and changed rows for `take_changes`
+    pub fn track_changes(&mut self) {
+        if self.changes.is_none() { self.changes = Some(Vec::new()); }
+    }
+
+    /// rows added or changed since the last call, oldest first
+    pub fn take_changes(&mut self) -> Vec<RowChange> {
+        self.changes.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// draw the row in alert colors for a while
+    pub fn flash(&mut self, id: usize, duration: Duration) {
+        self.flashes.insert(id, Instant::now() + duration);
+    }
+
+    /// a row changed in place, it only moves under a sort or filter and is counted again under aggregates
+    fn touch(&mut self, id: usize) {
+        if self.sort.is_some() || self.filter.is_some() || !self.aggregates.is_empty() {
+            self.mark(id);
+            self.dirty = true;
+        }
+        if !self.group_by.is_empty() {
+            self.dirty = true;
+        }
+    }
+
+    /// take the row out of `totals` before its cells change or it goes, the refresh counts it again
+    fn uncount(&mut self, id: usize) {
+        if !self.cached || !self.counted.get(id).cloned().unwrap_or(false) { return; }
+        if let Some(cells) = self.rows[id].as_ref() {
+            for ((_, aggregate), totals) in self.aggregates.iter().zip(self.totals.iter_mut()) {
+                aggregate.remove(totals, cells);
+            }
+        }
+        self.counted[id] = false;
+    }
+
+    fn count(&mut self, id: usize) {
+        if let Some(cells) = self.rows[id].as_ref() {
+            for ((_, aggregate), totals) in self.aggregates.iter().zip(self.totals.iter_mut()) {
+                aggregate.add(totals, cells);
+            }
+            self.counted[id] = true;
+        }
+    }
+
+    /// remember a row for the next refresh, past one change per row a rebuild is cheaper
+    fn mark(&mut self, id: usize) {
+        if !self.cached { return; }
+        self.touched.push(id);
+        if self.touched.len() > self.rows.len() { self.invalidate(); }
+    }
+
+    /// filter and sort every row on the next refresh
+    fn invalidate(&mut self) {
+        self.cached = false;
+        self.touched.clear();
+        self.dirty = true;
+    }
+
+    pub fn sort_by(&mut self, col: usize, desc: bool) {
+        assert!(self.columns() > col);
+        self.sort = Some((col, desc));
+        self.invalidate();
+    }
+
+    pub fn clear_sort(&mut self) {
+        self.sort = None;
+        self.invalidate();
+    }
+
+    /// keep rows containing `text` (case insensitive) in `col`, or in any column if `col` is None.
+    pub fn set_filter(&mut self, col: Option<usize>, text: &str) {
+        self.filter = if text.is_empty() { None } else { Some((col, text.to_lowercase())) };
+        self.invalidate();
+    }
+
+    pub fn set_column_width(&mut self, col: usize, width: usize) {
+        assert!(self.columns() > col);
+        self.content[col].width = width;
+    }
+
+    pub fn set_sparkline(&mut self, col: usize, sparkline: bool) {
+        assert!(self.columns() > col);
+        self.content[col].sparkline = sparkline;
+    }
+
+    /// allow editing cells of `col` in place, input must fit `kind`
+    pub fn set_editable(&mut self, col: usize, kind: CellType) {
+        assert!(self.columns() > col);
+        self.content[col].kind = Some(kind);
+        if self.content[self.idx_col].kind.is_none() { self.idx_col = col; }
+    }
+
+    /// called before an edit or undo is applied, with the row as it is now. An error refuses it.
+    pub fn on_change<F>(&mut self, f: F)
+        where F: FnMut(&CellChange, &[String]) -> Result<(), String> + 'static
+    {
+        self.on_change = Some(Box::new(f));
+    }
+
+    /// how `cancel_selected` cancels a row: the column and its new value, None if the row can not be cancelled
+    pub fn on_cancel<F>(&mut self, f: F)
+        where F: Fn(&[String]) -> Option<(usize, String)> + 'static
+    {
+        self.on_cancel = Some(Box::new(f));
+    }
+
+    fn editable(&self) -> bool {
+        self.content.iter().any(|c| c.kind.is_some())
+    }
+
+    /// move the cell cursor to the next editable column
+    fn move_column(&mut self, forward: bool) {
+        let cols: Vec<usize> = (0..self.columns()).filter(|&c| self.content[c].kind.is_some()).collect();
+        if cols.is_empty() { return; }
+        let idx = cols.iter().position(|&c| c == self.idx_col).unwrap_or(0);
+        let idx = if forward { (idx + 1) % cols.len() } else { (idx + cols.len() - 1) % cols.len() };
+        self.idx_col = cols[idx];
+    }
+
+    pub fn start_edit(&mut self) -> Result<(), String> {
+        self.ensure_view();
+        let row = self.row_at(self.idx_select).ok_or("no row to edit here")?;
+        let col = self.idx_col;
+        if self.content.get(col).and_then(|c| c.kind.as_ref()).is_none() {
+            return Err("no editable column in this table".into());
+        }
+        self.editing = Some(Edit { row, col, text: self.cell(row, col).to_string(), error: None });
+        Ok(())
+    }
+
+    fn commit_edit(&mut self) {
+        let mut edit = match self.editing.take() {
+            Some(edit) => edit,
+            None       => return,
+        };
+        let kind = self.content[edit.col].kind.clone().unwrap_or(CellType::Text);
+        match kind.validate(&edit.text).and_then(|value| self.apply_change(edit.row, edit.col, value)) {
+            Ok(Some(change)) => {
+                self.undo.push(change);
+                if self.undo.len() > MAX_UNDO { self.undo.remove(0); }
+            },
+            Ok(None)         => {  },
+            Err(e)           => {
+                edit.error = Some(e);
+                self.editing = Some(edit);
+            },
+        }
+    }
+
+    /// set a cell through the change callback, None if the value did not change
+    fn apply_change(&mut self, row: usize, col: usize, value: String) -> Result<Option<CellChange>, String> {
+        let cells = self.rows[row].as_deref().ok_or("row was removed")?;
+        if cells[col] == value { return Ok(None); }
+        let change = CellChange {
+            row,
+            col,
+            header : self.content[col].header.clone(),
+            old    : cells[col].clone(),
+            new    : value,
+        };
+        if let Some(f) = self.on_change.as_mut() {
+            f(&change, cells)?;
+        }
+        self.set_cell(row, col, change.new.clone());
+        Ok(Some(change))
+    }
+
+    /// revert the latest edit, it goes through the change callback like any other
+    pub fn undo(&mut self) -> Result<CellChange, String> {
+        let change = self.undo.pop().ok_or("nothing to undo")?;
+        match self.apply_change(change.row, change.col, change.old.clone()) {
+            Ok(_)  => Ok(change),
+            Err(e) => { self.undo.push(change); Err(e) },
+        }
+    }
+
+    /// nest rows under one group line per distinct value of each column, outermost first
+    pub fn set_group_by(&mut self, cols: Vec<usize>) {
+        assert!(cols.iter().all(|c| *c < self.columns()));
+        self.group_by = cols;
+        self.collapsed.clear();
+        self.dirty = true;
+    }
+
+    pub fn set_aggregate(&mut self, col: usize, aggregate: Aggregate) {
+        assert!(self.columns() > col);
+        self.aggregates.retain(|(c, _)| *c != col);
+        self.aggregates.push((col, aggregate));
+        self.invalidate();
+    }
+
+    pub fn clear_aggregates(&mut self) {
+        self.aggregates.clear();
+        self.invalidate();
+    }
+
+    fn toggle_group(&mut self) {
+        self.ensure_view();
+        if let Some(Line::Group { key, .. }) = self.line(self.idx_select).map(Cow::into_owned) {
+            if !self.collapsed.remove(&key) { self.collapsed.insert(key); }
+            self.dirty = true;
+        }
+    }
+
+    fn row_at(&self, idx: usize) -> Option<usize> {
+        match self.line(idx).as_deref() {
+            Some(Line::Row(row)) => Some(*row),
+            _                    => None,
+        }
+    }
+
+    /// line `idx` of the view, without groups the sorted rows are the lines
+    fn line(&self, idx: usize) -> Option<Cow<Line>> {
+        if self.group_by.is_empty() {
+            self.ordered.get(idx).map(|&id| Cow::Owned(Line::Row(id)))
+        } else {
+            self.view.get(idx).map(Cow::Borrowed)
+        }
+    }
+
+    fn lines(&self) -> usize {
+        if self.group_by.is_empty() { self.ordered.len() } else { self.view.len() }
+    }
+
+    fn find_line(&self, line: &Line) -> Option<usize> {
+        match line {
+            Line::Row(row) if self.group_by.is_empty() => self.ordered.iter().position(|id| id == row),
+            _                                          => self.view.iter().position(|l| l.same(line)),
+        }
+    }
+
+    /// index `col` for `find_row`, `upsert_row` and `remove_by_key`
+    pub fn set_key(&mut self, col: usize) {
+        assert!(self.columns() > col);
+        self.key = Some(col);
+        self.index.clear();
+        for &id in self.order.iter() {
+            if let Some(row) = self.rows[id].as_ref() {
+                self.index.insert(row[col].clone(), id);
+            }
+        }
+    }
+
+    /// id of a row with `value` in `col`, a lookup on the key column and a scan otherwise
+    pub fn find_row(&self, col: usize, value: &str) -> Option<usize> {
+        if self.key == Some(col) {
+            return self.index.get(value).cloned();
+        }
+        self.order.iter().cloned().find(|&id| self.rows[id].as_ref().map_or(false, |row| row[col] == value))
+    }
+
+    /// replace the row whose `key_col` equals the new one, or append it
+    pub fn upsert_row<S: ToString>(&mut self, key_col: usize, row: Vec<S>) -> usize {
+        assert!(row.len() == self.columns());
+        if self.key != Some(key_col) { self.set_key(key_col); }
+        let row: Vec<String> = row.into_iter().map(|s| s.to_string()).collect();
+        match self.index.get(&row[key_col]).cloned() {
+            Some(id) => {
+                self.uncount(id);
+                let old = self.rows[id].replace(row);
+                if self.changes.is_some() { self.record_change(id, old); }
+                self.touch(id);
+                id
+            },
+            None => self.insert(row),
+        }
+    }
+
+    /// rebuild the view if anything changed since the last one
+    fn ensure_view(&mut self) {
+        if self.dirty { self.refresh_view(); }
+    }
+
+    /// first and last line of the running visual range, the anchor row may have moved with a sort
+    /// or gone with the filter, then the range is the current line only
+    fn visual_range(&self) -> Option<(usize, usize)> {
+        let anchor = self.visual?;
+        let idx = self.find_line(&Line::Row(anchor)).unwrap_or(self.idx_select);
+        Some((idx.min(self.idx_select), idx.max(self.idx_select)))
+    }
+
+    fn is_selected(&self, idx: usize, visual: Option<(usize, usize)>) -> bool {
+        let in_visual = match visual {
+            Some((lo, hi)) => idx >= lo && idx <= hi,
+            None           => false,
+        };
+        match self.line(idx).as_deref() {
+            Some(Line::Row(row)) => in_visual || self.selected.contains(row),
+            _                    => false,
+        }
+    }
+
+    /// picked rows, also those hidden by the filter, plus the running visual range
+    fn selected_count(&self) -> usize {
+        let visual = match self.visual_range() {
+            Some((lo, hi)) => (lo..=hi)
+                .filter_map(|idx| self.row_at(idx))
+                .filter(|row| !self.selected.contains(row))
+                .count(),
+            None           => 0,
+        };
+        self.selected.len() + visual
+    }
+
+    /// rows bulk actions apply to, in view order: the shown selected rows, or the current row if none
+    pub fn selection(&mut self) -> Vec<usize> {
+        self.ensure_view();
+        let visual = self.visual_range();
+        let mut rows: Vec<usize> = (0..self.lines())
+            .filter(|&idx| self.is_selected(idx, visual))
+            .filter_map(|idx| self.row_at(idx))
+            .collect();
+        if rows.is_empty() {
+            rows.extend(self.row_at(self.idx_select));
+        }
+        rows
+    }
+
+    pub fn toggle_visual(&mut self) {
+        self.ensure_view();
+        match self.visual_range() {
+            Some((lo, hi)) => {
+                self.visual = None;
+                for idx in lo..=hi.min(self.lines().saturating_sub(1)) {
+                    if let Some(row) = self.row_at(idx) { self.selected.insert(row); }
+                }
+            },
+            // a range starts on a row, not on a group line
+            None => self.visual = self.row_at(self.idx_select),
+        }
+    }
+
+    pub fn toggle_current(&mut self) {
+        self.ensure_view();
+        if let Some(row) = self.row_at(self.idx_select) {
+            if !self.selected.remove(&row) { self.selected.insert(row); }
+            self.select_down();
+        }
+    }
+
+    pub fn select_all(&mut self) {
+        self.ensure_view();
+        self.visual = None;
+        let rows: Vec<usize> = (0..self.lines()).filter_map(|idx| self.row_at(idx)).collect();
+        self.selected.extend(rows);
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.visual = None;
+        self.selected.clear();
+    }
+
+    /// cancel the rows among the selection `on_cancel` allows, returns how many
+    pub fn cancel_selected(&mut self) -> Result<usize, String> {
+        if self.on_cancel.is_none() { return Err("rows of this table can not be cancelled".into()); }
+        let mut count = 0;
+        for row in self.selection() {
+            let cancel = match (self.on_cancel.as_ref(), self.rows[row].as_deref()) {
+                (Some(f), Some(cells)) => f(cells),
+                _                      => None,
+            };
+            if let Some((col, value)) = cancel {
+                self.set_cell(row, col, value);
+                count += 1;
+            }
+        }
+        self.clear_selection();
+        Ok(count)
+    }
+
+    pub fn remove_selected(&mut self) -> usize {
+        let count = self.selection().into_iter().filter(|&row| self.remove_row(row).is_ok()).count();
+        self.clear_selection();
+        count
+    }
+
+    /// selected rows with a header line, cells joined by `sep`
+    pub fn selected_text(&mut self, sep: char) -> String {
+        let escape = |cell: &str| if sep == ',' && (cell.contains(',') || cell.contains('"')) {
+            format!("\"{}\"", cell.replace('"', "\"\""))
+        } else {
+            cell.to_string()
+        };
+        let mut text = String::new();
+        let line: Vec<String> = self.content.iter().map(|c| escape(&c.header)).collect();
+        text.push_str(&line.join(&sep.to_string()));
+        text.push('\n');
+        for row in self.selection() {
+            let line: Vec<String> = (0..self.columns()).map(|col| escape(self.cell(row, col))).collect();
+            text.push_str(&line.join(&sep.to_string()));
+            text.push('\n');
+        }
+        text
+    }
+
+    fn row_matches(&self, row: usize) -> bool {
+        match self.filter {
+            None                     => true,
+            Some((Some(col), ref t)) => self.cell(row, col).to_lowercase().contains(t.as_str()),
+            Some((None, ref t))      =>
+                (0..self.columns()).any(|col| self.cell(row, col).to_lowercase().contains(t.as_str())),
+        }
+    }
+
+    fn refresh_view(&mut self) {
+        self.dirty = false;
+        let current = self.line(self.idx_select).map(Cow::into_owned);
+
+        // a burst of updates on a big table is merged in, not sorted again with everything else
+        if self.cached && self.touched.len() <= self.ordered.len() / 4 {
+            self.merge_touched();
+        } else {
+            self.rebuild_ordered();
+        }
+        self.touched.clear();
+        self.cached = true;
+        self.recount_stale();
+        self.footer = self.footer_cells();
+
+        let mut view = Vec::new();
+        if !self.group_by.is_empty() {
+            self.group_lines(&self.ordered, &mut vec![], &mut view);
+        }
+        self.view = view;
+
+        // keep the cursor on the same line when rows move around it
+        if let Some(idx) = current.and_then(|current| self.find_line(&current)) {
+            self.idx_select = idx;
+        }
+        self.apply_restore();
+        if self.idx_select >= self.lines() {
+            self.idx_select = self.lines().saturating_sub(1);
+        }
+        if self.idx_page >= self.lines() {
+            self.idx_page = self.lines().saturating_sub(1);
+        }
+        self.wrap_page();
+    }
+
+    /// take the restored positions once the view reaches them
+    fn apply_restore(&mut self) {
+        if let Some((select, page)) = self.restore {
+            if select < self.lines() {
+                self.idx_select = select;
+                self.idx_page = page.min(select);
+                self.restore = None;
+            }
+        }
+    }
+
+    /// filter and sort every row
+    fn rebuild_ordered(&mut self) {
+        let matched: Vec<bool> = (0..self.rows.len()).map(|id| self.rows[id].is_some() && self.row_matches(id)).collect();
+        let mut rows: Vec<usize> = self.order.iter().cloned().filter(|&id| matched[id]).collect();
+        if let Some((col, desc)) = self.sort {
+            // parse every cell once, and stable, so equal cells keep insertion order
+            let mut keyed: Vec<(SortKey, usize)> = rows.iter().map(|&id| (SortKey::new(self.cell(id, col)), id)).collect();
+            keyed.sort_by(|a, b| sort_order(&a.0, &b.0, desc));
+            rows = keyed.into_iter().map(|(_, id)| id).collect();
+        }
+        self.matched = matched;
+        self.ordered = rows;
+        self.rebuilds += 1;
+
+        self.totals = vec![Totals::default(); self.aggregates.len()];
+        self.counted = vec![false; self.rows.len()];
+        if !self.aggregates.is_empty() {
+            for idx in 0..self.ordered.len() {
+                let id = self.ordered[idx];
+                self.count(id);
+            }
+        }
+    }
+
+    /// count every shown row again for the aggregates whose min or max went with a row
+    fn recount_stale(&mut self) {
+        for idx in 0..self.totals.len() {
+            if !self.totals[idx].stale { continue; }
+            let aggregate = self.aggregates[idx].1;
+            let mut totals = Totals::default();
+            for cells in self.ordered.iter().filter_map(|&id| self.rows[id].as_ref()) {
+                aggregate.add(&mut totals, cells);
+            }
+            self.totals[idx] = totals;
+        }
+    }
+
+    /// filter and count the touched rows again and merge them into `ordered`, linear instead of a
+    /// full sort. A changed row goes after rows with an equal cell, as if it was added last.
+    fn merge_touched(&mut self) {
+        self.matched.resize(self.rows.len(), false);
+        self.counted.resize(self.rows.len(), false);
+        if self.touched.is_empty() { return; }
+        let mut touched = std::mem::take(&mut self.touched);
+        touched.sort_unstable();
+        touched.dedup();
+        let mut moved = false; // rows came or went
+        for &id in touched.iter() {
+            let shown = self.rows[id].is_some() && self.row_matches(id);
+            moved |= shown != self.matched[id];
+            self.matched[id] = shown;
+            if shown && !self.counted[id] { self.count(id); }
+        }
+
+        let (col, desc) = match self.sort {
+            Some(sort) => sort,
+            // insertion order, rows changed in place stay where they are
+            None if !moved => return,
+            None           => {
+                let matched = &self.matched;
+                self.ordered = self.order.iter().cloned().filter(|&id| matched[id]).collect();
+                return;
+            },
+        };
+        let mut changed: Vec<(SortKey, usize)> = touched.iter().cloned()
+            .filter(|&id| self.matched[id])
+            .map(|id| (SortKey::new(self.cell(id, col)), id))
+            .collect();
+        changed.sort_by(|a, b| sort_order(&a.0, &b.0, desc));
+        let touched: HashSet<usize> = touched.into_iter().collect();
+
+        let mut merged = Vec::with_capacity(self.ordered.len() + changed.len());
+        let mut changed = changed.into_iter().peekable();
+        for &id in self.ordered.iter().filter(|id| !touched.contains(*id)) {
+            let key = SortKey::new(self.cell(id, col));
+            while changed.peek().map_or(false, |(k, _)| sort_order(k, &key, desc) == Ordering::Less) {
+                merged.extend(changed.next().map(|(_, id)| id));
+            }
+            merged.push(id);
+        }
+        merged.extend(changed.map(|(_, id)| id));
+        self.ordered = merged;
+    }
+
+    /// sorted rows to lines, groups ordered by key and rows keep their order inside a group
+    fn group_lines(&self, rows: &[usize], key: &mut Vec<String>, out: &mut Vec<Line>) {
+        let depth = key.len();
+        if depth == self.group_by.len() {
+            out.extend(rows.iter().map(|&id| Line::Row(id)));
+            return;
+        }
+
+        let col = self.group_by[depth];
+        let mut groups: Vec<(String, Vec<usize>)> = vec![];
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        for &row in rows {
+            let cell = self.cell(row, col);
+            match index.get(cell) {
+                Some(idx) => groups[*idx].1.push(row),
+                None      => { index.insert(cell, groups.len()); groups.push((cell.to_string(), vec![row])); },
+            }
+        }
+        groups.sort_by(|a, b| compare_cell(&a.0, &b.0));
+
+        for (value, rows) in groups {
+            key.push(value);
+            out.push(Line::Group { key: key.clone(), count: rows.len(), cells: self.aggregate_cells(&rows) });
+            if !self.collapsed.contains(key) {
+                self.group_lines(&rows, key, out);
+            }
+            key.pop();
+        }
+    }
+
+    fn wrap_page(&mut self) {
+        if self.idx_select < self.idx_page {
+            self.idx_page = self.idx_select;
+        }
+
+        if self.idx_select >= self.idx_page + self.get_content_height() {
+            self.idx_page = self.idx_select - self.get_content_height();
+            if self.idx_page + 1 < self.lines() { self.idx_page += 1; }
+        }
+    }
+
+    fn wrap_select(&mut self) {
+        if self.idx_select < self.idx_page {
+            self.idx_select = self.idx_page;
+        }
+
+        let maxreach = self.idx_page + self.get_content_height();
+        if maxreach > 0 && self.idx_select > maxreach {
+            self.idx_select = maxreach - 1;
+        }
+    }
+
+    fn footer_height(&self) -> u16 {
+        if self.aggregates.is_empty() { 0 } else { 1 }
+    }
+
+    fn get_content_height(&self) -> usize {
+        if self.window.height > 3 + self.footer_height() {
+            (self.window.height - 3 - self.footer_height()) as _
+        } else {
+            0
+        }
+    }
+
+    /// the running totals, one cell per column, empty where the column has no aggregate
+    fn footer_cells(&self) -> Vec<String> {
+        if self.aggregates.is_empty() { return vec![]; }
+        (0..self.columns())
+            .map(|col| match self.aggregates.iter().zip(self.totals.iter()).find(|((c, _), _)| *c == col) {
+                Some(((_, aggregate), totals)) => aggregate.value(totals),
+                None                           => String::new(),
+            })
+            .collect()
+    }
+
+    /// one cell per column, empty where the column has no aggregate
+    fn aggregate_cells(&self, rows: &[usize]) -> Vec<String> {
+        if self.aggregates.is_empty() { return vec![]; }
+        (0..self.columns())
+            .map(|col| match self.aggregates.iter().find(|(c, _)| *c == col) {
+                Some((_, aggregate)) => aggregate.compute(self, rows),
+                None                 => String::new(),
+            })
+            .collect()
+    }
+}
+
+fn parse_aggregate(table: &ITable, kind: &str, col: usize, args: &[&str]) -> Result<Aggregate, String> {
+    let column = |header: &str| table.column_index(header).ok_or_else(|| format!("no column {}", header));
+    Ok(match (kind, args) {
+        ("count", []) => Aggregate::Count,
+        ("sum", [])   => Aggregate::Sum(col),
+        ("avg", [])   => Aggregate::Avg(col),
+        ("min", [])   => Aggregate::Min(col),
+        ("max", [])   => Aggregate::Max(col),
+        ("vwap", [volume]) => Aggregate::Vwap { price: col, volume: column(*volume)? },
+        _ => return Err(format!("unknown aggregate {} {:?}, expect count, sum, avg, min, max or vwap <volume>", kind, args)),
+    })
+}
+
+impl Interactive for ITable {
+    fn select_up(&mut self) {
+        self.ensure_view();
+        self.restore = None;
+        if self.idx_select > 0 {
+            self.idx_select -= 1;
+            self.wrap_page();
+        }
+    }
+
+    fn select_down(&mut self) {
+        self.ensure_view();
+        self.restore = None;
+        if self.idx_select + 1 < self.lines() {
+            self.idx_select += 1;
+            self.wrap_page();
+        }
+    }
+
+    fn select_first(&mut self) {
+        self.ensure_view();
+        self.restore = None;
+        if self.idx_select != 0 {
+            self.idx_select = 0;
+            self.wrap_page();
+        }
+    }
+
+    fn select_last(&mut self) {
+        self.ensure_view();
+        self.restore = None;
+        if self.lines() > 0 && self.idx_select != self.lines() - 1 {
+            self.idx_select = self.lines() - 1;
+            self.wrap_page();
+        }
+    }
+
+    fn select_page_up(&mut self) {
+        self.ensure_view();
+        self.restore = None;
+        if self.idx_page != 0 {
+            let shift = self.get_content_height().min(self.idx_page);
+            self.idx_page -= shift;
+            self.wrap_select();
+        }
+    }
+
+    fn select_page_down(&mut self) {
+        self.ensure_view();
+        self.restore = None;
+        if self.get_content_height() > 0 && self.lines() > 0 && self.idx_page < self.lines() {
+            self.idx_page = (self.idx_page + self.get_content_height() - 1).min(self.lines()-1);
+            self.wrap_select();
+        }
+    }
+
+    fn select_on_key(&mut self, event: &Event) -> bool {
+        if self.editing.is_some() {
+            match keymap::lookup(EDIT_KEYS, event) {
+                Some(EditKey::Commit) => self.commit_edit(),
+                Some(EditKey::Cancel) => self.editing = None,
+                Some(key)             => if let Some(edit) = self.editing.as_mut() { edit.input(key, event) },
+                None                  => {  },
+            }
+            return true;
+        }
+
+        self.ensure_view();
+        let on_group = match self.line(self.idx_select).as_deref() {
+            Some(Line::Group { .. }) => true,
+            _                        => false,
+        };
+        match keymap::lookup(TABLE_KEYS, event) {
+            Some(TableKey::Visual)         => self.toggle_visual(),
+            Some(TableKey::Toggle)         => self.toggle_current(),
+            Some(TableKey::SelectAll)      => self.select_all(),
+            Some(TableKey::ClearSelection) => self.clear_selection(),
+            Some(TableKey::Enter) if on_group || !self.editable() => self.toggle_group(),
+            Some(TableKey::Enter) | Some(TableKey::Edit) => {
+                if let Err(e) = self.start_edit() { warn!("{}", e); }
+            },
+            Some(TableKey::ColumnLeft)  if self.editable() => self.move_column(false),
+            Some(TableKey::ColumnRight) if self.editable() => self.move_column(true),
+            Some(TableKey::Undo)           => match self.undo() {
+                Ok(change) => info!(target: NOTIFY, "undo {}: {} -> {}", change.header, change.new, change.old),
+                Err(e)     => warn!("undo: {}", e),
+            },
+            _                              => return false,
+        }
+        true
+    }
+
+    fn keys(&self) -> Vec<Section> {
+        vec![Section::new("table", TABLE_KEYS), Section::new("editing a cell", EDIT_KEYS)]
+    }
+
+    fn capturing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    fn summary(&self) -> Option<String> {
+        match self.filter {
+            Some(_) => Some(format!("{}/{} rows", self.ordered.len(), self.len)),
+            None    => Some(format!("{} rows", self.len)),
+        }
+    }
+
+    fn click(&mut self, _xpos: u16, ypos: u16, ) {
+        self.ensure_view();
+        if ypos > 1 && ypos < self.window.height {
+            let idx = self.idx_page + ypos as usize - 2;
+            self.restore = None;
+            if idx == self.idx_select {
+                self.toggle_group();
+            } else if idx < self.lines() {
+                self.idx_select = idx;
+            }
+        }
+    }
+
+    fn on_command(&mut self, name: &str, args: &[&str]) -> Result<(), String> {
+        match (name, args) {
+            ("cancel", []) => {
+                let count = self.cancel_selected()?;
+                info!(target: NOTIFY, "cancel {} orders", count);
+            },
+            ("remove", []) => {
+                let count = self.remove_selected();
+                info!(target: NOTIFY, "removed {} rows", count);
+            },
+            ("export", [path]) => {
+                let rows = self.selection().len();
+                std::fs::write(path, self.selected_text(',')).map_err(|e| format!("{}: {}", path, e))?;
+                info!(target: NOTIFY, "exported {} rows to {}", rows, path);
+            },
+            ("copy", []) => {
+                let rows = self.selection().len();
+                osc52_copy(&self.selected_text('\t')).map_err(|e| e.to_string())?;
+                info!(target: NOTIFY, "copied {} rows", rows);
+            },
+            ("group", headers) if !headers.is_empty() => {
+                let cols = headers.iter()
+                    .map(|h| self.column_index(h).ok_or_else(|| format!("no column {}", h)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.set_group_by(cols);
+            },
+            ("nogroup", []) => self.set_group_by(vec![]),
+            ("agg", [header, kind, rest @ ..]) => {
+                let col = self.column_index(header).ok_or_else(|| format!("no column {}", header))?;
+                let aggregate = parse_aggregate(self, kind, col, rest)?;
+                self.set_aggregate(col, aggregate);
+            },
+            ("noagg", []) => self.clear_aggregates(),
+            ("undo", []) => {
+                let change = self.undo()?;
+                info!(target: NOTIFY, "undo {}: {} -> {}", change.header, change.new, change.old);
+            },
+            _ => return Err(format!("bad arguments for {}: {:?}", name, args)),
+        }
+        Ok(())
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        // still waiting for rows, keep what was restored
+        let (idx_select, idx_page) = self.restore.unwrap_or((self.idx_select, self.idx_page));
+        let state = ITableState {
+            idx_page,
+            idx_select,
+            group_by   : self.group_by.iter().map(|c| self.content[*c].header.clone()).collect(),
+            aggregates : Some(self.aggregates.iter().map(|(c, a)| (self.content[*c].header.clone(), a.spec(self))).collect()),
+        };
+        serde_json::to_value(state).ok()
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) {
+        let state: ITableState = match serde_json::from_value(state) {
+            Ok(state) => state,
+            Err(e)    => { warn!("ignore bad table state: {}", e); return; },
+        };
+
+        self.invalidate();
+        self.group_by = state.group_by.iter().filter_map(|h| self.column_index(h)).collect();
+        if let Some(aggregates) = state.aggregates {
+            self.aggregates.clear();
+            for (header, spec) in aggregates {
+                let args: Vec<&str> = spec.split_whitespace().collect();
+                let aggregate = match (self.column_index(&header), args.split_first()) {
+                    (Some(col), Some((kind, rest))) => parse_aggregate(self, kind, col, rest).map(|a| (col, a)),
+                    _                               => Err(format!("no column {} or no aggregate", header)),
+                };
+                match aggregate {
+                    Ok(aggregate) => self.aggregates.push(aggregate),
+                    Err(e)        => warn!("ignore saved aggregate {} {}: {}", header, spec, e),
+                }
+            }
+        }
+        // rows usually come in after the session is restored, the first view long enough takes the positions
+        self.restore = Some((state.idx_select, state.idx_page));
+        self.refresh_view();
+    }
+}
+
+impl<B: Backend> InteractiveWidget<B> for ITable {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, name: &str, is_active: bool) {
+        self.window = area;
+        self.ensure_view();
+        self.wrap_page();
+
+        let mut title = match self.filter {
+            Some((Some(col), ref text)) => format!("{} /{}={}/", name, self.content[col].header, text),
+            Some((None, ref text))      => format!("{} /{}/", name, text),
+            None                        => name.to_string(),
+        };
+        let count = self.selected_count();
+        let visual = self.visual_range();
+        if let Some(ref edit) = self.editing {
+            match edit.error {
+                Some(ref error) => title.push_str(&format!(" -- EDIT {}: {} --", self.content[edit.col].header, error)),
+                None            => title.push_str(&format!(" -- EDIT {} --", self.content[edit.col].header)),
+            }
+        } else if self.visual.is_some() {
+            title.push_str(&format!(" -- VISUAL {} --", count));
+        } else if count > 0 {
+            title.push_str(&format!(" [{} selected]", count));
+        }
+        let block = Block::default()
+            .title(&title)
+            .borders(Borders::ALL)
+            .border_style(if is_active { Style::default().fg(Color::Red) } else { Style::default().fg(Color::White) })
+            .title_style(Style::default().fg(Color::Yellow));
+        f.render_widget(block, area);
+
+        let constraints: Vec<_> = self.content.iter()
+            .map(|c| Constraint::Length(c.width as u16 + 1))
+            .collect();
+        let inner = block.inner(area);
+        let body = Rect { height: inner.height.saturating_sub(self.footer_height()), ..inner };
+        let tablerows = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(constraints.clone())
+            .split(body);
+
+        let idx_end = self.lines().min(self.idx_page+self.get_content_height());
+        let group_style = Style::default().fg(Color::Yellow).modifier(Modifier::BOLD);
+        let cell_cursor = if self.editable() { Some(self.idx_col) } else { None };
+        let now = Instant::now();
+        self.flashes.retain(|_, until| *until > now);
+
+        for (col, column) in self.content.iter().enumerate() {
+            let rowchunk = tablerows[col];
+            let mut rowlist = Vec::with_capacity(idx_end - self.idx_page + 1);
+            let header = match self.sort {
+                Some((c, false)) if c == col => format!("{}▲", column.header),
+                Some((c, true))  if c == col => format!("{}▼", column.header),
+                _                            => column.header.clone(),
+            };
+            rowlist.push(Text::styled(header, column.hstyle));
+            for abs_idx in self.idx_page..idx_end {
+                let line = match self.line(abs_idx) {
+                    Some(line) => line,
+                    None       => break,
+                };
+                let is_select = self.idx_select == abs_idx;
+                let row = match *line {
+                    Line::Row(row) => row,
+                    Line::Group { ref key, count, ref cells } => {
+                        let style = if is_select { group_style.modifier(Modifier::REVERSED) } else { group_style };
+                        let cell = if col == 0 {
+                            let marker = if self.collapsed.contains(key) { "▸" } else { "▾" };
+                            format!("{}{} {} ({})", "  ".repeat(key.len() - 1), marker, key[key.len() - 1], count)
+                        } else {
+                            cells.get(col).cloned().unwrap_or_default()
+                        };
+                        rowlist.push(Text::styled(cell, style));
+                        continue;
+                    },
+                };
+                let cell = self.cell(row, col);
+                let mut style = (column.style)(cell, is_select);
+                if self.is_selected(abs_idx, visual) { style = style.bg(Color::DarkGray); }
+                if let Some(until) = self.flashes.get(&row) {
+                    let phase = until.saturating_duration_since(now).as_millis() / FLASH_PERIOD.as_millis();
+                    if phase % 2 == 0 { style = style.fg(Color::White).bg(Color::Red); }
+                }
+                match self.editing {
+                    Some(ref edit) if edit.row == row && edit.col == col => {
+                        let bg = if edit.error.is_some() { Color::Red } else { Color::Yellow };
+                        rowlist.push(Text::styled(format!("{}_", edit.text), Style::default().fg(Color::Black).bg(bg)));
+                        continue;
+                    },
+                    _ if is_select && cell_cursor == Some(col) => style = style.modifier(Modifier::REVERSED),
+                    _ => {  },
+                }
+                if column.sparkline {
+                    let values: Vec<f64> = cell.split_whitespace().filter_map(|v| v.parse().ok()).collect();
+                    let values = &values[values.len().saturating_sub(column.width)..];
+                    rowlist.push(Text::styled(sparkline(values), style));
+                } else {
+                    rowlist.push(Text::styled(cell, style));
+                }
+            }
+
+            f.render_widget(List::new(rowlist.into_iter()), rowchunk);
+        }
+
+        if self.footer_height() > 0 && inner.height > self.footer_height() {
+            let footer = Rect { y: inner.y + inner.height - 1, height: 1, ..inner };
+            let footer_cells = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(constraints)
+                .split(footer);
+            for (col, chunk) in footer_cells.iter().enumerate().take(self.content.len()) {
+                let cell = match self.footer.get(col) {
+                    Some(cell) if !cell.is_empty() => cell.as_str(),
+                    _ if col == 0                  => "total",
+                    _                              => "",
+                };
+                f.render_widget(
+                    Paragraph::new([Text::styled(cell, group_style.modifier(Modifier::REVERSED))].iter()),
+                    *chunk
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,389 @@
+use crate::app::{Interactive, InteractiveWidget};
+use crate::keymap::{self, Binding, Key, Section};
+use crate::Event;
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tui::{
+    backend::Backend,
+    terminal::Frame,
+
+    style::{Color, Modifier, Style},
+    widgets::*,
+    layout::*,
+};
+
+pub struct TreeNode {
+    pub label    : String,
+    pub style    : Style,
+    pub children : Vec<TreeNode>,
+    pub expanded : bool,
+    pub lazy     : bool, // children come from the loader on first expand
+}
+
+impl TreeNode {
+    pub fn new<S: ToString>(label: S) -> Self {
+        Self {
+            label    : label.to_string(),
+            style    : Style::default().fg(Color::White),
+            children : vec![],
+            expanded : false,
+            lazy     : false,
+        }
+    }
+
+    pub fn style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn child(mut self, child: TreeNode) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    pub fn expanded(mut self) -> Self {
+        self.expanded = true;
+        self
+    }
+
+    pub fn lazy(mut self) -> Self {
+        self.lazy = true;
+        self
+    }
+
+    fn is_leaf(&self) -> bool {
+        !self.lazy && self.children.is_empty()
+    }
+}
+
+/// gets labels from root to the expanding node, returns its children
+type Loader = Box<dyn Fn(&[&str]) -> Vec<TreeNode>>;
+
+/// collapsible tree, every shown line is addressed by its index path from the roots.
+#[derive(Default)]
+pub struct ITree {
+    roots      : Vec<TreeNode>,
+    loader     : Option<Loader>,
+    visible    : Vec<Vec<usize>>,
+
+    idx_page   : usize, // index into visible
+    idx_select : usize, // index into visible
+
+    window     : Rect,
+}
+
+/// expanded nodes are saved by their label path.
+#[derive(Default, Serialize, Deserialize)]
+struct ITreeState {
+    idx_page   : usize,
+    idx_select : usize,
+    expanded   : Vec<Vec<String>>,
+}
+
+impl ITree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_root(&mut self, node: TreeNode) {
+        self.roots.push(node);
+        self.refresh_visible();
+    }
+
+    pub fn clear(&mut self) {
+        self.roots.clear();
+        self.refresh_visible();
+    }
+
+    pub fn set_loader<F>(&mut self, f: F)
+        where F: Fn(&[&str]) -> Vec<TreeNode> + 'static
+    {
+        self.loader = Some(Box::new(f));
+    }
+
+    fn node(&self, path: &[usize]) -> &TreeNode {
+        let mut node = &self.roots[path[0]];
+        for idx in path[1..].iter() { node = &node.children[*idx]; }
+        node
+    }
+
+    fn node_mut(&mut self, path: &[usize]) -> &mut TreeNode {
+        let mut node = &mut self.roots[path[0]];
+        for idx in path[1..].iter() { node = &mut node.children[*idx]; }
+        node
+    }
+
+    fn labels(&self, path: &[usize]) -> Vec<String> {
+        (1..=path.len()).map(|len| self.node(&path[..len]).label.clone()).collect()
+    }
+
+    fn load(&mut self, path: &[usize]) {
+        if !self.node(path).lazy { return; }
+        let labels = self.labels(path);
+        let labels: Vec<&str> = labels.iter().map(|s| s.as_str()).collect();
+        let children = match self.loader {
+            Some(ref loader) => loader(&labels),
+            None             => { warn!("no loader for lazy node {:?}", labels); vec![] },
+        };
+        let node = self.node_mut(path);
+        node.children = children;
+        node.lazy = false;
+    }
+
+    fn expand_path(&mut self, path: &[usize]) {
+        self.load(path);
+        let node = self.node_mut(path);
+        if !node.children.is_empty() { node.expanded = true; }
+    }
+
+    pub fn expand(&mut self) {
+        if let Some(path) = self.visible.get(self.idx_select).cloned() {
+            self.expand_path(&path);
+            self.refresh_visible();
+        }
+    }
+
+    /// collapse selected node, or jump to its parent if there is nothing to collapse
+    pub fn collapse(&mut self) {
+        if let Some(path) = self.visible.get(self.idx_select).cloned() {
+            let node = self.node_mut(&path);
+            if node.expanded {
+                node.expanded = false;
+            } else if path.len() > 1 {
+                let parent = &path[..path.len()-1];
+                if let Some(idx) = self.visible.iter().position(|p| p.as_slice() == parent) {
+                    self.idx_select = idx;
+                }
+            }
+            self.refresh_visible();
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        let expanded = match self.visible.get(self.idx_select) {
+            Some(path) => self.node(path).expanded,
+            None       => return,
+        };
+        if expanded { self.collapse(); } else { self.expand(); }
+    }
+
+    fn refresh_visible(&mut self) {
+        fn walk(nodes: &[TreeNode], path: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+            for (idx, node) in nodes.iter().enumerate() {
+                path.push(idx);
+                out.push(path.clone());
+                if node.expanded { walk(&node.children, path, out); }
+                path.pop();
+            }
+        }
+
+        let selected = self.visible.get(self.idx_select).cloned();
+        let mut visible = vec![];
+        walk(&self.roots, &mut vec![], &mut visible);
+        self.visible = visible;
+
+        if let Some(selected) = selected {
+            if let Some(idx) = self.visible.iter().position(|p| *p == selected) {
+                self.idx_select = idx;
+            }
+        }
+        if self.idx_select >= self.visible.len() {
+            self.idx_select = self.visible.len().saturating_sub(1);
+        }
+        if self.idx_page >= self.visible.len() {
+            self.idx_page = self.visible.len().saturating_sub(1);
+        }
+        self.wrap_page();
+    }
+
+    fn wrap_page(&mut self) {
+        if self.idx_select < self.idx_page {
+            self.idx_page = self.idx_select;
+        }
+
+        let height = self.get_content_height();
+        if height > 0 && self.idx_select >= self.idx_page + height {
+            self.idx_page = self.idx_select + 1 - height;
+        }
+    }
+
+    fn wrap_select(&mut self) {
+        if self.idx_select < self.idx_page {
+            self.idx_select = self.idx_page;
+        }
+
+        let maxreach = self.idx_page + self.get_content_height();
+        if maxreach > 0 && self.idx_select >= maxreach {
+            self.idx_select = maxreach - 1;
+        }
+    }
+
+    fn get_content_height(&self) -> usize {
+        if self.window.height > 2 {
+            (self.window.height - 2) as _
+        } else {
+            0
+        }
+    }
+
+    fn expanded_labels(&self) -> Vec<Vec<String>> {
+        self.visible.iter()
+            .filter(|path| self.node(path).expanded)
+            .map(|path| self.labels(path))
+            .collect()
+    }
+
+    /// follow labels from the roots, loading lazy nodes on the way
+    fn expand_labels(&mut self, labels: &[String]) {
+        let mut path = vec![];
+        for label in labels {
+            let found = {
+                let siblings = if path.is_empty() { &self.roots } else { &self.node(&path).children };
+                siblings.iter().position(|n| n.label == *label)
+            };
+            match found {
+                Some(idx) => { path.push(idx); self.expand_path(&path); },
+                None      => return,
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum TreeKey {
+    Expand,
+    Collapse,
+    Toggle,
+}
+
+const TREE_KEYS: &[Binding<TreeKey>] = &[
+    Binding { keys: &[Key::Char('l'), Key::Right], action: TreeKey::Expand,   help: "expand node" },
+    Binding { keys: &[Key::Char('h'), Key::Left],  action: TreeKey::Collapse, help: "collapse node" },
+    Binding { keys: &[Key::Enter],                 action: TreeKey::Toggle,   help: "expand or collapse node" },
+];
+
+impl Interactive for ITree {
+    fn select_up(&mut self) {
+        if self.idx_select > 0 {
+            self.idx_select -= 1;
+            self.wrap_page();
+        }
+    }
+
+    fn select_down(&mut self) {
+        if self.idx_select + 1 < self.visible.len() {
+            self.idx_select += 1;
+            self.wrap_page();
+        }
+    }
+
+    fn select_first(&mut self) {
+        self.idx_select = 0;
+        self.wrap_page();
+    }
+
+    fn select_last(&mut self) {
+        self.idx_select = self.visible.len().saturating_sub(1);
+        self.wrap_page();
+    }
+
+    fn select_page_up(&mut self) {
+        self.idx_page = self.idx_page.saturating_sub(self.get_content_height());
+        self.wrap_select();
+    }
+
+    fn select_page_down(&mut self) {
+        if self.get_content_height() > 0 && self.visible.len() > 0 {
+            self.idx_page = (self.idx_page + self.get_content_height() - 1).min(self.visible.len() - 1);
+            self.wrap_select();
+        }
+    }
+
+    fn select_on_key(&mut self, event: &Event) -> bool {
+        match keymap::lookup(TREE_KEYS, event) {
+            Some(TreeKey::Expand)   => self.expand(),
+            Some(TreeKey::Collapse) => self.collapse(),
+            Some(TreeKey::Toggle)   => self.toggle(),
+            None                    => return false,
+        }
+        true
+    }
+
+    fn keys(&self) -> Vec<Section> {
+        vec![Section::new("tree", TREE_KEYS)]
+    }
+
+    fn click(&mut self, _xpos: u16, ypos: u16) {
+        if ypos > 0 && ypos + 1 < self.window.height {
+            let idx = self.idx_page + ypos as usize - 1;
+            if idx == self.idx_select {
+                self.toggle();
+            } else if idx < self.visible.len() {
+                self.idx_select = idx;
+            }
+        }
+    }
+
+    fn save_state(&self) -> Option<serde_json::Value> {
+        let state = ITreeState {
+            idx_page   : self.idx_page,
+            idx_select : self.idx_select,
+            expanded   : self.expanded_labels(),
+        };
+        serde_json::to_value(state).ok()
+    }
+
+    fn restore_state(&mut self, state: serde_json::Value) {
+        let state: ITreeState = match serde_json::from_value(state) {
+            Ok(state) => state,
+            Err(e)    => { warn!("ignore bad tree state: {}", e); return; },
+        };
+
+        fn collapse_all(nodes: &mut [TreeNode]) {
+            for node in nodes.iter_mut() {
+                node.expanded = false;
+                collapse_all(&mut node.children);
+            }
+        }
+        collapse_all(&mut self.roots);
+
+        // parents come before children in the saved list
+        for labels in state.expanded.iter() {
+            self.expand_labels(labels);
+        }
+        self.refresh_visible();
+        self.idx_select = state.idx_select.min(self.visible.len().saturating_sub(1));
+        self.idx_page = state.idx_page.min(self.idx_select);
+    }
+}
+
+impl<B: Backend> InteractiveWidget<B> for ITree {
+    fn draw(&mut self, f: &mut Frame<B>, area: Rect, name: &str, is_active: bool) {
+        self.window = area;
+
+        let idx_end = self.visible.len().min(self.idx_page + self.get_content_height());
+        let mut lines = Vec::with_capacity(idx_end - self.idx_page);
+        for (idx, path) in self.visible[self.idx_page..idx_end].iter().enumerate() {
+            let node = self.node(path);
+            let marker = if node.is_leaf() { "  " } else if node.expanded { "▾ " } else { "▸ " };
+            let style = if self.idx_page + idx == self.idx_select {
+                node.style.modifier(Modifier::REVERSED)
+            } else {
+                node.style
+            };
+            lines.push(Text::styled(format!("{}{}{}", "  ".repeat(path.len() - 1), marker, node.label), style));
+        }
+
+        f.render_widget(
+            List::new(lines.into_iter())
+            .block(Block::default()
+                .title(name)
+                .borders(Borders::ALL)
+                .border_style(
+                    if is_active { Style::default().fg(Color::Red) } else { Style::default().fg(Color::White) })
+                .title_style(Style::default().fg(Color::Yellow))),
+            area
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+use tui_simple::alert::{Alert, IAlerts, Rule, RuleConfig};
+use tui_simple::app::Interactive;
+use tui_simple::Event;
+
+fn rule(name: &str, when: &str) -> Result<Rule, String> {
+    Rule::parse(&RuleConfig { name: name.into(), table: "trades".into(), when: when.into() })
+}
+
+#[test]
+fn rules_parse_column_op_and_value() {
+    let parsed = rule("big", "  volume >= 1000 ").unwrap();
+    assert_eq!((parsed.name.as_str(), parsed.table.as_str(), parsed.column.as_str()), ("big", "trades", "volume"));
+
+    // the value is the rest of the line
+    let parsed = rule("note", "status == Partly Filled").unwrap();
+    assert!(parsed.fires(None, "Partly Filled"));
+    assert!(rule("eq", "status = Error").unwrap().fires(None, "Error"));
+
+    assert!(rule("short", "volume >=").is_err());
+    assert!(rule("op", "volume ~ 10").is_err());
+    assert!(rule("nan", "price > cheap").is_err());
+    assert!(rule("cross", "price crosses high").is_err());
+}
+
+#[test]
+fn a_rule_fires_when_it_starts_to_hold() {
+    let error = rule("error", "status == Error").unwrap();
+    assert!(error.fires(None, "Error"));
+    assert!(error.fires(Some("Pending"), "Error"));
+    assert!(!error.fires(Some("Error"), "Error"), "no alert on every update");
+    assert!(!error.fires(Some("Error"), "Pending"));
+
+    let big = rule("big", "volume > 1000").unwrap();
+    assert!(big.fires(Some("900"), "1500"));
+    assert!(!big.fires(Some("1200"), "1500"));
+    assert!(!big.fires(Some("1000"), "1000"));
+    assert!(!big.fires(None, "lots"), "a cell that is no number never holds");
+    assert!(big.fires(Some("lots"), "1001"));
+
+    let gone = rule("gone", "status != Pending").unwrap();
+    assert!(gone.fires(Some("Pending"), "Filled"));
+    assert!(!gone.fires(Some("Filled"), "Cancel"));
+}
+
+#[test]
+fn crosses_fires_both_ways_but_needs_a_number_before() {
+    let level = rule("level", "price crosses 10").unwrap();
+    assert!(level.fires(Some("9.5"), "10.5"));
+    assert!(level.fires(Some("10.5"), "9.5"));
+    assert!(level.fires(Some("9.99"), "10"));
+    assert!(!level.fires(Some("10.5"), "11"));
+    assert!(!level.fires(None, "11"), "a new row crosses nothing");
+    assert!(!level.fires(Some("n/a"), "11"));
+}
+
+fn alerts() -> (IAlerts, Rule, Rule) {
+    let (error, big) = (rule("error", "status == Error").unwrap(), rule("big", "volume > 1000").unwrap());
+    let mut alerts = IAlerts::new();
+    alerts.push(Alert::new(&error, "000001 Error".into()));
+    alerts.push(Alert::new(&big, "000002 1500".into()));
+    alerts.push(Alert::new(&error, "000003 Error".into()));
+    (alerts, error, big)
+}
+
+#[test]
+fn alerts_are_acknowledged_one_by_one_or_all() {
+    let (mut alerts, _, _) = alerts();
+    assert_eq!(alerts.unacked(), 3);
+    assert_eq!(alerts.summary(), Some("3 unacked".to_string()));
+
+    // the newest is selected
+    alerts.select_on_key(&Event::CharKey('a'));
+    alerts.select_on_key(&Event::CharKey('a'));
+    assert_eq!(alerts.unacked(), 2);
+    alerts.select_first();
+    alerts.on_command("ack", &[]).unwrap();
+    assert_eq!(alerts.unacked(), 1);
+
+    assert_eq!(alerts.ack_all(), 1);
+    assert_eq!(alerts.unacked(), 0);
+    assert!(alerts.on_command("ack", &["some"]).is_err());
+}
+
+#[test]
+fn snoozing_a_rule_acknowledges_its_alerts() {
+    let (mut alerts, error, big) = alerts();
+    alerts.snooze(&error.name, Duration::from_secs(60));
+    assert!(alerts.is_snoozed("error"));
+    assert!(!alerts.is_snoozed("big"));
+    assert_eq!(alerts.unacked(), 1);
+
+    alerts.snooze(&big.name, Duration::from_millis(0));
+    assert_eq!(alerts.unacked(), 0);
+    assert!(!alerts.is_snoozed("big"), "a snooze ends");
+}
+
+#[test]
+fn snooze_by_key_takes_the_selected_rule() {
+    let (mut alerts, _, _) = alerts();
+    alerts.select_up();
+    assert!(alerts.select_on_key(&Event::CharKey('s')));
+    assert!(alerts.is_snoozed("big"));
+    assert_eq!(alerts.unacked(), 2);
+
+    assert!(IAlerts::new().on_command("snooze", &[]).is_err(), "nothing selected");
+}
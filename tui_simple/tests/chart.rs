@@ -0,0 +1,64 @@
+use tui_simple::app::Interactive;
+use tui_simple::chart::{sparkline, IChart};
+
+#[test]
+fn sparkline_spans_lowest_to_highest() {
+    assert_eq!(sparkline(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]), "▁▂▃▄▅▆▇█");
+    assert_eq!(sparkline(&[10.0, 0.0]), "█▁");
+    assert_eq!(sparkline(&[]), "");
+}
+
+#[test]
+fn sparkline_of_flat_or_broken_values() {
+    assert_eq!(sparkline(&[3.0, 3.0, 3.0]).chars().count(), 3);
+    let line: Vec<char> = sparkline(&[f64::NAN, 1.0, f64::INFINITY, 2.0]).chars().collect();
+    assert_eq!(line, vec![' ', '▁', ' ', '█']);
+    assert_eq!(sparkline(&[f64::NAN]), " ");
+}
+
+#[test]
+fn candles_group_trades_and_keep_the_partial_one() {
+    let mut chart = IChart::new();
+    for (price, volume) in [(10.0, 1.0), (12.0, 2.0), (9.0, 3.0), (11.0, 4.0), (13.0, 5.0)].iter() {
+        chart.push("AAA", *price, *volume);
+    }
+    let candles = chart.series("AAA").unwrap().candles(2);
+
+    assert_eq!(candles.len(), 3);
+    assert_eq!((candles[0].open, candles[0].high, candles[0].low, candles[0].close), (10.0, 12.0, 10.0, 12.0));
+    assert_eq!((candles[1].open, candles[1].high, candles[1].low, candles[1].close), (9.0, 11.0, 9.0, 11.0));
+    assert_eq!(candles[1].volume, 7.0);
+    assert_eq!((candles[2].open, candles[2].close, candles[2].volume), (13.0, 13.0, 5.0));
+}
+
+#[test]
+fn series_are_kept_per_code() {
+    let mut chart = IChart::new();
+    for i in 0..10 {
+        chart.push("AAA", i as f64, 1.0);
+    }
+    chart.push("BBB", 50.0, 2.5);
+
+    assert_eq!(chart.recent("AAA", 3), &[7.0, 8.0, 9.0]);
+    assert_eq!(chart.recent("BBB", 3), &[50.0]);
+    assert!(chart.recent("CCC", 3).is_empty());
+    assert_eq!(chart.total_volume("AAA"), 10.0);
+    assert_eq!(chart.total_volume("CCC"), 0.0);
+
+    assert!(chart.show("BBB").is_ok());
+    assert!(chart.show("CCC").is_err());
+}
+
+#[test]
+fn commands_check_their_arguments() {
+    let mut chart = IChart::new();
+    chart.push("AAA", 1.0, 1.0);
+
+    assert!(chart.on_command("series", &["AAA"]).is_ok());
+    assert!(chart.on_command("chartmode", &["candle"]).is_ok());
+    assert!(chart.on_command("chartmode", &["pie"]).is_err());
+    assert!(chart.on_command("bars", &["10"]).is_ok());
+    assert!(chart.on_command("bars", &["0"]).is_err());
+    assert!(chart.on_command("bars", &["ten"]).is_err());
+    assert!(chart.on_command("bars", &[]).is_err());
+}
@@ -0,0 +1,70 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use structopt::StructOpt;
+use tui_simple::config::{Config, Opts};
+
+/// the command line as given, after the program name
+fn opts(args: &[&str]) -> Opts {
+    Opts::from_iter_safe(std::iter::once("tui_simple").chain(args.iter().cloned())).unwrap()
+}
+
+fn write(dir: &Path, text: &str) -> String {
+    let path = dir.join("tui_simple.toml");
+    fs::write(&path, text).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+#[test]
+fn flags_win_over_the_file_and_the_file_over_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = write(dir.path(), "tick_rate = 500\nlayout = \"wide\"\nlog_level = \"warn\"\n");
+    let config = Config::merge(opts(&["--config", &file, "--tick-rate", "300", "-L", "error"])).unwrap();
+
+    assert_eq!(config.tick_rate, Duration::from_millis(300));
+    assert_eq!(config.log_level, log::LevelFilter::Error);
+    assert_eq!(config.layout, "wide");
+    assert_eq!(config.log_file, Path::new("output.log"));
+    assert_eq!(config.rows.len(), 2);
+    assert!(config.alerts.is_empty());
+}
+
+#[test]
+fn the_file_fills_in_what_flags_leave_out() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = write(dir.path(), "tick_rate = 500\nsource = \"gen:10\"\n[[rows]]\nheight = 100\n[[rows.widgets]]\nname = \"t\"\nkind = \"trades\"\nwidth = 100\n");
+    let config = Config::merge(opts(&["--config", &file, "--layout", "small"])).unwrap();
+
+    assert_eq!(config.tick_rate, Duration::from_millis(500));
+    assert_eq!(config.source, "gen:10");
+    assert_eq!(config.layout, "small");
+    assert_eq!(config.rows.len(), 1);
+    assert_eq!(config.rows[0].widgets[0].min_width, 16);
+}
+
+#[test]
+fn a_missing_explicit_config_is_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let missing = dir.path().join("none.toml");
+    assert!(Config::merge(opts(&["--config", missing.to_str().unwrap()])).is_err());
+}
+
+#[test]
+fn bad_values_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let file = write(dir.path(), "tick_rate = 50\n");
+    let err = Config::merge(opts(&["--config", &file])).unwrap_err();
+    assert!(err.to_string().contains("too small"), "{}", err);
+
+    let file = write(dir.path(), "log_level = \"loud\"\n");
+    assert!(Config::merge(opts(&["--config", &file, "--tick-rate", "200"])).is_err());
+
+    let file = write(dir.path(), "colour = \"red\"\n");
+    assert!(Config::merge(opts(&["--config", &file])).is_err());
+
+    let file = write(dir.path(), "");
+    assert!(Config::merge(opts(&["--config", &file, "--replay", "x.jsonl", "--speed", "-1"])).is_err());
+    let config = Config::merge(opts(&["--config", &file, "--replay", "x.jsonl", "--speed", "0"])).unwrap();
+    assert_eq!(config.speed, 0.0);
+}
@@ -0,0 +1,104 @@
+use tui_simple::app::Interactive;
+use tui_simple::command::{self, Action, AppCommands};
+use tui_simple::keymap::{lookup, Binding, Key, Section};
+use tui_simple::registry::trades_table;
+use tui_simple::Event;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Act {
+    Down,
+    Type,
+    Quit,
+}
+
+const KEYS: &[Binding<Act>] = &[
+    Binding { keys: &[Key::Char('j'), Key::Down], action: Act::Down, help: "move down" },
+    Binding { keys: &[Key::Ctrl('c'), Key::Esc],  action: Act::Quit, help: "leave" },
+    Binding { keys: &[Key::Text],                 action: Act::Type, help: "type" },
+];
+
+#[test]
+fn keys_match_their_events_only() {
+    assert!(Key::Char('j').matches(&Event::CharKey('j')));
+    assert!(!Key::Char('j').matches(&Event::CharKey('J')));
+    assert!(!Key::Char('c').matches(&Event::CtrlKey('c')));
+    assert!(Key::Ctrl('c').matches(&Event::CtrlKey('c')));
+    assert!(Key::Text.matches(&Event::CharKey('%')));
+    assert!(!Key::Text.matches(&Event::Enter));
+    assert!(Key::Click.matches(&Event::Press(3, 4)));
+    assert!(Key::WheelDown.matches(&Event::ScrollDown(0, 0)));
+    assert!(!Key::WheelDown.matches(&Event::ScrollUp(0, 0)));
+    assert!(!Key::Enter.matches(&Event::Tick));
+}
+
+#[test]
+fn the_first_matching_binding_wins() {
+    assert_eq!(lookup(KEYS, &Event::CharKey('j')), Some(Act::Down));
+    assert_eq!(lookup(KEYS, &Event::Down), Some(Act::Down));
+    assert_eq!(lookup(KEYS, &Event::CharKey('k')), Some(Act::Type));
+    assert_eq!(lookup(KEYS, &Event::CtrlKey('c')), Some(Act::Quit));
+    assert_eq!(lookup(KEYS, &Event::Up), None);
+}
+
+#[test]
+fn help_is_built_from_the_bindings() {
+    let section = Section::new("test", KEYS);
+    assert_eq!(section.title, "test");
+    let lines: Vec<(&str, &str)> = section.keys.iter().map(|k| (k.keys.as_str(), k.help)).collect();
+    assert_eq!(lines, vec![("j Down", "move down"), ("Ctrl-C Esc", "leave"), ("text", "type")]);
+    assert_eq!(Key::Char(' ').label(), "space");
+}
+
+#[test]
+fn widgets_list_the_keys_they_take() {
+    let table = trades_table();
+    let sections = table.keys();
+    assert_eq!(sections.iter().map(|s| s.title.as_str()).collect::<Vec<_>>(), vec!["table", "editing a cell"]);
+    assert!(sections[0].keys.iter().any(|k| k.keys == "V" && k.help.contains("visual")));
+}
+
+#[derive(Default)]
+struct Screen {
+    focused : Option<String>,
+    zoomed  : bool,
+}
+
+impl AppCommands for Screen {
+    fn focus(&mut self, name: &str) -> Result<(), String> {
+        self.focused = Some(name.to_string());
+        Ok(())
+    }
+
+    fn zoom(&mut self, on: bool) -> Result<(), String> {
+        self.zoomed = on;
+        Ok(())
+    }
+}
+
+fn run(name: &str, args: &[&str], screen: &mut Screen) -> Result<(), String> {
+    match command::find(name).expect("known command").action {
+        Action::App(f) => f(screen, args),
+        Action::Widget => panic!("{} goes to the widget", name),
+    }
+}
+
+#[test]
+fn commands_are_found_by_name_and_check_their_arguments() {
+    assert!(command::find("group").is_some());
+    assert!(command::find("grp").is_none());
+    assert!(match command::find("undo").unwrap().action {
+        Action::Widget => true,
+        Action::App(_) => false,
+    });
+
+    let mut screen = Screen::default();
+    run("focus", &["trades"], &mut screen).unwrap();
+    assert_eq!(screen.focused.as_deref(), Some("trades"));
+    assert!(run("focus", &[], &mut screen).is_err());
+
+    run("zoom", &[], &mut screen).unwrap();
+    assert!(screen.zoomed);
+    run("zoom", &["off"], &mut screen).unwrap();
+    assert!(!screen.zoomed);
+    assert!(run("zoom", &["in"], &mut screen).is_err());
+}
@@ -0,0 +1,78 @@
+use std::sync::mpsc;
+use tui::backend::TestBackend;
+use tui::Terminal;
+use tui_simple::app::App;
+use tui_simple::config::FileConfig;
+use tui_simple::record::dump;
+use tui_simple::registry::Registry;
+
+/// alpha is kept longest, bravo folds first
+const COLUMNS: &str = r#"
+[[rows]]
+height = 100
+widgets = [
+    { name = "alpha",   kind = "watch", width = 34, min_width = 30, priority = 3 },
+    { name = "bravo",   kind = "watch", width = 33, min_width = 30, priority = 1 },
+    { name = "charlie", kind = "watch", width = 33, min_width = 30, priority = 2 },
+]
+"#;
+
+const ROWS: &str = r#"
+[[rows]]
+height = 50
+widgets = [{ name = "top", kind = "watch", width = 100, min_height = 6, priority = 1 }]
+[[rows]]
+height = 50
+widgets = [{ name = "bottom", kind = "watch", width = 100, min_height = 6, priority = 2 }]
+"#;
+
+/// the screen lines of `layout` drawn at this size
+fn screen(layout: &str, width: u16, height: u16) -> Vec<String> {
+    let config: FileConfig = toml::from_str(layout).unwrap();
+    let (_logs, logs_rx) = mpsc::channel();
+    let (_updates, updates_rx) = mpsc::channel();
+    let registry = Registry::builtin();
+    let mut app = App::new(logs_rx, updates_rx, "test".into(), &config.rows.unwrap(), &registry).unwrap();
+
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|f| app.draw(f)).unwrap();
+    dump(terminal.backend().buffer()).lines().map(String::from).collect()
+}
+
+fn has_all(line: &str, titles: &[&str]) -> bool {
+    titles.iter().all(|t| line.contains(t))
+}
+
+#[test]
+fn widgets_stand_side_by_side_while_they_fit() {
+    let lines = screen(COLUMNS, 100, 20);
+    assert!(has_all(&lines[0], &["Alpha", "Bravo", "Charlie"]), "{:#?}", lines);
+    assert!(!lines[1].contains("Bravo") && !lines[1].contains("Charlie"), "{:#?}", lines);
+}
+
+#[test]
+fn the_weakest_widget_becomes_a_tab_of_its_neighbour() {
+    // bravo would get 29 columns, it joins charlie rather than alpha
+    let lines = screen(COLUMNS, 90, 20);
+    assert!(has_all(&lines[0], &["Alpha", "Bravo", "Charlie"]), "{:#?}", lines);
+    assert!(lines[1].contains("Bravo") || lines[1].contains("Charlie"), "tabs above the shown one: {:#?}", lines);
+    assert!(!lines[1].contains("Alpha"), "{:#?}", lines);
+}
+
+#[test]
+fn a_narrow_screen_folds_a_row_into_one_place() {
+    let lines = screen(COLUMNS, 40, 20);
+    assert!(has_all(&lines[0], &["Alpha", "Bravo", "Charlie"]), "{:#?}", lines);
+    assert!(lines[1].contains("Alpha") || lines[1].contains("Bravo") || lines[1].contains("Charlie"), "{:#?}", lines);
+}
+
+#[test]
+fn a_low_screen_folds_the_weaker_row_into_the_other() {
+    let lines = screen(ROWS, 40, 20);
+    assert!(lines[0].contains("Top") && !lines[0].contains("Bottom"), "{:#?}", lines);
+    assert!(lines.iter().any(|l| l.contains("Bottom")), "{:#?}", lines);
+
+    let lines = screen(ROWS, 40, 10);
+    assert!(has_all(&lines[0], &["Top", "Bottom"]), "{:#?}", lines);
+    assert!(lines[1..].iter().all(|l| !has_all(l, &["Top", "Bottom"])), "{:#?}", lines);
+}
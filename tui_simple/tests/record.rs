@@ -0,0 +1,114 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc;
+use tui::backend::TestBackend;
+use tui::Terminal;
+use tui_simple::app::App;
+use tui_simple::config::{FileConfig, RowConfig};
+use tui_simple::record::{dump, Entry, Recorder, Replay, Start};
+use tui_simple::registry::Registry;
+use tui_simple::remote::RemoteCommand;
+use tui_simple::session::Session;
+use tui_simple::source::Update;
+use tui_simple::Event;
+
+fn rows() -> Vec<RowConfig> {
+    let config: FileConfig = toml::from_str("[[rows]]\nheight = 100\n[[rows.widgets]]\nname = \"trades\"\nkind = \"trades\"\nwidth = 100\n").unwrap();
+    config.rows.unwrap()
+}
+
+fn start() -> Start {
+    Start { layout: "test".into(), rows: rows(), alerts: vec![], session: Session::default() }
+}
+
+fn trade(code: &str, price: &str) -> Update {
+    Update { table: "trades".into(), row: vec![code.into(), "SSE".into(), price.into(), "100".into(), "Buy".into(), "Pending".into()] }
+}
+
+/// the screen without the status bar, its clock moves on between runs
+fn body(terminal: &Terminal<TestBackend>) -> String {
+    let screen = dump(terminal.backend().buffer());
+    let lines: Vec<&str> = screen.lines().collect();
+    lines[..lines.len() - 1].join("\n")
+}
+
+/// a short session with the recorder on, returns the last screen
+fn record(path: &Path) -> String {
+    let (_logs, logs_rx) = mpsc::channel();
+    let (updates, updates_rx) = mpsc::channel();
+    let registry = Registry::builtin();
+    let mut app = App::new(logs_rx, updates_rx, "test".into(), &rows(), &registry).unwrap();
+    app.set_recorder(Recorder::create(path, start()).unwrap());
+
+    let mut terminal = Terminal::new(TestBackend::new(60, 12)).unwrap();
+    terminal.draw(|f| app.draw(f)).unwrap();
+    updates.send(trade("000001", "10.00")).unwrap();
+    updates.send(trade("000002", "20.00")).unwrap();
+    app.on_event(Event::Tick);
+    terminal.draw(|f| app.draw(f)).unwrap();
+    app.on_event(Event::CharKey(':'));
+    for c in "group direction".chars() {
+        app.on_event(Event::CharKey(c));
+    }
+    app.on_event(Event::Enter);
+    terminal.draw(|f| app.draw(f)).unwrap();
+    body(&terminal)
+}
+
+fn replay(path: &Path) -> String {
+    let replay = Replay::load(path).unwrap();
+    let (_logs, logs_rx) = mpsc::channel();
+    let (updates, updates_rx) = mpsc::channel();
+    let registry = Registry::builtin();
+    let mut app = App::new(logs_rx, updates_rx, replay.start.layout.clone(), &replay.start.rows, &registry).unwrap();
+    app.restore_session(&replay.start.session);
+
+    let (width, height) = replay.size().unwrap();
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    replay
+        .run(&mut terminal, &mut app, updates, 0.0, |_| true, |terminal, width, height| {
+            *terminal = Terminal::new(TestBackend::new(width, height))?;
+            Ok(())
+        })
+        .unwrap();
+    body(&terminal)
+}
+
+#[test]
+fn a_replay_ends_on_the_recorded_screen() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.jsonl");
+    let recorded = record(&path);
+    assert!(recorded.contains("000002"), "{}", recorded);
+
+    assert_eq!(Replay::load(&path).unwrap().size(), Some((60, 12)));
+    assert_eq!(replay(&path), recorded);
+}
+
+#[test]
+fn a_line_cut_short_ends_the_recording() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.jsonl");
+    let mut recorder = Recorder::create(&path, start()).unwrap();
+    recorder.record(Entry::Resize(80, 24));
+    recorder.record(Entry::Remote(RemoteCommand::Focus { widget: "trades".into() }));
+    drop(recorder);
+    OpenOptions::new().append(true).open(&path).unwrap().write_all(b"{\"at\":12,\"entry\":{\"Resi").unwrap();
+
+    let replay = Replay::load(&path).unwrap();
+    assert_eq!(replay.start.layout, "test");
+    assert_eq!(replay.size(), Some((80, 24)));
+}
+
+#[test]
+fn only_recordings_are_loaded() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(Replay::load(dir.path().join("none.jsonl")).is_err());
+
+    let path = dir.path().join("updates.jsonl");
+    fs::write(&path, "{\"at\":0,\"entry\":{\"Resize\":[80,24]}}\n").unwrap();
+    assert!(Replay::load(&path).is_err());
+    fs::write(&path, "").unwrap();
+    assert!(Replay::load(&path).is_err());
+}
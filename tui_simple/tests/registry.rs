@@ -0,0 +1,87 @@
+use tui::backend::TestBackend;
+use tui_simple::app::{IEmpty, InteractiveWidget};
+use tui_simple::registry::{Field, FieldKind, Options, Registry};
+use tui_simple::table::ITable;
+
+type Widget = Box<dyn InteractiveWidget<TestBackend>>;
+
+fn options(text: &str) -> Options {
+    toml::from_str(text).unwrap()
+}
+
+fn error(built: Result<Widget, String>) -> String {
+    match built {
+        Ok(_)  => panic!("built a widget from bad options"),
+        Err(e) => e,
+    }
+}
+
+const GAUGE_SCHEMA: &[Field] = &[Field { name: "max", kind: FieldKind::Integer, required: true, help: "full scale" }];
+
+#[test]
+fn every_builtin_kind_builds() {
+    let registry = Registry::<TestBackend>::builtin();
+    for kind in ["assets", "orders", "trades", "watch", "positions", "chart", "logs", "alerts", "empty"].iter() {
+        assert!(registry.build(kind, &Options::new()).is_ok(), "{}", kind);
+    }
+    assert!(registry.build("table", &options("columns = [\"code\", \"price\"]")).is_ok());
+    assert!(registry.build("chart", &options("bar_size = 10\nzoom = 120")).is_ok());
+}
+
+#[test]
+fn options_are_checked_against_the_schema() {
+    let registry = Registry::<TestBackend>::builtin();
+
+    let e = error(registry.build("tabel", &Options::new()));
+    assert!(e.contains("unknown widget kind tabel") && e.contains("table"), "{}", e);
+    let e = error(registry.build("table", &Options::new()));
+    assert_eq!(e, "table needs option columns");
+    let e = error(registry.build("table", &options("columns = \"code\"")));
+    assert_eq!(e, "table: columns should be list of strings");
+    let e = error(registry.build("table", &options("columns = [\"code\"]\nwidth = 3")));
+    assert!(e.starts_with("table has no option width"), "{}", e);
+    let e = error(registry.build("logs", &options("lines = 3")));
+    assert_eq!(e, "logs has no option lines, it takes []");
+}
+
+#[test]
+fn kinds_check_their_own_values() {
+    let registry = Registry::<TestBackend>::builtin();
+    assert!(registry.build("table", &options("columns = []")).is_err());
+    assert!(registry.build("table", &options("columns = [\"code\"]\nkey = \"id\"")).is_err());
+    assert!(registry.build("chart", &options("bar_size = 0")).is_err());
+    assert!(registry.build("chart", &options("zoom = 2")).is_err());
+}
+
+#[test]
+fn a_table_with_a_key_updates_rows_in_place() {
+    let registry = Registry::<TestBackend>::builtin();
+    let mut widget = registry.build("table", &options("columns = [\"code\", \"price\"]\nkey = \"code\"")).unwrap();
+    let table = widget.as_any_mut().downcast_mut::<ITable>().unwrap();
+    table.upsert_row(0, vec!["000001", "10.00"]);
+    table.upsert_row(0, vec!["000001", "10.50"]);
+    assert_eq!(table.len(), 1);
+}
+
+#[test]
+fn kinds_are_registered_once() {
+    let mut registry = Registry::<TestBackend>::builtin();
+    assert!(registry.register("gauge", "one value against its maximum", GAUGE_SCHEMA, |_| Ok(Box::new(IEmpty) as Widget)).is_ok());
+    assert!(registry.register("gauge", "again", &[], |_| Ok(Box::new(IEmpty) as Widget)).is_err());
+    assert!(registry.register("table", "a table of my own", &[], |_| Ok(Box::new(IEmpty) as Widget)).is_err());
+
+    assert!(registry.build("gauge", &options("max = 100")).is_ok());
+    assert!(registry.build("gauge", &Options::new()).is_err());
+}
+
+#[test]
+fn describe_lists_kinds_and_their_options() {
+    let mut registry = Registry::<TestBackend>::new();
+    registry.register("gauge", "one value against its maximum", GAUGE_SCHEMA, |_| Ok(Box::new(IEmpty) as Widget)).unwrap();
+    registry.register("blank", "nothing", &[], |_| Ok(Box::new(IEmpty) as Widget)).unwrap();
+    assert_eq!(
+        registry.describe(),
+        "blank        nothing\n\
+         gauge        one value against its maximum\n    max            integer, required: full scale\n"
+    );
+}
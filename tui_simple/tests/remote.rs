@@ -0,0 +1,52 @@
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use tui_simple::remote::{RemoteCommand, RemoteServer, Response};
+
+#[test]
+fn bind_refuses_other_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("not-a-socket");
+    fs::write(&file, "keep me").unwrap();
+
+    let e = RemoteServer::bind(&file).err().expect("bound over a file");
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(fs::read_to_string(&file).unwrap(), "keep me");
+}
+
+#[test]
+fn bind_replaces_stale_socket_only() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tui.sock");
+    // the file stays when a listener goes away without cleaning up
+    drop(UnixListener::bind(&path).unwrap());
+
+    let server = RemoteServer::bind(&path).unwrap();
+    let e = RemoteServer::bind(&path).err().expect("bound twice");
+    assert_eq!(e.kind(), io::ErrorKind::AddrInUse);
+    drop(server);
+    assert!(!path.exists());
+}
+
+#[test]
+fn request_is_answered_by_the_app() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tui.sock");
+    let (_server, requests) = RemoteServer::bind(&path).unwrap();
+
+    let mut client = UnixStream::connect(&path).unwrap();
+    client.write_all(b"{\"cmd\": \"focus\", \"widget\": \"trades\"}\nnot json\n").unwrap();
+    let request = requests.recv().unwrap();
+    match request.cmd {
+        RemoteCommand::Focus { ref widget } => assert_eq!(widget, "trades"),
+        ref cmd                             => panic!("unexpected {:?}", cmd),
+    }
+    request.reply.send(Response { ok: true, error: None }).unwrap();
+
+    let mut lines = BufReader::new(client).lines();
+    let ok: Response = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    assert!(ok.ok);
+    let bad: Response = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+    assert!(!bad.ok);
+    assert!(bad.error.unwrap().starts_with("bad request"));
+}
@@ -0,0 +1,66 @@
+use tui::backend::TestBackend;
+use tui::style::Style;
+use tui::Terminal;
+use tui_simple::record::dump;
+use tui_simple::status::StatusBar;
+
+const HINT: &str = "press ? for help";
+
+/// the bar on a line of its own, as text
+fn screen(bar: &mut StatusBar) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(100, 1)).unwrap();
+    terminal
+        .draw(|mut f| {
+            let area = f.size();
+            bar.draw(&mut f, area, vec![("NORMAL".into(), Style::default())], HINT);
+        })
+        .unwrap();
+    dump(terminal.backend().buffer())
+}
+
+#[test]
+fn the_hint_shows_until_there_is_news() {
+    let mut bar = StatusBar::new();
+    let text = screen(&mut bar);
+    assert!(text.starts_with(" NORMAL "), "{}", text);
+    assert!(text.contains(HINT) && text.contains("source waiting"), "{}", text);
+
+    bar.on_log("[12:00:00 INFO tui_simple::app] redraw");
+    assert!(screen(&mut bar).contains(HINT));
+
+    bar.on_log("[12:00:01 INFO notify] exported 3 rows");
+    let text = screen(&mut bar);
+    assert!(text.contains("exported 3 rows") && !text.contains(HINT), "{}", text);
+}
+
+#[test]
+fn errors_are_counted_until_read() {
+    let mut bar = StatusBar::new();
+    bar.on_log("[12:00:00 ERROR tui_simple::source] connection lost");
+    bar.on_log("[12:00:01 WARN tui_simple::source] retry");
+    bar.on_log("[12:00:02 ERROR tui_simple::source] connection lost again");
+    let text = screen(&mut bar);
+    assert!(text.contains("2 errors"), "{}", text);
+    assert!(text.contains("connection lost again"), "the latest line is shown: {}", text);
+
+    bar.read_errors();
+    assert!(!screen(&mut bar).contains("errors"));
+
+    // not a log line as main writes them
+    bar.on_log("ERROR something");
+    assert!(!screen(&mut bar).contains("errors"));
+}
+
+#[test]
+fn alerts_and_the_source_are_on_the_right() {
+    let mut bar = StatusBar::new();
+    bar.set_alerts(3);
+    bar.source_alive();
+    let text = screen(&mut bar);
+    assert!(text.contains("3 alerts") && text.contains("source live"), "{}", text);
+
+    bar.set_alerts(0);
+    bar.source_closed();
+    let text = screen(&mut bar);
+    assert!(!text.contains("alerts") && text.contains("source done"), "{}", text);
+}
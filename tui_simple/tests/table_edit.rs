@@ -0,0 +1,123 @@
+use tui::style::Style;
+use tui_simple::app::Interactive;
+use tui_simple::registry::trades_table;
+use tui_simple::table::{default_highlight, CellType, ITable};
+use tui_simple::Event;
+
+const PRICE: usize = 2;
+const VOLUME: usize = 3;
+const STATUS: usize = 5;
+
+fn trades() -> (ITable, Vec<usize>) {
+    let mut table = trades_table();
+    let ids = ["Pending", "Filled"]
+        .iter()
+        .enumerate()
+        .map(|(i, status)| table.add_row(vec![format!("{:06}", i), "SSE".into(), "10.00".into(), "100".into(), "Buy".into(), status.to_string()]))
+        .collect();
+    (table, ids)
+}
+
+/// edit the cell under the cursor as a user would, from `i` to enter
+fn type_in(table: &mut ITable, text: &str) {
+    assert!(table.select_on_key(&Event::CharKey('i')));
+    table.select_on_key(&Event::CtrlKey('w'));
+    for c in text.chars() {
+        table.select_on_key(&Event::CharKey(c));
+    }
+    table.select_on_key(&Event::Enter);
+}
+
+fn cell(table: &ITable, id: usize, col: usize) -> &str {
+    &table.row(id).unwrap()[col]
+}
+
+#[test]
+fn values_are_checked_by_cell_type() {
+    assert_eq!(CellType::Text.validate("  a b "), Ok("a b".to_string()));
+    assert_eq!(CellType::Integer.validate(" 042 "), Ok("42".to_string()));
+    assert!(CellType::Integer.validate("4.2").is_err());
+    assert_eq!(CellType::Decimal.validate("12.50"), Ok("12.50".to_string()));
+    assert!(CellType::Decimal.validate("NaN").is_err());
+    assert!(CellType::Decimal.validate("inf").is_err());
+    assert!(CellType::Decimal.validate("").is_err());
+
+    let side = CellType::Choice(vec!["Buy".into(), "Sell".into()]);
+    assert_eq!(side.validate("sell"), Ok("Sell".to_string()));
+    assert!(side.validate("hold").is_err());
+}
+
+#[test]
+fn an_edit_is_saved_on_enter_and_undone() {
+    let (mut table, ids) = trades();
+    type_in(&mut table, "12.75");
+    assert_eq!(cell(&table, ids[0], PRICE), "12.75");
+
+    // the next editable column
+    table.select_on_key(&Event::Right);
+    type_in(&mut table, "300");
+    assert_eq!(cell(&table, ids[0], VOLUME), "300");
+
+    assert!(table.select_on_key(&Event::CharKey('u')));
+    assert_eq!(cell(&table, ids[0], VOLUME), "100");
+    let change = table.undo().unwrap();
+    assert_eq!((change.header.as_str(), change.old.as_str(), change.new.as_str()), ("price", "10.00", "12.75"));
+    assert_eq!(cell(&table, ids[0], PRICE), "10.00");
+    assert!(table.undo().is_err());
+}
+
+#[test]
+fn a_bad_value_keeps_the_cell_open() {
+    let (mut table, ids) = trades();
+    type_in(&mut table, "cheap");
+    assert_eq!(cell(&table, ids[0], PRICE), "10.00");
+
+    // still editing, so keys go to the cell and not to the table
+    table.select_on_key(&Event::CharKey('V'));
+    table.select_on_key(&Event::Esc);
+    assert_eq!(cell(&table, ids[0], PRICE), "10.00");
+    assert!(table.undo().is_err());
+
+    // an unchanged value is no change to undo
+    type_in(&mut table, "10.00");
+    assert!(table.undo().is_err());
+}
+
+#[test]
+fn the_change_callback_can_refuse_edits_and_undos() {
+    let (mut table, ids) = trades();
+    table.select_down();
+    type_in(&mut table, "11.00");
+    table.select_on_key(&Event::Esc);
+    assert_eq!(cell(&table, ids[1], PRICE), "10.00", "filled orders can not be amended");
+
+    table.select_up();
+    type_in(&mut table, "11.00");
+    table.set_cell(ids[0], STATUS, "Filled");
+    assert!(table.undo().is_err());
+    assert_eq!(cell(&table, ids[0], PRICE), "11.00");
+
+    // a refused undo stays on the stack
+    table.set_cell(ids[0], STATUS, "Pending");
+    assert!(table.undo().is_ok());
+    assert_eq!(cell(&table, ids[0], PRICE), "10.00");
+}
+
+#[test]
+fn removing_a_row_drops_its_undo() {
+    let (mut table, ids) = trades();
+    type_in(&mut table, "11.00");
+    table.remove_row(ids[0]).unwrap();
+    assert!(table.undo().is_err());
+}
+
+#[test]
+fn only_editable_columns_can_be_edited() {
+    let mut table = ITable::new();
+    table.add_column("code", Style::default(), 6, Vec::<&str>::new(), default_highlight);
+    table.add_row(vec!["000001"]);
+    assert!(table.start_edit().is_err());
+
+    table.set_editable(0, CellType::Integer);
+    assert!(table.start_edit().is_ok());
+}
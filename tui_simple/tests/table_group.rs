@@ -0,0 +1,133 @@
+use serde_json::json;
+use tui_simple::app::Interactive;
+use tui_simple::registry::trades_table;
+use tui_simple::table::ITable;
+use tui_simple::Event;
+
+const CODE: usize = 0;
+const PRICE: usize = 2;
+const VOLUME: usize = 3;
+
+/// trades alternating between two exchanges, SZSE first
+fn trades() -> (ITable, Vec<usize>) {
+    let mut table = trades_table();
+    let ids = (0..6)
+        .map(|i| {
+            let exchange = if i % 2 == 0 { "SZSE" } else { "SSE" };
+            let price = format!("{}.00", 10 + i);
+            table.add_row(vec![format!("{:06}", i), exchange.into(), price, (100 * (i + 1)).to_string(), "Buy".into(), "Pending".into()])
+        })
+        .collect();
+    (table, ids)
+}
+
+/// every shown row in view order
+fn shown(table: &mut ITable) -> Vec<usize> {
+    table.select_all();
+    let rows = table.selection();
+    table.clear_selection();
+    rows
+}
+
+#[test]
+fn rows_nest_under_their_group_in_key_order() {
+    let (mut table, ids) = trades();
+    table.on_command("group", &["exchange"]).unwrap();
+
+    assert_eq!(shown(&mut table), vec![ids[1], ids[3], ids[5], ids[0], ids[2], ids[4]]);
+    // a group line is no row, bulk actions have nothing to act on there
+    assert!(table.selection().is_empty());
+    table.select_down();
+    assert_eq!(table.selection(), vec![ids[1]]);
+
+    table.on_command("nogroup", &[]).unwrap();
+    assert_eq!(shown(&mut table), ids);
+}
+
+#[test]
+fn enter_on_a_group_line_folds_it() {
+    let (mut table, ids) = trades();
+    table.on_command("group", &["exchange"]).unwrap();
+
+    assert!(table.select_on_key(&Event::Enter));
+    table.select_down();
+    assert!(table.selection().is_empty(), "the next line is the SZSE group");
+    table.select_down();
+    assert_eq!(table.selection(), vec![ids[0]]);
+
+    table.select_first();
+    table.select_on_key(&Event::Enter);
+    table.select_down();
+    assert_eq!(table.selection(), vec![ids[1]]);
+}
+
+#[test]
+fn groups_nest_one_column_in_the_other() {
+    let (mut table, ids) = trades();
+    table.set_cell(ids[2], 4, "Sell");
+    table.on_command("group", &["exchange", "direction"]).unwrap();
+
+    // SSE, SSE Buy, 1, 3, 5, SZSE, SZSE Buy, 0, 4, SZSE Sell, 2
+    assert_eq!(shown(&mut table), vec![ids[1], ids[3], ids[5], ids[0], ids[4], ids[2]]);
+    table.select_last();
+    table.select_up();
+    assert!(table.selection().is_empty());
+}
+
+#[test]
+fn aggregates_are_set_by_command() {
+    let (mut table, _) = trades();
+    table.on_command("noagg", &[]).unwrap();
+    assert!(table.footer().is_empty());
+
+    table.on_command("agg", &["code", "count"]).unwrap();
+    table.on_command("agg", &["price", "avg"]).unwrap();
+    table.on_command("agg", &["volume", "sum"]).unwrap();
+    assert_eq!(table.footer()[CODE], "6");
+    assert_eq!(table.footer()[PRICE], "12.50");
+    assert_eq!(table.footer()[VOLUME], "2100");
+
+    // a second aggregate on a column takes the place of the first
+    table.on_command("agg", &["price", "vwap", "volume"]).unwrap();
+    assert_eq!(table.footer()[PRICE], "13.33");
+
+    table.on_command("agg", &["price", "min"]).unwrap();
+    table.on_command("agg", &["volume", "max"]).unwrap();
+    assert_eq!(table.footer()[PRICE], "10");
+    assert_eq!(table.footer()[VOLUME], "600");
+}
+
+#[test]
+fn bad_group_and_aggregate_commands_are_refused() {
+    let (mut table, _) = trades();
+    assert!(table.on_command("group", &[]).is_err());
+    assert!(table.on_command("group", &["market"]).is_err());
+    assert!(table.on_command("agg", &["market", "sum"]).is_err());
+    assert!(table.on_command("agg", &["price", "median"]).is_err());
+    assert!(table.on_command("agg", &["price", "vwap"]).is_err());
+    assert!(table.on_command("agg", &["price", "vwap", "market"]).is_err());
+    assert!(table.on_command("agg", &["price", "sum", "volume"]).is_err());
+}
+
+#[test]
+fn groups_and_aggregates_come_back_with_the_session() {
+    let (mut table, _) = trades();
+    table.on_command("group", &["direction", "exchange"]).unwrap();
+    table.on_command("noagg", &[]).unwrap();
+    table.on_command("agg", &["volume", "sum"]).unwrap();
+    table.on_command("agg", &["price", "vwap", "volume"]).unwrap();
+    let state = table.save_state().unwrap();
+    assert_eq!(state["group_by"], json!(["direction", "exchange"]));
+    assert_eq!(state["aggregates"], json!([["volume", "sum"], ["price", "vwap volume"]]));
+
+    let (mut restored, _) = trades();
+    restored.restore_state(state.clone());
+    assert_eq!(restored.save_state().unwrap()["aggregates"], state["aggregates"]);
+    assert_eq!(restored.save_state().unwrap()["group_by"], state["group_by"]);
+    assert_eq!(restored.footer(), table.footer());
+
+    // sessions from before aggregates were saved keep the ones the table comes with
+    let (mut old, _) = trades();
+    old.restore_state(json!({ "idx_page": 0, "idx_select": 0, "group_by": [] }));
+    assert_eq!(old.footer()[CODE], "6");
+}
@@ -0,0 +1,91 @@
+use tui_simple::app::Interactive;
+use tui_simple::registry::{trades_table, watch_table};
+use tui_simple::table::ITable;
+
+const STATUS: usize = 5;
+
+/// every third trade is filled, the others are pending
+fn trades(rows: usize) -> (ITable, Vec<usize>) {
+    let mut table = trades_table();
+    let ids = (0..rows)
+        .map(|i| {
+            let status = if i % 3 == 0 { "Filled" } else { "Pending" };
+            table.add_row(vec![format!("{:06}", i), "SSE".into(), "10.00".into(), "100".into(), "Buy".into(), status.to_string()])
+        })
+        .collect();
+    (table, ids)
+}
+
+#[test]
+fn without_picks_the_current_row_is_the_selection() {
+    let (mut table, ids) = trades(5);
+    assert_eq!(table.selection(), vec![ids[0]]);
+    table.select_down();
+    table.select_down();
+    assert_eq!(table.selection(), vec![ids[2]]);
+}
+
+#[test]
+fn space_picks_rows_one_by_one() {
+    let (mut table, ids) = trades(5);
+    table.toggle_current();
+    table.toggle_current();
+    table.select_down();
+    table.toggle_current();
+    assert_eq!(table.selection(), vec![ids[0], ids[1], ids[3]]);
+
+    // picking again drops it
+    table.select_up();
+    table.toggle_current();
+    assert_eq!(table.selection(), vec![ids[0], ids[1]]);
+
+    table.clear_selection();
+    assert_eq!(table.selection(), vec![ids[4]]);
+}
+
+#[test]
+fn a_visual_range_follows_the_cursor_and_is_kept_when_ended() {
+    let (mut table, ids) = trades(6);
+    table.select_down();
+    table.toggle_visual();
+    table.select_down();
+    table.select_down();
+    assert_eq!(table.selection(), ids[1..4].to_vec());
+
+    // the range runs either way from its anchor
+    table.select_first();
+    assert_eq!(table.selection(), ids[0..2].to_vec());
+
+    table.select_last();
+    table.toggle_visual();
+    table.select_first();
+    assert_eq!(table.selection(), ids[1..6].to_vec());
+}
+
+#[test]
+fn cancel_only_touches_rows_that_can_be_cancelled() {
+    let (mut table, ids) = trades(6);
+    table.select_all();
+    assert_eq!(table.cancel_selected(), Ok(4));
+
+    let status: Vec<&str> = ids.iter().map(|&id| table.row(id).unwrap()[STATUS].as_str()).collect();
+    assert_eq!(status, vec!["Filled", "Cancel", "Cancel", "Filled", "Cancel", "Cancel"]);
+    assert_eq!(table.selection(), vec![ids[0]], "the selection is done with");
+
+    assert_eq!(table.cancel_selected(), Ok(0));
+    assert!(watch_table().cancel_selected().is_err());
+}
+
+#[test]
+fn remove_takes_the_whole_selection() {
+    let (mut table, ids) = trades(5);
+    table.select_down();
+    table.toggle_visual();
+    table.select_down();
+    assert_eq!(table.remove_selected(), 2);
+
+    assert_eq!(table.len(), 3);
+    assert!(table.row(ids[1]).is_none() && table.row(ids[2]).is_none());
+    table.select_all();
+    assert_eq!(table.selection(), vec![ids[0], ids[3], ids[4]]);
+}
@@ -0,0 +1,38 @@
+use serde_json::json;
+use tui_simple::app::Interactive;
+use tui_simple::registry::watch_table;
+use tui_simple::table::ITable;
+
+fn saved(table: &ITable, key: &str) -> u64 {
+    table.save_state().expect("tables save their state")[key].as_u64().unwrap()
+}
+
+fn fill(table: &mut ITable, rows: usize) -> Vec<usize> {
+    (0..rows).map(|i| table.add_row(vec![format!("{:06}", i), "12.5".into(), "100".into(), "".into()])).collect()
+}
+
+#[test]
+fn restored_positions_wait_for_rows_in_a_plain_table() {
+    let mut table = watch_table();
+    table.restore_state(json!({ "idx_page": 4, "idx_select": 6 }));
+    let ids = fill(&mut table, 10);
+
+    assert_eq!(table.selection(), vec![ids[6]]);
+    assert_eq!(saved(&table, "idx_select"), 6);
+    assert_eq!(saved(&table, "idx_page"), 4);
+
+    // moves after the restore are what gets saved next
+    table.select_down();
+    assert_eq!(saved(&table, "idx_select"), 7);
+}
+
+#[test]
+fn restored_positions_are_kept_until_enough_rows_come() {
+    let mut table = watch_table();
+    table.restore_state(json!({ "idx_page": 4, "idx_select": 6 }));
+    let ids = fill(&mut table, 3);
+
+    assert_eq!(table.selection(), vec![ids[0]]);
+    assert_eq!(saved(&table, "idx_select"), 6);
+    assert_eq!(saved(&table, "idx_page"), 4);
+}
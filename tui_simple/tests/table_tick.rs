@@ -0,0 +1,116 @@
+use tui::style::Style;
+use tui_simple::registry::trades_table;
+use tui_simple::table::{default_highlight, Aggregate, ITable};
+
+const ROWS: usize = 20_000;
+/// a burst of updates, well below the quarter of the shown rows past which the view sorts again
+const UPDATES: usize = 1_000;
+
+/// distinct for every `i` below ROWS, so the sort has no ties
+fn price(i: usize) -> String {
+    let v = i * 7919 % 1_000_003;
+    format!("{}.{:02}", v / 100, v % 100)
+}
+
+/// distinct from every `price` and from each other
+fn new_price(i: usize) -> String {
+    let v = (i * 7919 + 12345) % 1_000_003;
+    format!("{}.{:02}5", v / 100, v % 100)
+}
+
+fn row(i: usize, exchange: &str, price: String) -> Vec<String> {
+    vec![format!("{:07}", i), exchange.into(), price, (100 * (1 + i % 50)).to_string(), "Buy".into(), "Pending".into()]
+}
+
+/// every shown row in view order
+fn shown(table: &mut ITable) -> String {
+    table.select_all();
+    let text = table.selected_text(',');
+    table.clear_selection();
+    text
+}
+
+#[test]
+fn tick_merges_instead_of_sorting() {
+    let mut table = trades_table();
+    let exchange = table.column_index("exchange").unwrap();
+    table.sort_by(table.column_index("price").unwrap(), false);
+    table.set_filter(Some(exchange), "szse");
+    for i in 0..ROWS {
+        table.upsert_row(0, row(i, if i % 2 == 0 { "SZSE" } else { "SSE" }, price(i)));
+    }
+
+    table.selection();
+    let rebuilds = table.rebuilds();
+
+    // new prices, some rows in and out of the filter, some gone
+    let step = ROWS / UPDATES;
+    for i in (0..ROWS).step_by(step) {
+        match i % 3 {
+            0 => { table.upsert_row(0, row(i, "SZSE", new_price(i))); },
+            1 => { table.upsert_row(0, row(i, "SSE", new_price(i))); },
+            _ => assert!(table.remove_by_key(&format!("{:07}", i))),
+        }
+    }
+    table.selection();
+    assert_eq!(table.rebuilds(), rebuilds, "the tick sorted every row again");
+
+    // the same rows in the same order and the same totals as filtering and sorting from scratch
+    let merged = shown(&mut table);
+    let footer = table.footer().to_vec();
+    table.set_filter(Some(exchange), "szse");
+    assert_eq!(merged, shown(&mut table));
+    assert_eq!(footer, table.footer());
+    assert_eq!(table.rebuilds(), rebuilds + 1);
+}
+
+#[test]
+fn footer_follows_changed_rows_without_rebuilding() {
+    let mut table = trades_table();
+    for i in 0..100 {
+        table.upsert_row(0, row(i, "SZSE", "10.00".into()));
+    }
+    let (code, price, volume) = (0, table.column_index("price").unwrap(), table.column_index("volume").unwrap());
+    assert_eq!(table.footer()[code], "100");
+    let rebuilds = table.rebuilds();
+
+    let mut trade = row(7, "SZSE", "20.00".into());
+    trade[volume] = "9900".into();
+    table.upsert_row(0, trade);
+    assert!(table.remove_by_key(&format!("{:07}", 8)));
+    let footer = table.footer().to_vec();
+    assert_eq!(footer[code], "99");
+    assert_eq!(table.rebuilds(), rebuilds);
+
+    table.clear_aggregates();
+    table.set_aggregate(code, Aggregate::Count);
+    table.set_aggregate(price, Aggregate::Vwap { price, volume });
+    table.set_aggregate(volume, Aggregate::Sum(volume));
+    assert_eq!(table.footer(), footer.as_slice());
+}
+
+#[test]
+fn max_is_found_again_when_its_row_goes() {
+    let mut table = ITable::new();
+    table.add_column("code", Style::default(), 6, Vec::<&str>::new(), default_highlight);
+    table.add_column("price", Style::default(), 8, Vec::<&str>::new(), default_highlight);
+    table.set_aggregate(1, Aggregate::Max(1));
+    table.set_aggregate(0, Aggregate::Count);
+    let ids: Vec<usize> = (1..=5).map(|i| table.add_row(vec![i.to_string(), (i * 10).to_string()])).collect();
+    assert_eq!(table.footer(), ["5", "50"]);
+
+    table.remove_row(ids[4]).unwrap();
+    assert_eq!(table.footer(), ["4", "40"]);
+    table.set_cell(ids[0], 1, "NaN");
+    table.set_cell(ids[3], 1, "15");
+    assert_eq!(table.footer(), ["4", "30"]);
+}
+
+#[test]
+fn remove_missing_row_is_an_error() {
+    let mut table = trades_table();
+    let id = table.add_row(row(1, "SZSE", price(1)));
+    assert!(table.remove_row(id).is_ok());
+    assert!(table.remove_row(id).is_err());
+    assert!(table.remove_row(id + 100).is_err());
+}
@@ -0,0 +1,124 @@
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::rc::Rc;
+use tui_simple::app::Interactive;
+use tui_simple::tree::{ITree, TreeNode};
+
+/// `a` with two leaves, then `b` whose children come from the loader; every load is logged
+fn tree() -> (ITree, Rc<RefCell<Vec<Vec<String>>>>) {
+    let loads = Rc::new(RefCell::new(vec![]));
+    let mut tree = ITree::new();
+    tree.add_root(TreeNode::new("a").child(TreeNode::new("a1")).child(TreeNode::new("a2")));
+    tree.add_root(TreeNode::new("b").lazy());
+
+    let log = loads.clone();
+    tree.set_loader(move |labels| {
+        log.borrow_mut().push(labels.iter().map(|s| s.to_string()).collect());
+        match labels {
+            ["b"]       => vec![TreeNode::new("b1").lazy(), TreeNode::new("b2")],
+            ["b", "b1"] => vec![TreeNode::new("b11")],
+            _           => vec![],
+        }
+    });
+    (tree, loads)
+}
+
+fn selected(tree: &ITree) -> u64 {
+    tree.save_state().unwrap()["idx_select"].as_u64().unwrap()
+}
+
+fn expanded(tree: &ITree) -> Value {
+    tree.save_state().unwrap()["expanded"].clone()
+}
+
+#[test]
+fn expand_shows_children_and_collapse_hides_them() {
+    let (mut tree, _) = tree();
+    tree.select_last();
+    assert_eq!(selected(&tree), 1);
+
+    tree.select_first();
+    tree.expand();
+    assert_eq!(expanded(&tree), json!([["a"]]));
+    tree.select_last();
+    assert_eq!(selected(&tree), 3, "a, a1, a2, b");
+
+    tree.collapse();
+    tree.select_first();
+    tree.collapse();
+    assert_eq!(expanded(&tree), json!([]));
+    tree.select_last();
+    assert_eq!(selected(&tree), 1);
+}
+
+#[test]
+fn collapse_on_a_leaf_goes_to_its_parent() {
+    let (mut tree, _) = tree();
+    tree.expand();
+    tree.select_down();
+    tree.select_down();
+    assert_eq!(selected(&tree), 2);
+
+    tree.collapse();
+    assert_eq!(selected(&tree), 0);
+    assert_eq!(expanded(&tree), json!([["a"]]), "only moved, nothing collapsed");
+}
+
+#[test]
+fn the_selection_stays_on_its_node_when_lines_above_change() {
+    let (mut tree, _) = tree();
+    tree.select_last();
+    tree.select_first();
+    tree.toggle();
+    assert_eq!(expanded(&tree), json!([["a"]]));
+
+    tree.select_last();
+    tree.select_up();
+    tree.select_up();
+    tree.select_up();
+    tree.toggle();
+    assert_eq!(expanded(&tree), json!([]));
+    tree.select_down();
+    assert_eq!(selected(&tree), 1, "b is right below a again");
+}
+
+#[test]
+fn lazy_nodes_load_once_on_first_expand() {
+    let (mut tree, loads) = tree();
+    tree.select_last();
+    tree.toggle();
+    assert_eq!(*loads.borrow(), vec![vec!["b".to_string()]]);
+    assert_eq!(expanded(&tree), json!([["b"]]));
+
+    tree.toggle();
+    tree.toggle();
+    assert_eq!(loads.borrow().len(), 1);
+
+    // b2 is a plain leaf, expanding it does nothing
+    tree.select_last();
+    tree.expand();
+    assert_eq!(loads.borrow().len(), 1);
+    assert_eq!(expanded(&tree), json!([["b"]]));
+}
+
+#[test]
+fn a_lazy_node_without_children_stays_collapsed() {
+    let mut tree = ITree::new();
+    tree.add_root(TreeNode::new("empty").lazy());
+    tree.set_loader(|_| vec![]);
+    tree.expand();
+    assert_eq!(expanded(&tree), json!([]));
+}
+
+#[test]
+fn restore_expands_saved_paths_through_the_loader() {
+    let (mut tree, loads) = tree();
+    tree.restore_state(json!({ "idx_page": 0, "idx_select": 3, "expanded": [["b"], ["b", "b1"], ["gone"]] }));
+
+    assert_eq!(loads.borrow().len(), 2);
+    assert_eq!(expanded(&tree), json!([["b"], ["b", "b1"]]));
+    assert_eq!(selected(&tree), 3, "a, b, b1, b11");
+
+    tree.select_last();
+    assert_eq!(selected(&tree), 4);
+}